@@ -72,7 +72,21 @@ use super::ocalls::{
 };
 
 #[cfg(feature = "SGX_MODE_HW")]
-use super::{hex, report::EndorsedAttestationReport};
+use super::dcap::{
+    check_collateral_expiry, check_collateral_window, check_dcap_status, check_quote_size,
+    DcapCollateral,
+};
+use super::dcap::{resolve_collateral, CollateralStore, DcapError, DcapQuote};
+#[cfg(feature = "SGX_MODE_HW")]
+use super::report::SgxQuoteStatus;
+
+use super::policy::AttestationPolicy;
+
+#[cfg(feature = "SGX_MODE_HW")]
+use super::{
+    hex,
+    report::{challenge_report_data, expected_report_data, EndorsedAttestationReport},
+};
 
 #[cfg(feature = "SGX_MODE_HW")]
 pub const DEV_HOSTNAME: &str = "api.trustedservices.intel.com";
@@ -315,20 +329,63 @@ pub fn get_mr_enclave() -> [u8; 32] {
 }
 
 #[cfg(not(feature = "SGX_MODE_HW"))]
-pub fn verify_quote_ecdsa(
+pub fn verify_quote_ecdsa_with_policy(
     _vec_quote: &[u8],
     _vec_coll: &[u8],
-    _time_s: i64,
-) -> Result<(sgx_report_body_t, sgx_ql_qv_result_t), sgx_status_t> {
-    Err(sgx_status_t::SGX_ERROR_NO_DEVICE)
+    _time_s: Option<i64>,
+    _report_time_s: Option<i64>,
+    _policy: &AttestationPolicy,
+) -> Result<(sgx_report_body_t, sgx_ql_qv_result_t), DcapError> {
+    Err(DcapError::VerificationFailed(
+        sgx_status_t::SGX_ERROR_NO_DEVICE,
+    ))
 }
 
-#[cfg(feature = "SGX_MODE_HW")]
+/// Verify the quote against the collateral at `time_s` (seconds since epoch). Without a trusted
+/// time the library goes by the host's clock, and the collateral can't be checked for expiry
 pub fn verify_quote_ecdsa(
     vec_quote: &[u8],
     vec_coll: &[u8],
-    time_s: i64,
-) -> Result<(sgx_report_body_t, sgx_ql_qv_result_t), sgx_status_t> {
+    time_s: Option<i64>,
+) -> Result<(sgx_report_body_t, sgx_ql_qv_result_t), DcapError> {
+    verify_quote_ecdsa_with_policy(
+        vec_quote,
+        vec_coll,
+        time_s,
+        None,
+        &AttestationPolicy::default(),
+    )
+}
+
+/// Verify the quote against collateral from `store` rather than collateral supplied along with
+/// it, so that verification works offline
+pub fn verify_quote_ecdsa_with_store(
+    vec_quote: &[u8],
+    store: &dyn CollateralStore,
+    time_s: Option<i64>,
+) -> Result<(sgx_report_body_t, sgx_ql_qv_result_t), DcapError> {
+    let collateral = DcapQuote::parse_from(vec_quote)
+        .and_then(|quote| resolve_collateral(&quote, store))
+        .map_err(|e| {
+            trace!("Failed to resolve the collateral of the quote: {:?}", e);
+            e
+        })?;
+
+    verify_quote_ecdsa(vec_quote, &collateral.to_bytes(), time_s)
+}
+
+/// Verify the quote against the collateral. Quote verification results other than
+/// the policy's `dcap_accepted_statuses` are only accepted on the platforms it tolerates them on.
+/// `report_time_s` is when the quote was produced, if known, which the policy may require to be
+/// within the validity of the collateral
+#[cfg(feature = "SGX_MODE_HW")]
+pub fn verify_quote_ecdsa_with_policy(
+    vec_quote: &[u8],
+    vec_coll: &[u8],
+    time_s: Option<i64>,
+    report_time_s: Option<i64>,
+    policy: &AttestationPolicy,
+) -> Result<(sgx_report_body_t, sgx_ql_qv_result_t), DcapError> {
     //
     // use sgx_types::sgx_ql_qv_supplemental_t;
 
@@ -340,6 +397,30 @@ pub fn verify_quote_ecdsa(
     let mut qv_result: sgx_ql_qv_result_t = sgx_ql_qv_result_t::default();
     let mut rt: sgx_status_t = sgx_status_t::default();
 
+    if let Err(e) = check_quote_size(vec_quote, policy) {
+        trace!("Quote size is not accepted: {:?}", e);
+        return Err(e);
+    }
+
+    if let Err(e) = DcapQuote::parse_from(vec_quote).and_then(|quote| {
+        quote.verify_isv_signature()?;
+        quote.verify_qe_identity()?;
+        quote.verify_pck_chain()
+    }) {
+        trace!("Quote structure is invalid: {:?}", e);
+        return Err(e);
+    }
+
+    // fail closed on stale collateral, whatever the library makes of it
+    if let Some(time_s) = time_s {
+        if let Err(e) = DcapCollateral::parse_from(vec_coll)
+            .and_then(|collateral| check_collateral_expiry(time_s, &collateral))
+        {
+            trace!("Collateral is expired: {:?}", e);
+            return Err(e);
+        }
+    }
+
     let mut ti: sgx_target_info_t = sgx_target_info_t::default();
     unsafe { sgx_self_target(&mut ti) };
 
@@ -351,7 +432,8 @@ pub fn verify_quote_ecdsa(
             vec_coll.as_ptr(),
             vec_coll.len() as u32,
             &ti,
-            time_s,
+            // the library takes 0 for the current time
+            time_s.unwrap_or(0),
             &mut qe_report,
             p_supp.as_mut_ptr(),
             p_supp.len() as u32,
@@ -363,23 +445,30 @@ pub fn verify_quote_ecdsa(
     };
 
     if res != sgx_status_t::SGX_SUCCESS {
-        return Err(res);
+        return Err(DcapError::VerificationFailed(res));
     }
     if rt != sgx_status_t::SGX_SUCCESS {
-        return Err(rt);
+        return Err(DcapError::VerificationFailed(rt));
     }
 
-    match qv_result {
-        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_OK => {}
-        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_SW_HARDENING_NEEDED => {}
-        _ => {
-            trace!("Quote verification result: {}", qv_result);
-            return Err(sgx_status_t::SGX_ERROR_UNEXPECTED);
-        }
-    };
+    let fmspc = DcapCollateral::parse_from(vec_coll)
+        .and_then(|collateral| collateral.tcb_info())
+        .map(|tcb_info| tcb_info.fmspc)
+        .ok();
+    if let Err(e) = check_dcap_status(&SgxQuoteStatus::from(qv_result), fmspc.as_deref(), policy) {
+        trace!("Quote verification result: {}", qv_result);
+        return Err(e);
+    }
+
+    if let Err(e) = DcapCollateral::parse_from(vec_coll)
+        .and_then(|collateral| check_collateral_window(report_time_s, &collateral, policy))
+    {
+        trace!("Quote time is not within the collateral validity: {:?}", e);
+        return Err(e);
+    }
 
     // verify the qve report
-    if time_s != 0 {
+    if let Some(time_s) = time_s {
         exp_time_s = time_s; // insist on our time, if supplied
     }
 
@@ -400,7 +489,9 @@ pub fn verify_quote_ecdsa(
 
     if dcap_ret != sgx_quote3_error_t::SGX_QL_SUCCESS {
         trace!("QVE report verification result: {}", dcap_ret);
-        return Err(sgx_status_t::SGX_ERROR_UNEXPECTED);
+        return Err(DcapError::VerificationFailed(
+            sgx_status_t::SGX_ERROR_UNEXPECTED,
+        ));
     }
 
     trace!("n_supp = {}", n_supp);
@@ -432,12 +523,12 @@ pub fn verify_quote_ecdsa(
 
     if exp_status != 0 {
         trace!("DCAP Collateral expired");
-        return Err(sgx_status_t::SGX_ERROR_UNEXPECTED);
+        return Err(DcapError::CollateralExpired);
     }
 
     if vec_quote.len() < mem::size_of::<sgx_quote_t>() {
         trace!("Quote too small");
-        return Err(sgx_status_t::SGX_ERROR_UNEXPECTED);
+        return Err(DcapError::MalformedQuote);
     }
 
     let my_p_quote = vec_quote.as_ptr() as *const sgx_quote_t;
@@ -493,7 +584,7 @@ pub fn get_quote_ecdsa(pub_k: &[u8; 32]) -> Result<(Vec<u8>, Vec<u8>), sgx_statu
     trace!("ECDSA quote size = {}", quote_size);
 
     let mut report_data: sgx_report_data_t = sgx_report_data_t::default();
-    report_data.d[..32].copy_from_slice(pub_k);
+    report_data.d = expected_report_data(pub_k, None);
 
     let my_report: sgx_report_t = match rsgx_create_report(&qe_target_info, &report_data) {
         Ok(r) => r,
@@ -562,7 +653,7 @@ pub fn get_quote_ecdsa(pub_k: &[u8; 32]) -> Result<(Vec<u8>, Vec<u8>), sgx_statu
     }
 
     // test self
-    match verify_quote_ecdsa(&vec_quote, &vec_coll, 0) {
+    match verify_quote_ecdsa(&vec_quote, &vec_coll, None) {
         Ok(r) => {
             trace!("Self quote verified ok");
             if r.1 != sgx_ql_qv_result_t::SGX_QL_QV_RESULT_OK {
@@ -571,8 +662,11 @@ pub fn get_quote_ecdsa(pub_k: &[u8; 32]) -> Result<(Vec<u8>, Vec<u8>), sgx_statu
             }
         }
         Err(e) => {
-            trace!("Self quote verification failed: {}", e);
-            return Err(e);
+            trace!("Self quote verification failed: {:?}", e);
+            return Err(match e {
+                DcapError::VerificationFailed(status) => status,
+                _ => sgx_status_t::SGX_ERROR_UNEXPECTED,
+            });
         }
     };
 
@@ -642,10 +736,17 @@ pub fn create_attestation_report(
     // Fill ecc256 public key into report_data
     let mut report_data: sgx_report_data_t = sgx_report_data_t::default();
 
-    report_data.d[..32].copy_from_slice(pub_k);
-    if let Some(c) = challenge {
-        report_data.d[32..36].copy_from_slice(c);
-    }
+    let challenge = match challenge {
+        Some(c) => Some(challenge_report_data(c).ok_or_else(|| {
+            error!(
+                "Challenge of {} bytes doesn't fit in the report data",
+                c.len()
+            );
+            sgx_status_t::SGX_ERROR_INVALID_PARAMETER
+        })?),
+        None => None,
+    };
+    report_data.d = expected_report_data(pub_k, challenge.as_ref());
 
     let rep = match rsgx_create_report(&ti, &report_data) {
         Ok(r) => {
@@ -0,0 +1,157 @@
+//! IAS signing CA certificates that are loaded at runtime.
+//!
+//! The report signing certs chain to the IAS root CA that is pinned in the enclave. When Intel
+//! rotates the certs between the root and the signing cert, the new ones can be supplied at
+//! runtime instead of waiting for an enclave release. They are only accepted if they are issued
+//! by the pinned root.
+
+use log::*;
+
+use super::cert::get_ias_auth_config;
+use super::report::{AttestationReport, EndorsedAttestationReport, Error};
+
+/// DER encoding of the sha256WithRSAEncryption OID, the algorithm the IAS root CA signs with
+const SHA256_WITH_RSA_OID: &[u8] = &[
+    0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b,
+];
+
+/// Splits the DER element at the start of `bytes` into the whole element, its contents, and the
+/// bytes that follow it
+pub(crate) fn split_der_element(bytes: &[u8]) -> Result<(&[u8], &[u8], &[u8]), Error> {
+    if bytes.len() < 2 {
+        return Err(Error::ReportParseError);
+    }
+
+    let (len, header_len) = if bytes[1] < 0x80 {
+        (bytes[1] as usize, 2)
+    } else {
+        let len_bytes = (bytes[1] & 0x7f) as usize;
+        if len_bytes == 0 || len_bytes > 4 || bytes.len() < 2 + len_bytes {
+            return Err(Error::ReportParseError);
+        }
+        let len = bytes[2..2 + len_bytes]
+            .iter()
+            .fold(0usize, |len, b| (len << 8) | *b as usize);
+        (len, 2 + len_bytes)
+    };
+
+    if bytes.len() - header_len < len {
+        return Err(Error::ReportParseError);
+    }
+
+    let end = header_len + len;
+    Ok((&bytes[..end], &bytes[header_len..end], &bytes[end..]))
+}
+
+/// Checks that `cert_der` is signed by the key of `issuer_der`
+fn verify_issued_by(cert_der: &[u8], issuer_der: &[u8]) -> Result<(), Error> {
+    let (_, cert, _) = split_der_element(cert_der)?;
+    let (tbs_certificate, _, rest) = split_der_element(cert)?;
+    let (signature_algorithm, _, rest) = split_der_element(rest)?;
+    let (_, signature, _) = split_der_element(rest)?;
+
+    if !signature_algorithm
+        .windows(SHA256_WITH_RSA_OID.len())
+        .any(|window| window == SHA256_WITH_RSA_OID)
+    {
+        warn!("Certificate in the CA bundle isn't signed with sha256WithRSAEncryption");
+        return Err(Error::CertChainInvalid);
+    }
+
+    // the first byte of the BIT STRING is the number of unused bits
+    let signature = signature.get(1..).ok_or(Error::ReportParseError)?;
+
+    let issuer = webpki::EndEntityCert::from(issuer_der).map_err(|e| {
+        error!("Failed to parse the issuer certificate: {:?}", e);
+        Error::ReportParseError
+    })?;
+    issuer
+        .verify_signature(
+            &webpki::RSA_PKCS1_2048_8192_SHA256,
+            tbs_certificate,
+            signature,
+        )
+        .map_err(|e| {
+            warn!(
+                "Certificate in the CA bundle isn't issued by the IAS root CA: {:?}",
+                e
+            );
+            Error::CertChainInvalid
+        })
+}
+
+/// Intermediate certificates for verifying report signing certs, all issued by the IAS root CA
+pub struct SigningCaBundle {
+    certs: Vec<Vec<u8>>,
+}
+
+impl SigningCaBundle {
+    /// Loads DER encoded certificates, rejecting the bundle if any of them isn't signed by the
+    /// pinned IAS root CA
+    pub fn from_der_certs(certs: Vec<Vec<u8>>) -> Result<Self, Error> {
+        let (ias_root_cert, _) = get_ias_auth_config();
+
+        for cert in certs.iter() {
+            verify_issued_by(cert, &ias_root_cert)?;
+        }
+
+        Ok(Self { certs })
+    }
+
+    pub fn certs(&self) -> Vec<&[u8]> {
+        self.certs.iter().map(|cert| cert.as_slice()).collect()
+    }
+}
+
+impl AttestationReport {
+    /// Like `from_endorsed`, but chains the signing cert to the root through the certificates of
+    /// the bundle rather than the ones built into the enclave
+    pub fn from_endorsed_with_bundle(
+        report: &EndorsedAttestationReport,
+        bundle: &SigningCaBundle,
+    ) -> Result<Self, Error> {
+        Self::from_signed_report(
+            &report.report,
+            &report.signature,
+            &report.signing_cert,
+            &bundle.certs(),
+        )
+    }
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use crate::registration::report::tests::{
+        endorsed_report_v4, read_fixture, tls_ra_cert_der_v4,
+    };
+    use crate::registration::report::SgxQuoteStatus;
+
+    use super::*;
+
+    pub fn test_signing_ca_bundle_verifies_report() {
+        let endorsed = endorsed_report_v4();
+
+        // the IAS signing cert is issued by the root, so it can be loaded just like a new CA
+        assert!(SigningCaBundle::from_der_certs(vec![endorsed.signing_cert.clone()]).is_ok());
+
+        let bundle =
+            SigningCaBundle::from_der_certs(vec![read_fixture("ias_root_ca_cert.der")]).unwrap();
+        let report = AttestationReport::from_endorsed_with_bundle(&endorsed, &bundle).unwrap();
+        assert_eq!(report.sgx_quote_status, SgxQuoteStatus::GroupOutOfDate);
+    }
+
+    pub fn test_signing_ca_bundle_rejects_unsigned() {
+        let endorsed = endorsed_report_v4();
+
+        // self-signed by the node, not by the IAS root CA
+        let node_cert = tls_ra_cert_der_v4();
+        assert!(SigningCaBundle::from_der_certs(vec![node_cert]).is_err());
+
+        let mut tampered = endorsed.signing_cert.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 0xff;
+        assert!(SigningCaBundle::from_der_certs(vec![tampered]).is_err());
+
+        assert!(SigningCaBundle::from_der_certs(vec![vec![0x30, 0x82]]).is_err());
+    }
+}
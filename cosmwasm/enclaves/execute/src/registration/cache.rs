@@ -0,0 +1,336 @@
+//! Caching of attestation verification outcomes.
+//!
+//! Verifying a report is expensive, and the same report is often submitted again (e.g. when a
+//! node retries its registration). Failures are cached too, with a shorter TTL, so that a flood
+//! of identical bad reports is rejected cheaply.
+//!
+//! The advisory whitelist decides whether a report is accepted, so callers that change it at
+//! runtime key the cache by the whitelist too (see `key_with_whitelist`), so that an accept made
+//! under an old whitelist isn't served after an advisory is removed from it.
+
+use std::collections::HashMap;
+
+use log::*;
+
+use enclave_crypto::{sha_256, HASH_SIZE};
+use enclave_ffi_types::NodeAuthResult;
+
+struct CacheEntry {
+    result: Result<(), NodeAuthResult>,
+    /// Time (seconds since epoch) after which the entry may not be served
+    expires_at: u64,
+}
+
+/// Counters of the cache's activity since it was created
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries dropped to make room for new ones
+    pub evictions: u64,
+    /// Number of entries currently in the cache, expired or not
+    pub size: usize,
+}
+
+impl CacheStats {
+    /// Share of lookups served from the cache, between 0 and 1
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            return 0.0;
+        }
+
+        self.hits as f64 / lookups as f64
+    }
+}
+
+/// Outcomes of recent verifications, keyed by the hash of the verified report
+pub struct VerificationCache {
+    capacity: usize,
+    /// How long (in seconds) a successful verification is cached for
+    positive_ttl: u64,
+    /// How long (in seconds) a failed verification is cached for
+    negative_ttl: u64,
+    entries: HashMap<[u8; HASH_SIZE], CacheEntry>,
+    stats: CacheStats,
+}
+
+impl VerificationCache {
+    pub fn new(capacity: usize, positive_ttl: u64, negative_ttl: u64) -> Self {
+        Self {
+            capacity,
+            positive_ttl,
+            negative_ttl,
+            entries: HashMap::new(),
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// The cache key of a report - the hash of its bytes as received (e.g. the RA certificate)
+    pub fn key(report: &[u8]) -> [u8; HASH_SIZE] {
+        sha_256(report)
+    }
+
+    /// The cache key of a report verified against the advisory whitelist `whitelist`. Reports
+    /// verified against another whitelist have another key
+    pub fn key_with_whitelist(report: &[u8], whitelist: &[String]) -> [u8; HASH_SIZE] {
+        sha_256(&[Self::key(report), whitelist_digest(whitelist)].concat())
+    }
+
+    /// The cached outcome for `key`, if there is one that hasn't expired by `now`
+    pub fn get(&mut self, key: &[u8; HASH_SIZE], now: u64) -> Option<Result<(), NodeAuthResult>> {
+        let result = match self.entries.get(key) {
+            Some(entry) if now < entry.expires_at => Some(entry.result),
+            Some(_) => {
+                self.entries.remove(key);
+                None
+            }
+            None => None,
+        };
+
+        match result {
+            Some(_) => self.stats.hits += 1,
+            None => self.stats.misses += 1,
+        }
+        result
+    }
+
+    /// Caches the outcome of verifying the report with the given key at `now`
+    pub fn insert(&mut self, key: [u8; HASH_SIZE], result: Result<(), NodeAuthResult>, now: u64) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            self.evict(now);
+        }
+
+        let ttl = match result {
+            Ok(()) => self.positive_ttl,
+            Err(_) => self.negative_ttl,
+        };
+        self.entries.insert(
+            key,
+            CacheEntry {
+                result,
+                expires_at: now.saturating_add(ttl),
+            },
+        );
+    }
+
+    /// Returns the cached outcome for `report` if there is one, and otherwise runs `verify` and
+    /// caches its outcome
+    pub fn verify_cached(
+        &mut self,
+        report: &[u8],
+        now: u64,
+        verify: impl FnOnce() -> Result<(), NodeAuthResult>,
+    ) -> Result<(), NodeAuthResult> {
+        self.verify_cached_by_key(Self::key(report), now, verify)
+    }
+
+    /// Like `verify_cached`, for a report verified against the advisory whitelist `whitelist`
+    pub fn verify_cached_with_whitelist(
+        &mut self,
+        report: &[u8],
+        whitelist: &[String],
+        now: u64,
+        verify: impl FnOnce() -> Result<(), NodeAuthResult>,
+    ) -> Result<(), NodeAuthResult> {
+        self.verify_cached_by_key(Self::key_with_whitelist(report, whitelist), now, verify)
+    }
+
+    fn verify_cached_by_key(
+        &mut self,
+        key: [u8; HASH_SIZE],
+        now: u64,
+        verify: impl FnOnce() -> Result<(), NodeAuthResult>,
+    ) -> Result<(), NodeAuthResult> {
+        if let Some(result) = self.get(&key, now) {
+            trace!("Serving verification result from cache: {:?}", result);
+            return result;
+        }
+
+        let result = verify();
+        self.insert(key, result, now);
+
+        result
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            size: self.entries.len(),
+            ..self.stats
+        }
+    }
+
+    /// Drops all the cached outcomes, e.g. after the policy they were verified against changed.
+    /// The counters of `stats` are kept
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Makes room for a new entry - drops the expired entries, or if there are none, the entry
+    /// that expires the soonest
+    fn evict(&mut self, now: u64) {
+        let size = self.entries.len();
+        self.entries.retain(|_, entry| now < entry.expires_at);
+        self.stats.evictions += (size - self.entries.len()) as u64;
+        if self.entries.len() < self.capacity {
+            return;
+        }
+
+        let soonest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.expires_at)
+            .map(|(key, _)| *key);
+        if let Some(key) = soonest {
+            self.entries.remove(&key);
+            self.stats.evictions += 1;
+        }
+    }
+}
+
+/// Digest of an advisory whitelist, which doesn't depend on the order of the advisories or on
+/// duplicates
+pub fn whitelist_digest(whitelist: &[String]) -> [u8; HASH_SIZE] {
+    let mut advisories: Vec<&str> = whitelist.iter().map(String::as_str).collect();
+    advisories.sort_unstable();
+    advisories.dedup();
+
+    // each ID is prefixed with its length, so that no ID, however malformed, can pass for several
+    let mut encoded = vec![];
+    for advisory in advisories {
+        encoded.extend_from_slice(&(advisory.len() as u32).to_le_bytes());
+        encoded.extend_from_slice(advisory.as_bytes());
+    }
+
+    sha_256(&encoded)
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use std::cell::Cell;
+
+    use crate::registration::policy::AttestationPolicy;
+    use crate::registration::report::tests::mock_attestation_report;
+    use crate::registration::report::AdvisoryIDs;
+
+    use super::*;
+
+    pub fn test_verification_cache_negative_ttl() {
+        let mut cache = VerificationCache::new(10, 3600, 60);
+        let bad_report = b"bad report".to_vec();
+        let verifications = Cell::new(0);
+        let verify = || {
+            verifications.set(verifications.get() + 1);
+            Err(NodeAuthResult::InvalidCert)
+        };
+
+        assert_eq!(
+            cache.verify_cached(&bad_report, 1000, verify),
+            Err(NodeAuthResult::InvalidCert)
+        );
+        assert_eq!(verifications.get(), 1);
+
+        // the same report within the TTL is served from the cache
+        assert_eq!(
+            cache.verify_cached(&bad_report, 1059, verify),
+            Err(NodeAuthResult::InvalidCert)
+        );
+        assert_eq!(verifications.get(), 1);
+
+        // and verified again once it expires
+        assert_eq!(
+            cache.verify_cached(&bad_report, 1060, verify),
+            Err(NodeAuthResult::InvalidCert)
+        );
+        assert_eq!(verifications.get(), 2);
+    }
+
+    pub fn test_verification_cache_positive_ttl() {
+        let mut cache = VerificationCache::new(10, 3600, 60);
+        let report = b"good report".to_vec();
+        let key = VerificationCache::key(&report);
+
+        assert_eq!(cache.verify_cached(&report, 1000, || Ok(())), Ok(()));
+        assert_eq!(cache.get(&key, 1060), Some(Ok(())));
+        assert_eq!(cache.get(&key, 4600), None);
+    }
+
+    pub fn test_verification_cache_whitelist_change() {
+        let mut cache = VerificationCache::new(10, 3600, 60);
+        let mut report = mock_attestation_report();
+        report.advisory_ids = AdvisoryIDs(vec!["INTEL-SA-00334".to_string()]);
+        let report_bytes = b"report with INTEL-SA-00334".to_vec();
+
+        let verifications = Cell::new(0);
+        let mut verify_under = |whitelist: &[&str]| {
+            let whitelist: Vec<String> = whitelist.iter().map(|id| id.to_string()).collect();
+            let policy = AttestationPolicy {
+                whitelisted_advisories: whitelist.clone(),
+                ..Default::default()
+            };
+            cache.verify_cached_with_whitelist(&report_bytes, &whitelist, 1000, || {
+                verifications.set(verifications.get() + 1);
+                report.evaluate(&policy)
+            })
+        };
+
+        assert_eq!(verify_under(&["INTEL-SA-00334", "INTEL-SA-00615"]), Ok(()));
+        assert_eq!(verifications.get(), 1);
+        // the same whitelist in another order is still a hit
+        assert_eq!(verify_under(&["INTEL-SA-00615", "INTEL-SA-00334"]), Ok(()));
+        assert_eq!(verifications.get(), 1);
+
+        // the advisory is no longer whitelisted, so the cached accept mustn't be served
+        assert!(verify_under(&["INTEL-SA-00615"]).is_err());
+        assert_eq!(verifications.get(), 2);
+
+        // IDs aren't told apart by a separator they might contain themselves
+        assert_ne!(
+            whitelist_digest(&["a\nb".to_string()]),
+            whitelist_digest(&["a".to_string(), "b".to_string()])
+        );
+    }
+
+    pub fn test_verification_cache_stats() {
+        let mut cache = VerificationCache::new(2, 3600, 60);
+        let (first, second, third) = (
+            VerificationCache::key(b"first"),
+            VerificationCache::key(b"second"),
+            VerificationCache::key(b"third"),
+        );
+
+        assert_eq!(cache.get(&first, 1000), None);
+        cache.insert(first, Ok(()), 1000);
+        cache.insert(second, Err(NodeAuthResult::InvalidCert), 1000);
+        assert_eq!(cache.get(&first, 1010), Some(Ok(())));
+        assert_eq!(
+            cache.get(&second, 1010),
+            Some(Err(NodeAuthResult::InvalidCert))
+        );
+
+        // full - the failure expires the soonest, so it makes room
+        cache.insert(third, Ok(()), 1020);
+        assert_eq!(cache.get(&second, 1020), None);
+
+        let stats = cache.stats();
+        assert_eq!(
+            stats,
+            CacheStats {
+                hits: 2,
+                misses: 2,
+                evictions: 1,
+                size: 2,
+            }
+        );
+        assert_eq!(stats.hit_rate(), 0.5);
+
+        cache.clear();
+        assert_eq!(cache.get(&first, 1030), None);
+        assert_eq!(cache.stats().size, 0);
+        assert_eq!(cache.stats().misses, 3);
+    }
+}
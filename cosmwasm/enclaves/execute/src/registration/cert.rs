@@ -23,6 +23,10 @@ use enclave_ffi_types::NodeAuthResult;
 use crate::registration::report::AdvisoryIDs;
 
 use super::attestation::get_mr_enclave;
+#[cfg(not(feature = "SGX_MODE_HW"))]
+use super::encoding::decode_base64;
+#[cfg(feature = "SGX_MODE_HW")]
+use super::events::notify_verification;
 #[cfg(feature = "SGX_MODE_HW")]
 use super::report::{AttestationReport, SgxQuoteStatus};
 
@@ -241,11 +245,27 @@ pub fn get_netscape_comment(cert_der: &[u8]) -> Result<Vec<u8>, Error> {
     extract_asn1_value(cert_der, ns_cmt_oid)
 }
 
-#[allow(dead_code)]
+/// The public key of a prime256v1 certificate, i.e. the uncompressed point of its
+/// `subjectPublicKey`
 pub fn get_cert_pubkey(cert_der: &[u8]) -> Result<Vec<u8>, Error> {
-    // Search for Public Key prime256v1 OID
+    // Search for Public Key prime256v1 OID, which the subjectPublicKey BIT STRING follows
     let prime256v1_oid = &[0x06, 0x08, 0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x03, 0x01, 0x07];
-    extract_asn1_value(cert_der, prime256v1_oid)
+    let offset = match cert_der
+        .windows(prime256v1_oid.len())
+        .position(|window| window == prime256v1_oid)
+    {
+        Some(position) => position + prime256v1_oid.len(),
+        None => return Err(Error::GenericError),
+    };
+
+    // BIT STRING tag, length (short form, a point is only 65 bytes) and no unused bits
+    match cert_der.get(offset..offset + 3) {
+        Some(&[0x03, len, 0x00]) if (2..0x80).contains(&len) => cert_der
+            .get(offset + 3..offset + 2 + len as usize)
+            .map(|key| key.to_vec())
+            .ok_or(Error::GenericError),
+        _ => Err(Error::GenericError),
+    }
 }
 
 pub fn get_ias_auth_config() -> (Vec<u8>, rustls::RootCertStore) {
@@ -276,14 +296,14 @@ pub fn verify_ra_cert(
 ) -> Result<Vec<u8>, NodeAuthResult> {
     let payload = get_netscape_comment(cert_der).map_err(|_err| NodeAuthResult::InvalidCert)?;
 
-    let pk = base64::decode(&payload).map_err(|_err| NodeAuthResult::InvalidCert)?;
+    let pk = decode_base64(&payload).map_err(|_err| NodeAuthResult::InvalidCert)?;
 
     Ok(pk)
 }
 
 pub fn verify_ra_report(
     report_mr_signer: &[u8; 32],
-    report_mr_enclave : & [u8;32],
+    report_mr_enclave: &[u8; 32],
     override_verify_type: Option<SigningMethod>,
 ) -> NodeAuthResult {
     let signing_method: SigningMethod = match override_verify_type {
@@ -328,7 +348,6 @@ pub fn verify_ra_report(
     NodeAuthResult::Success
 }
 
-
 /// # Verifies remote attestation cert
 ///
 /// Logic:
@@ -338,6 +357,8 @@ pub fn verify_ra_report(
 /// 4. Extract public key from report body
 /// 5. Verify enclave signature (mr enclave/signer)
 ///
+/// The registered `EventSink` (see `events::set_event_sink`) is notified of the outcome of every
+/// report that could be parsed
 #[cfg(feature = "SGX_MODE_HW")]
 pub fn verify_ra_cert(
     cert_der: &[u8],
@@ -346,17 +367,34 @@ pub fn verify_ra_cert(
 ) -> Result<Vec<u8>, NodeAuthResult> {
     let report = AttestationReport::from_cert(cert_der).map_err(|_| NodeAuthResult::InvalidCert)?;
 
+    let res = verify_report(&report, override_verify_type, check_tcb_version);
+    let result = match &res {
+        Ok(_) => NodeAuthResult::Success,
+        Err(e) => *e,
+    };
+    notify_verification(&report, result);
+
+    res
+}
+
+#[cfg(feature = "SGX_MODE_HW")]
+fn verify_report(
+    report: &AttestationReport,
+    override_verify_type: Option<SigningMethod>,
+    check_tcb_version: bool,
+) -> Result<Vec<u8>, NodeAuthResult> {
     // this is a small hack - override_verify_type is only used when verifying the master certificate
     // and in that case we don't care about checking vulns etc. Master certificate will also have
     // a bad GID in prod, so there's no reason to verify it
     if override_verify_type.is_none() {
-        verify_quote_status(&report, &report.advisory_ids)?;
+        verify_quote_status(report, &report.advisory_ids)?;
     }
 
     let res = verify_ra_report(
         &report.sgx_quote_body.isv_enclave_report.mr_signer,
         &report.sgx_quote_body.isv_enclave_report.mr_enclave,
-        override_verify_type);
+        override_verify_type,
+    );
 
     if res != NodeAuthResult::Success {
         return Err(res);
@@ -364,13 +402,17 @@ pub fn verify_ra_cert(
 
     if check_tcb_version {
         // todo: change this to a parameters or const when we migrate the code to main
-        if report.tcb_eval_data_number < 16 {
+        if report.tcb_eval_data_number.unwrap_or(0) < 16 {
             info!("Got an outdated certificate");
             return Err(NodeAuthResult::GroupOutOfDate);
         }
     }
 
-    let report_public_key = report.sgx_quote_body.isv_enclave_report.report_data[0..32].to_vec();
+    let report_public_key = report
+        .sgx_quote_body
+        .isv_enclave_report
+        .report_data_pubkey()
+        .to_vec();
     Ok(report_public_key)
 }
 
@@ -495,11 +537,9 @@ fn check_advisories(
 
 #[cfg(feature = "test")]
 pub mod tests {
-    use std::io::Read;
-    use std::untrusted::fs::File;
-
     use enclave_ffi_types::NodeAuthResult;
 
+    use crate::registration::report::tests::read_fixture;
     use crate::registration::report::AttestationReport;
 
     use super::verify_ra_cert;
@@ -516,33 +556,17 @@ pub mod tests {
     // }
 
     fn tls_ra_cert_der_sw_config_needed() -> Vec<u8> {
-        let mut cert = vec![];
-        let mut f = File::open(
-            "../execute/src/registration/fixtures/attestation_cert_sw_config_needed.der",
-        )
-        .unwrap();
-        f.read_to_end(&mut cert).unwrap();
-
-        cert
+        read_fixture("attestation_cert_sw_config_needed.der")
     }
 
     #[cfg(feature = "SGX_MODE_HW")]
     fn tls_ra_cert_der_valid() -> Vec<u8> {
-        let mut cert = vec![];
-        let mut f =
-            File::open("../execute/src/registration/fixtures/attestation_cert_hw_v2").unwrap();
-        f.read_to_end(&mut cert).unwrap();
-
-        cert
+        read_fixture("attestation_cert_hw_v2")
     }
 
     #[cfg(not(feature = "SGX_MODE_HW"))]
     fn tls_ra_cert_der_valid() -> Vec<u8> {
-        let mut cert = vec![];
-        let mut f = File::open("../execute/src/registration/fixtures/attestation_cert_sw").unwrap();
-        f.read_to_end(&mut cert).unwrap();
-
-        cert
+        read_fixture("attestation_cert_sw")
     }
 
     #[cfg(not(feature = "SGX_MODE_HW"))]
@@ -104,14 +104,12 @@ pub unsafe extern "C" fn ecall_check_patch_level(
         return NodeAuthResult::BadQuoteStatus;
     }
 
-    if report.tcb_eval_data_number < 16 {
+    let tcb_eval_data_number = report.tcb_eval_data_number.unwrap_or(0);
+    if tcb_eval_data_number < 16 {
         error!("Your current platform is probably not up to date, and may require a BIOS or PSW update. \n \
                 Please see https://docs.scrt.network/secret-network-documentation/infrastructure/setting-up-a-node-validator/hardware-setup/patching-your-node \
                 for more information");
-        println!(
-            "Tried to attest using old data: {}",
-            report.tcb_eval_data_number
-        );
+        println!("Tried to attest using old data: {}", tcb_eval_data_number);
         return NodeAuthResult::GroupOutOfDate;
     }
 
@@ -0,0 +1,136 @@
+//! The outcome of an attestation, in the form contracts consume it.
+//!
+//! Contracts that care whether a node is attested only check which enclave it runs, whether the
+//! verification succeeded, and when. This is that summary, with a fixed size encoding so it's
+//! cheap to keep in contract storage and to return from queries.
+
+use std::convert::TryInto;
+
+use log::*;
+
+use enclave_ffi_types::NodeAuthResult;
+
+use super::policy::AttestationPolicy;
+use super::rejection::as_code;
+use super::report::{AttestationReport, Error};
+
+/// Version of the encoding, the first byte of `ContractAttestation::to_bytes`
+pub const CONTRACT_ATTESTATION_VERSION: u8 = 1;
+
+/// Size of `ContractAttestation::to_bytes` - the version, MRENCLAVE, status code and timestamp
+pub const CONTRACT_ATTESTATION_SIZE: usize = 1 + 32 + 2 + 8;
+
+/// A verified attestation, as stored and queried by contracts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContractAttestation {
+    pub mr_enclave: [u8; 32],
+    /// The stable code of the verification's `NodeAuthResult`, as assigned by the `rejection`
+    /// module - 0 if the report was accepted
+    pub status_code: u16,
+    /// Timestamp of the report, in seconds since epoch
+    pub timestamp: u64,
+}
+
+impl ContractAttestation {
+    pub fn new(report: &AttestationReport, verdict: Result<(), NodeAuthResult>) -> Self {
+        let status = match verdict {
+            Ok(()) => NodeAuthResult::Success,
+            Err(e) => e,
+        };
+
+        Self {
+            mr_enclave: report.sgx_quote_body.isv_enclave_report.mr_enclave,
+            status_code: as_code(&status).code,
+            timestamp: report.timestamp,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.status_code == as_code(&NodeAuthResult::Success).code
+    }
+
+    /// Serialize to the fixed layout contracts store. Integers are little endian
+    pub fn to_bytes(&self) -> [u8; CONTRACT_ATTESTATION_SIZE] {
+        let mut bytes = [0u8; CONTRACT_ATTESTATION_SIZE];
+        bytes[0] = CONTRACT_ATTESTATION_VERSION;
+        bytes[1..33].copy_from_slice(&self.mr_enclave);
+        bytes[33..35].copy_from_slice(&self.status_code.to_le_bytes());
+        bytes[35..].copy_from_slice(&self.timestamp.to_le_bytes());
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != CONTRACT_ATTESTATION_SIZE {
+            warn!(
+                "Contract attestation is {} bytes long rather than {}",
+                bytes.len(),
+                CONTRACT_ATTESTATION_SIZE
+            );
+            return Err(Error::ReportParseError);
+        }
+
+        if bytes[0] != CONTRACT_ATTESTATION_VERSION {
+            warn!("Unsupported contract attestation version {}", bytes[0]);
+            return Err(Error::ReportParseError);
+        }
+
+        Ok(Self {
+            mr_enclave: bytes[1..33].try_into()?,
+            status_code: u16::from_le_bytes(bytes[33..35].try_into()?),
+            timestamp: u64::from_le_bytes(bytes[35..].try_into()?),
+        })
+    }
+}
+
+impl AttestationReport {
+    /// Verifies the report in `cert` against `policy`, and summarizes the outcome for contracts.
+    /// A report the policy rejects is summarized along with the reason, while a report that
+    /// can't be parsed or whose signature is invalid is an error
+    pub fn contract_attestation(
+        cert: &[u8],
+        policy: &AttestationPolicy,
+    ) -> Result<ContractAttestation, Error> {
+        let (report, verdict) = Self::from_cert_with_verdict(cert, policy)?;
+
+        Ok(ContractAttestation::new(&report, verdict))
+    }
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use crate::registration::report::tests::mock_attestation_report;
+
+    use super::*;
+
+    pub fn test_contract_attestation_encoding() {
+        let report = mock_attestation_report();
+        let accepted = ContractAttestation::new(&report, Ok(()));
+        assert!(accepted.is_success());
+
+        let bytes = accepted.to_bytes();
+        assert_eq!(bytes.len(), CONTRACT_ATTESTATION_SIZE);
+        assert_eq!(ContractAttestation::from_bytes(&bytes).unwrap(), accepted);
+
+        // the layout is what contracts rely on, so it must not change
+        let mut expected = vec![CONTRACT_ATTESTATION_VERSION];
+        expected.extend_from_slice(&report.sgx_quote_body.isv_enclave_report.mr_enclave);
+        expected.extend_from_slice(&[0, 0]);
+        expected.extend_from_slice(&[0xf7, 0x2f, 0x43, 0x5e, 0, 0, 0, 0]);
+        assert_eq!(bytes.to_vec(), expected);
+
+        let rejected = ContractAttestation::new(&report, Err(NodeAuthResult::GroupOutOfDate));
+        assert!(!rejected.is_success());
+        assert_eq!(rejected.status_code, 101);
+        assert_eq!(rejected.to_bytes()[33..35], [101, 0]);
+        assert_eq!(
+            ContractAttestation::from_bytes(&rejected.to_bytes()).unwrap(),
+            rejected
+        );
+
+        assert!(ContractAttestation::from_bytes(&bytes[..CONTRACT_ATTESTATION_SIZE - 1]).is_err());
+        let mut unknown_version = bytes;
+        unknown_version[0] = CONTRACT_ATTESTATION_VERSION + 1;
+        assert!(ContractAttestation::from_bytes(&unknown_version).is_err());
+    }
+}
@@ -0,0 +1,1357 @@
+//! Structural parsing of ECDSA (DCAP) quotes and of their collateral.
+//!
+//! The cryptographic verification of a DCAP quote is done by the quote verification library (see
+//! `verify_quote_ecdsa`). The types here let us run additional consistency checks on the quote
+//! itself, before and after handing it off to the library.
+
+use std::convert::TryFrom;
+
+use log::*;
+use ring::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_FIXED, ECDSA_P384_SHA384_FIXED};
+use serde_json::Value;
+use sgx_types::sgx_status_t;
+use uuid::Uuid;
+
+use super::ca_bundle::split_der_element;
+use super::encoding::decode_base64;
+use super::policy::AttestationPolicy;
+use super::report::{
+    take_bytes, AttestationReport, Error, SgxEcdsaQuoteAkType, SgxEnclaveReport, SgxQuoteStatus,
+    TEE_TYPE_SGX,
+};
+use super::temporal::ValidityWindow;
+
+#[derive(Debug, PartialEq)]
+pub enum DcapError {
+    /// The quote is truncated, or the lengths it declares are inconsistent
+    MalformedQuote,
+    /// The quote header declares a version other than 3 or 4
+    UnsupportedQuoteVersion(u16),
+    /// The v4 quote is of a TEE other than SGX, e.g. a TDX TD
+    UnsupportedTeeType(u32),
+    /// The attestation key type in the quote header doesn't match the signature data
+    KeyTypeMismatch,
+    /// The collateral is truncated, or its TCB info can't be parsed
+    MalformedCollateral,
+    /// The quote verification result isn't accepted for this platform
+    StatusNotAccepted(SgxQuoteStatus),
+    /// The collateral was issued for a different platform model
+    FmspcMismatch,
+    /// None of the TCB levels in the collateral match the platform
+    TcbLevelNotSupported,
+    /// The QE report wasn't produced by Intel's Quoting Enclave
+    QeIdentityMismatch,
+    /// The quote is larger than the policy allows
+    QuoteTooLarge { max: usize, got: usize },
+    /// The PCK cert chain in the quote isn't a leaf, an intermediate CA and a root CA, each with
+    /// the basic constraints of its place in the chain
+    InvalidPckChain,
+    /// The collateral store has no collateral for the platform of the quote
+    CollateralNotFound,
+    /// The quote was produced at a time outside of the validity window of the collateral, or at
+    /// an unknown time
+    TimestampOutsideCollateral,
+    /// The signature over the header and the enclave report doesn't verify with the attestation
+    /// key, i.e. they were altered after the quote was produced
+    IsvSignatureInvalid,
+    /// The TCB info or the QE identity of the collateral was due to be updated before the time
+    /// the quote is verified at
+    CollateralExpired,
+    /// The quote verification library rejected the quote, or couldn't be called
+    VerificationFailed(sgx_status_t),
+}
+
+/// Quote verification results that are accepted regardless of the platform, unless the policy
+/// says otherwise
+pub const DCAP_ACCEPTED_STATUSES: &[SgxQuoteStatus] =
+    &[SgxQuoteStatus::OK, SgxQuoteStatus::SwHardeningNeeded];
+
+/// MRSIGNER of Intel's Quoting Enclave
+pub const INTEL_QE_MRSIGNER: [u8; 32] = [
+    0x8c, 0x4f, 0x57, 0x75, 0xd7, 0x96, 0x50, 0x3e, 0x96, 0x13, 0x7f, 0x77, 0xc6, 0x8a, 0x82, 0x9a,
+    0x00, 0x56, 0xac, 0x8d, 0xed, 0x70, 0x14, 0x0b, 0x08, 0x1b, 0x09, 0x44, 0x90, 0xc5, 0x7b, 0xff,
+];
+
+/// Sizes of the ECDSA signature and of the attestation public key for each key type
+fn ecdsa_sizes(ak_type: &SgxEcdsaQuoteAkType) -> (usize, usize) {
+    match ak_type {
+        SgxEcdsaQuoteAkType::P256_256 => (64, 64),
+        SgxEcdsaQuoteAkType::P384_384 => (96, 96),
+    }
+}
+
+/// Certification data type of a PEM encoded PCK cert chain
+const PCK_CERT_CHAIN_TYPE: u16 = 5;
+/// Certification data type of the QE report certification data, which wraps the QE report and
+/// its own certification data in v4 quotes
+const QE_REPORT_CERTIFICATION_DATA_TYPE: u16 = 6;
+
+/// DER encoding of the basicConstraints extension OID
+const BASIC_CONSTRAINTS_OID: &[u8] = &[0x06, 0x03, 0x55, 0x1d, 0x13];
+
+/// DER encoding of the OIDs of the PCE ID (1.2.840.113741.1.13.1.3) and of the FMSPC
+/// (1.2.840.113741.1.13.1.4) in the SGX extension of the PCK cert
+const PCEID_OID: &[u8] = &[
+    0x06, 0x0a, 0x2a, 0x86, 0x48, 0x86, 0xf8, 0x4d, 0x01, 0x0d, 0x01, 0x03,
+];
+const FMSPC_OID: &[u8] = &[
+    0x06, 0x0a, 0x2a, 0x86, 0x48, 0x86, 0xf8, 0x4d, 0x01, 0x0d, 0x01, 0x04,
+];
+
+/// The value of the SGX extension field `oid` in a PCK cert, which is an octet string of `len`
+/// bytes
+fn sgx_extension_field<'a>(cert_der: &'a [u8], oid: &[u8], len: usize) -> Option<&'a [u8]> {
+    let pos = cert_der
+        .windows(oid.len())
+        .position(|window| window == oid)?;
+    let (value, content, _) = split_der_element(&cert_der[pos + oid.len()..]).ok()?;
+
+    if value[0] != 0x04 || content.len() != len {
+        return None;
+    }
+
+    Some(content)
+}
+
+/// The basic constraints of a certificate - whether it's a CA, and how many CAs may follow it
+/// down the chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BasicConstraints {
+    ca: bool,
+    path_len: Option<u64>,
+}
+
+/// The issuer, subject and basic constraints of a DER encoded X.509 certificate. A certificate
+/// without the extension is not a CA
+fn pck_cert_fields(cert_der: &[u8]) -> Result<(&[u8], &[u8], BasicConstraints), Error> {
+    let (_, cert, _) = split_der_element(cert_der)?;
+    let (_, tbs_certificate, _) = split_der_element(cert)?;
+
+    let mut rest = tbs_certificate;
+    if rest.first() == Some(&0xa0) {
+        rest = split_der_element(rest)?.2;
+    }
+    // serial number, signature algorithm
+    for _ in 0..2 {
+        rest = split_der_element(rest)?.2;
+    }
+    let (issuer, _, rest) = split_der_element(rest)?;
+    let (_, _, rest) = split_der_element(rest)?;
+    let (subject, _, mut rest) = split_der_element(rest)?;
+
+    let mut constraints = BasicConstraints {
+        ca: false,
+        path_len: None,
+    };
+
+    // the extensions are the last, explicitly tagged [3], field
+    while !rest.is_empty() {
+        let (element, content, next) = split_der_element(rest)?;
+        rest = next;
+        if element[0] != 0xa3 {
+            continue;
+        }
+
+        let (_, mut extensions, _) = split_der_element(content)?;
+        while !extensions.is_empty() {
+            let (_, extension, next) = split_der_element(extensions)?;
+            extensions = next;
+
+            let (oid, _, mut value) = split_der_element(extension)?;
+            if oid != BASIC_CONSTRAINTS_OID {
+                continue;
+            }
+            // skip the critical flag
+            if value.first() == Some(&0x01) {
+                value = split_der_element(value)?.2;
+            }
+            let (_, value, _) = split_der_element(value)?;
+            let (_, mut fields, _) = split_der_element(value)?;
+
+            if fields.first() == Some(&0x01) {
+                let (_, ca, next) = split_der_element(fields)?;
+                constraints.ca = ca.first().map_or(false, |b| *b != 0);
+                fields = next;
+            }
+            if fields.first() == Some(&0x02) {
+                let (_, path_len, _) = split_der_element(fields)?;
+                if path_len.is_empty() || path_len.len() > 8 {
+                    return Err(Error::ReportParseError);
+                }
+                constraints.path_len = Some(
+                    path_len
+                        .iter()
+                        .fold(0u64, |path_len, b| (path_len << 8) | *b as u64),
+                );
+            }
+        }
+    }
+
+    Ok((issuer, subject, constraints))
+}
+
+/// A version 3 or 4 ECDSA quote of an SGX enclave, as produced by the DCAP quoting enclave
+pub struct DcapQuote {
+    /// Version of the quote structure
+    pub version: u16,
+    /// Attestation key type declared in the quote header
+    pub attestation_key_type: SgxEcdsaQuoteAkType,
+    /// Security version number of the Quoting Enclave
+    pub qe_svn: u16,
+    /// Security version number of the PCE
+    pub pce_svn: u16,
+    /// Vendor ID of the Quoting Enclave
+    pub qe_vendor_id: Uuid,
+    /// User data
+    pub user_data: [u8; 20],
+    /// Report generated by the attesting enclave
+    pub isv_enclave_report: SgxEnclaveReport,
+    /// The header and the enclave report as they appear in the quote, which is what
+    /// `isv_signature` signs
+    pub signed_data: Vec<u8>,
+    /// Signature over the header and the enclave report, by the attestation key
+    pub isv_signature: Vec<u8>,
+    /// The attestation public key
+    pub attestation_key: Vec<u8>,
+    /// Report of the Quoting Enclave, binding the attestation key
+    pub qe_report: SgxEnclaveReport,
+    /// Signature over the QE report, by the PCK
+    pub qe_report_signature: Vec<u8>,
+    /// QE authentication data
+    pub qe_auth_data: Vec<u8>,
+    /// Type of the QE certification data (5 is a PEM PCK cert chain)
+    pub certification_data_type: u16,
+    /// QE certification data
+    pub certification_data: Vec<u8>,
+}
+
+impl DcapQuote {
+    /// Parse bytes of an ECDSA quote into `DcapQuote`.
+    pub fn parse_from<'a>(bytes: &'a [u8]) -> Result<Self, DcapError> {
+        let mut pos: usize = 0;
+        let mut take = |n: usize| -> Result<&'a [u8], DcapError> {
+            match take_bytes(bytes, &mut pos, n) {
+                Some(ret) if n > 0 => Ok(ret),
+                _ => {
+                    warn!("DCAP quote parsing error - quote is truncated");
+                    Err(DcapError::MalformedQuote)
+                }
+            }
+        };
+        let to_u16 = |b: &[u8]| u16::from_le_bytes([b[0], b[1]]);
+        let to_u32 = |b: &[u8]| u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+
+        // off 0, size 2
+        let version = to_u16(take(2)?);
+        if version != 3 && version != 4 {
+            warn!("DCAP quote parsing error - unsupported version {}", version);
+            return Err(DcapError::UnsupportedQuoteVersion(version));
+        }
+
+        // off 2, size 2
+        let attestation_key_type = match to_u16(take(2)?) {
+            2 => SgxEcdsaQuoteAkType::P256_256,
+            3 => SgxEcdsaQuoteAkType::P384_384,
+            _ => {
+                warn!("DCAP quote parsing error - ecdsa quote type invalid");
+                return Err(DcapError::MalformedQuote);
+            }
+        };
+
+        // off 4, size 4 - reserved in v3 quotes, and the TEE type in v4 quotes
+        let tee_type = to_u32(take(4)?);
+        if version == 4 && tee_type != TEE_TYPE_SGX {
+            warn!(
+                "DCAP quote parsing error - unsupported TEE type {:#x}",
+                tee_type
+            );
+            return Err(DcapError::UnsupportedTeeType(tee_type));
+        }
+
+        // off 8, size 2
+        let qe_svn = to_u16(take(2)?);
+
+        // off 10, size 2
+        let pce_svn = to_u16(take(2)?);
+
+        // off 12, size 16
+        let qe_vendor_id = Uuid::from_slice(take(16)?).map_err(|_| DcapError::MalformedQuote)?;
+
+        // off 28, size 20
+        let user_data = <[u8; 20]>::try_from(take(20)?).map_err(|_| DcapError::MalformedQuote)?;
+
+        // off 48, size 384
+        let isv_enclave_report =
+            SgxEnclaveReport::parse_from(take(384)?).map_err(|_| DcapError::MalformedQuote)?;
+
+        // off 432, size 4
+        let signature_data_len = to_u32(take(4)?) as usize;
+        if bytes.len() != 436 + signature_data_len {
+            warn!("DCAP quote parsing error - signature data length mismatch");
+            return Err(DcapError::MalformedQuote);
+        }
+
+        // The rest of the quote is laid out according to the attestation key type declared in
+        // the header. If the sections don't add up to exactly the signature data length, the
+        // header doesn't describe the signature data it came with.
+        let key_type_mismatch = |_| {
+            warn!("DCAP quote attestation key type doesn't match the signature data");
+            DcapError::KeyTypeMismatch
+        };
+        let (signature_size, key_size) = ecdsa_sizes(&attestation_key_type);
+
+        let isv_signature = take(signature_size).map_err(key_type_mismatch)?.to_vec();
+        let attestation_key = take(key_size).map_err(key_type_mismatch)?.to_vec();
+
+        // v4 quotes wrap the rest in QE report certification data, which must span all of it
+        if version == 4 {
+            let wrapper_type = to_u16(take(2).map_err(key_type_mismatch)?);
+            let wrapper_len = to_u32(take(4).map_err(key_type_mismatch)?) as usize;
+            let wrapper_start = 436 + signature_size + key_size + 6;
+            if wrapper_type != QE_REPORT_CERTIFICATION_DATA_TYPE
+                || wrapper_len != bytes.len() - wrapper_start
+            {
+                warn!(
+                    "DCAP quote parsing error - certification data of type {} and length {} \
+                     doesn't wrap the QE report",
+                    wrapper_type, wrapper_len
+                );
+                return Err(DcapError::MalformedQuote);
+            }
+        }
+        let qe_report = SgxEnclaveReport::parse_from(take(384).map_err(key_type_mismatch)?)
+            .map_err(|_| DcapError::MalformedQuote)?;
+        let qe_report_signature = take(signature_size).map_err(key_type_mismatch)?.to_vec();
+
+        let qe_auth_data_len = to_u16(take(2).map_err(key_type_mismatch)?) as usize;
+        let qe_auth_data = if qe_auth_data_len > 0 {
+            take(qe_auth_data_len).map_err(key_type_mismatch)?.to_vec()
+        } else {
+            vec![]
+        };
+
+        let certification_data_type = to_u16(take(2).map_err(key_type_mismatch)?);
+        let certification_data_len = to_u32(take(4).map_err(key_type_mismatch)?) as usize;
+        let certification_data = take(certification_data_len)
+            .map_err(key_type_mismatch)?
+            .to_vec();
+
+        if pos != bytes.len() {
+            warn!("DCAP quote attestation key type doesn't match the signature data");
+            return Err(DcapError::KeyTypeMismatch);
+        }
+
+        Ok(Self {
+            version,
+            attestation_key_type,
+            qe_svn,
+            pce_svn,
+            qe_vendor_id,
+            user_data,
+            isv_enclave_report,
+            signed_data: bytes[..432].to_vec(),
+            isv_signature,
+            attestation_key,
+            qe_report,
+            qe_report_signature,
+            qe_auth_data,
+            certification_data_type,
+            certification_data,
+        })
+    }
+
+    /// Checks that the QE report was produced by Intel's Quoting Enclave. This is on top of the
+    /// QE identity collateral check done by the quote verification library, so that a
+    /// substituted QE identity can't vouch for a QE of another signer
+    pub fn verify_qe_identity(&self) -> Result<(), DcapError> {
+        if self.qe_report.mr_signer != INTEL_QE_MRSIGNER {
+            warn!(
+                "QE report has an unexpected MRSIGNER: {:?}",
+                self.qe_report.mr_signer
+            );
+            return Err(DcapError::QeIdentityMismatch);
+        }
+
+        Ok(())
+    }
+
+    /// Checks the signature of the attestation key over the header and the enclave report, so
+    /// that a quote whose report (e.g. its `report_data` or MRENCLAVE) was altered is rejected
+    /// regardless of what the quote verification library makes of it
+    pub fn verify_isv_signature(&self) -> Result<(), DcapError> {
+        let algorithm = match self.attestation_key_type {
+            SgxEcdsaQuoteAkType::P256_256 => &ECDSA_P256_SHA256_FIXED,
+            SgxEcdsaQuoteAkType::P384_384 => &ECDSA_P384_SHA384_FIXED,
+        };
+
+        // the attestation key is the bare coordinates, without the uncompressed point prefix
+        let mut public_key = vec![0x04];
+        public_key.extend_from_slice(&self.attestation_key);
+
+        UnparsedPublicKey::new(algorithm, &public_key)
+            .verify(&self.signed_data, &self.isv_signature)
+            .map_err(|_| {
+                warn!("DCAP quote signature doesn't match the report");
+                DcapError::IsvSignatureInvalid
+            })
+    }
+
+    /// The DER encoded certificates of the PCK cert chain in the certification data, leaf first
+    pub fn pck_cert_chain(&self) -> Result<Vec<Vec<u8>>, DcapError> {
+        if self.certification_data_type != PCK_CERT_CHAIN_TYPE {
+            warn!(
+                "Quote certification data is of type {} rather than a PCK cert chain",
+                self.certification_data_type
+            );
+            return Err(DcapError::InvalidPckChain);
+        }
+
+        let pem = std::str::from_utf8(&self.certification_data)
+            .map_err(|_| DcapError::InvalidPckChain)?
+            .trim_end_matches('\0');
+
+        pem.split("-----END CERTIFICATE-----")
+            .filter(|block| !block.trim().is_empty())
+            .map(|block| {
+                let encoded: String = block
+                    .trim()
+                    .trim_start_matches("-----BEGIN CERTIFICATE-----")
+                    .split_whitespace()
+                    .collect();
+                decode_base64(encoded.as_bytes()).map_err(|_| {
+                    warn!("Failed to decode a certificate of the PCK cert chain");
+                    DcapError::InvalidPckChain
+                })
+            })
+            .collect()
+    }
+
+    /// Checks the structure of the PCK cert chain: it's the PCK leaf, the Processor or Platform
+    /// CA, and the root CA, each issued by the next, and the basic constraints of each allow for
+    /// its place in the chain. The signatures are checked by the quote verification library
+    pub fn verify_pck_chain(&self) -> Result<(), DcapError> {
+        let chain = self.pck_cert_chain()?;
+        if chain.len() != 3 {
+            warn!(
+                "PCK cert chain has {} certificates rather than 3",
+                chain.len()
+            );
+            return Err(DcapError::InvalidPckChain);
+        }
+
+        let fields = chain
+            .iter()
+            .map(|cert| pck_cert_fields(cert))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| {
+                warn!("Failed to parse a certificate of the PCK cert chain");
+                DcapError::InvalidPckChain
+            })?;
+
+        for (level, (issuer, subject, constraints)) in fields.iter().enumerate() {
+            // the PCK leaf isn't a CA, and each CA has at most `path_len` CAs below it, not
+            // counting the leaf
+            let valid = if level == 0 {
+                !constraints.ca
+            } else {
+                constraints.ca
+                    && constraints
+                        .path_len
+                        .map_or(true, |path_len| path_len >= (level - 1) as u64)
+            };
+            if !valid {
+                warn!(
+                    "Certificate {} of the PCK cert chain has invalid basic constraints {:?}",
+                    level, constraints
+                );
+                return Err(DcapError::InvalidPckChain);
+            }
+
+            let issuer_subject = match fields.get(level + 1) {
+                Some((_, issuer_subject, _)) => issuer_subject,
+                // the root is self-issued
+                None => subject,
+            };
+            if issuer != issuer_subject {
+                warn!(
+                    "Certificate {} of the PCK cert chain isn't issued by the next one",
+                    level
+                );
+                return Err(DcapError::InvalidPckChain);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The FMSPC and PCE ID of the platform, as lowercase hex, from the SGX extension of the PCK
+    /// cert. These identify the collateral the quote is verified against
+    pub fn platform_ids(&self) -> Result<(String, String), DcapError> {
+        let chain = self.pck_cert_chain()?;
+        let leaf = chain.first().ok_or(DcapError::InvalidPckChain)?;
+
+        let fmspc = sgx_extension_field(leaf, FMSPC_OID, 6);
+        let pceid = sgx_extension_field(leaf, PCEID_OID, 2);
+        match (fmspc, pceid) {
+            (Some(fmspc), Some(pceid)) => Ok((hex::encode(fmspc), hex::encode(pceid))),
+            _ => {
+                warn!("PCK cert doesn't have the FMSPC and PCE ID of the platform");
+                Err(DcapError::InvalidPckChain)
+            }
+        }
+    }
+}
+
+/// DCAP collateral, as serialized by the untrusted side: a header with the TEE type and the sizes
+/// of the items, followed by the items themselves. Each item is NUL-terminated.
+pub struct DcapCollateral {
+    /// 0 for SGX, 0x81 for TDX
+    pub tee_type: u32,
+    pub pck_crl_issuer_chain: Vec<u8>,
+    pub root_ca_crl: Vec<u8>,
+    pub pck_crl: Vec<u8>,
+    pub tcb_info_issuer_chain: Vec<u8>,
+    /// TCB info JSON, signed by Intel
+    pub tcb_info: Vec<u8>,
+    pub qe_identity_issuer_chain: Vec<u8>,
+    /// QE identity JSON, signed by Intel
+    pub qe_identity: Vec<u8>,
+}
+
+/// The fields of the collateral TCB info we make decisions on
+#[derive(Debug, Clone, PartialEq)]
+pub struct TcbInfo {
+    /// Family-Model-Stepping-Platform-CustomSKU of the platform, as lowercase hex
+    pub fmspc: String,
+    /// Time the TCB info was issued at, in seconds since epoch
+    pub issue_date: i64,
+    /// Time by which the next TCB info will be issued, in seconds since epoch
+    pub next_update: i64,
+    pub tcb_eval_data_number: u16,
+    /// TCB levels of the platform model, from the most recent to the oldest
+    pub tcb_levels: Vec<TcbLevel>,
+}
+
+/// A TCB level in the TCB info, and the status of platforms at that level
+#[derive(Debug, Clone, PartialEq)]
+pub struct TcbLevel {
+    /// SVNs of the SGX TCB components (`sgxtcbcomp01svn` to `sgxtcbcomp16svn`)
+    pub sgx_tcb_components: [u8; 16],
+    pub pce_svn: u16,
+    pub tcb_status: SgxQuoteStatus,
+}
+
+impl TcbInfo {
+    /// Status of a platform with the given TCB, according to the first (i.e. most recent) level
+    /// the platform is at or above. `None` if the platform is below all the levels.
+    pub fn tcb_status(&self, cpu_svn: &[u8; 16], pce_svn: u16) -> Option<SgxQuoteStatus> {
+        self.tcb_levels
+            .iter()
+            .find(|level| {
+                pce_svn >= level.pce_svn
+                    && cpu_svn
+                        .iter()
+                        .zip(level.sgx_tcb_components.iter())
+                        .all(|(svn, level_svn)| svn >= level_svn)
+            })
+            .map(|level| level.tcb_status.clone())
+    }
+}
+
+/// Convert a `tcbStatus` of the TCB info to the matching quote status
+fn tcb_status_from_str(status: &str) -> SgxQuoteStatus {
+    match status {
+        "UpToDate" => SgxQuoteStatus::OK,
+        "SWHardeningNeeded" => SgxQuoteStatus::SwHardeningNeeded,
+        "ConfigurationNeeded" => SgxQuoteStatus::ConfigurationNeeded,
+        "ConfigurationAndSWHardeningNeeded" => SgxQuoteStatus::ConfigurationAndSwHardeningNeeded,
+        "OutOfDate" => SgxQuoteStatus::OutOfDate,
+        "OutOfDateConfigurationNeeded" => SgxQuoteStatus::OutOfDateConfigurationNeeded,
+        "Revoked" => SgxQuoteStatus::KeyRevoked,
+        _ => SgxQuoteStatus::UnknownBadStatus,
+    }
+}
+
+fn parse_tcb_level(level: &Value) -> Option<TcbLevel> {
+    let tcb = &level["tcb"];
+
+    // the collateral is untrusted, so SVNs out of range are malformed rather than truncated
+    let mut sgx_tcb_components = [0u8; 16];
+    for (i, svn) in sgx_tcb_components.iter_mut().enumerate() {
+        *svn = u8::try_from(tcb[format!("sgxtcbcomp{:02}svn", i + 1)].as_u64()?).ok()?;
+    }
+
+    Some(TcbLevel {
+        sgx_tcb_components,
+        pce_svn: u16::try_from(tcb["pcesvn"].as_u64()?).ok()?,
+        tcb_status: tcb_status_from_str(level["tcbStatus"].as_str()?),
+    })
+}
+
+impl DcapCollateral {
+    /// Parse the serialized collateral into `DcapCollateral`.
+    pub fn parse_from<'a>(bytes: &'a [u8]) -> Result<Self, DcapError> {
+        let mut pos: usize = 0;
+        let mut take = |n: usize| -> Result<&'a [u8], DcapError> {
+            take_bytes(bytes, &mut pos, n).ok_or_else(|| {
+                warn!("DCAP collateral parsing error - collateral is truncated");
+                DcapError::MalformedCollateral
+            })
+        };
+
+        let mut header = [0u32; 8];
+        for field in header.iter_mut() {
+            let b = take(4)?;
+            *field = u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+        }
+
+        let mut items = header[1..]
+            .iter()
+            .map(|size| take(*size as usize).map(|item| item.to_vec()))
+            .collect::<Result<Vec<Vec<u8>>, DcapError>>()?
+            .into_iter();
+        let mut next = || items.next().unwrap_or_default();
+
+        Ok(Self {
+            tee_type: header[0],
+            pck_crl_issuer_chain: next(),
+            root_ca_crl: next(),
+            pck_crl: next(),
+            tcb_info_issuer_chain: next(),
+            tcb_info: next(),
+            qe_identity_issuer_chain: next(),
+            qe_identity: next(),
+        })
+    }
+
+    /// Serialize the collateral in the layout `parse_from` reads
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let items = [
+            &self.pck_crl_issuer_chain,
+            &self.root_ca_crl,
+            &self.pck_crl,
+            &self.tcb_info_issuer_chain,
+            &self.tcb_info,
+            &self.qe_identity_issuer_chain,
+            &self.qe_identity,
+        ];
+
+        let mut bytes = self.tee_type.to_le_bytes().to_vec();
+        for item in items.iter() {
+            bytes.extend_from_slice(&(item.len() as u32).to_le_bytes());
+        }
+        for item in items.iter() {
+            bytes.extend_from_slice(item);
+        }
+
+        bytes
+    }
+
+    /// Parse the TCB info JSON. The signature over it is not checked here - that is done by the
+    /// quote verification library.
+    pub fn tcb_info(&self) -> Result<TcbInfo, DcapError> {
+        let v = parse_collateral_json(&self.tcb_info, "TCB info")?;
+        let tcb_info = &v["tcbInfo"];
+        let parse_date = |field: &str| parse_collateral_date(tcb_info, field, "TCB info");
+
+        Ok(TcbInfo {
+            fmspc: tcb_info["fmspc"]
+                .as_str()
+                .ok_or(DcapError::MalformedCollateral)?
+                .to_lowercase(),
+            issue_date: parse_date("issueDate")?,
+            next_update: parse_date("nextUpdate")?,
+            tcb_eval_data_number: tcb_info["tcbEvaluationDataNumber"]
+                .as_u64()
+                .and_then(|number| u16::try_from(number).ok())
+                .ok_or(DcapError::MalformedCollateral)?,
+            tcb_levels: tcb_info["tcbLevels"]
+                .as_array()
+                .ok_or(DcapError::MalformedCollateral)?
+                .iter()
+                .map(parse_tcb_level)
+                .collect::<Option<Vec<TcbLevel>>>()
+                .ok_or_else(|| {
+                    warn!("Failed to parse TCB info levels");
+                    DcapError::MalformedCollateral
+                })?,
+        })
+    }
+
+    /// Time by which the next QE identity will be issued, in seconds since epoch. Like the TCB
+    /// info, its signature is checked by the quote verification library
+    pub fn qe_identity_next_update(&self) -> Result<i64, DcapError> {
+        let v = parse_collateral_json(&self.qe_identity, "QE identity")?;
+        parse_collateral_date(&v["enclaveIdentity"], "nextUpdate", "QE identity")
+    }
+}
+
+/// Parse a NUL-terminated JSON item of the collateral
+fn parse_collateral_json(item: &[u8], name: &str) -> Result<Value, DcapError> {
+    let json = item.split(|b| *b == 0).next().unwrap_or_default();
+    serde_json::from_slice(json).map_err(|e| {
+        warn!("Failed to parse {}: {}", name, e);
+        DcapError::MalformedCollateral
+    })
+}
+
+/// Parse the RFC 3339 date in `field` of a collateral JSON object, in seconds since epoch
+fn parse_collateral_date(object: &Value, field: &str, name: &str) -> Result<i64, DcapError> {
+    let date = object[field]
+        .as_str()
+        .ok_or(DcapError::MalformedCollateral)?;
+    chrono::DateTime::parse_from_rfc3339(date)
+        .map(|time| time.timestamp())
+        .map_err(|e| {
+            warn!("Failed to decode {} {}: {}", name, field, e);
+            DcapError::MalformedCollateral
+        })
+}
+
+/// A local source of collateral, for verifiers that can't fetch it when verifying (e.g. air-gapped
+/// ones). Collateral is looked up by the FMSPC and PCE ID of the platform, as lowercase hex
+pub trait CollateralStore {
+    fn get(&self, fmspc: &str, pceid: &str) -> Option<DcapCollateral>;
+}
+
+/// The collateral of the platform that produced `quote`, from `store`. The collateral must be for
+/// the same platform model as the quote
+pub fn resolve_collateral(
+    quote: &DcapQuote,
+    store: &dyn CollateralStore,
+) -> Result<DcapCollateral, DcapError> {
+    let (fmspc, pceid) = quote.platform_ids()?;
+    let collateral = store.get(&fmspc, &pceid).ok_or_else(|| {
+        warn!(
+            "No collateral in the store for FMSPC {} PCE ID {}",
+            fmspc, pceid
+        );
+        DcapError::CollateralNotFound
+    })?;
+
+    let tcb_info = collateral.tcb_info()?;
+    if tcb_info.fmspc != fmspc {
+        warn!(
+            "Collateral stored for FMSPC {} is for FMSPC {}",
+            fmspc, tcb_info.fmspc
+        );
+        return Err(DcapError::FmspcMismatch);
+    }
+
+    Ok(collateral)
+}
+
+/// Checks that `report_time` (seconds since epoch), the time the quote was produced, is within
+/// the window from the issue of the collateral TCB info to its next update, if the policy requires
+/// it. A quote verified against collateral issued after it was produced may be a replay, and one
+/// produced at an unknown time may be as well
+pub fn check_collateral_window(
+    report_time: Option<i64>,
+    collateral: &DcapCollateral,
+    policy: &AttestationPolicy,
+) -> Result<(), DcapError> {
+    if !policy.require_timestamp_within_collateral {
+        return Ok(());
+    }
+
+    let timestamp = report_time.ok_or_else(|| {
+        warn!("The time the quote was produced is unknown");
+        DcapError::TimestampOutsideCollateral
+    })?;
+
+    let window = ValidityWindow::of_tcb_info(&collateral.tcb_info()?);
+    if !window.contains(timestamp) {
+        warn!(
+            "Quote timestamp {} is outside of the collateral validity {:?}",
+            timestamp, window
+        );
+        return Err(DcapError::TimestampOutsideCollateral);
+    }
+
+    Ok(())
+}
+
+/// Checks that neither the TCB info nor the QE identity of the collateral was due to be updated
+/// by `time_s` (seconds since epoch), the time the quote is verified at. Unlike
+/// `check_collateral_window` this is not up to the policy: a platform verified against stale
+/// collateral may have been revoked since
+pub fn check_collateral_expiry(time_s: i64, collateral: &DcapCollateral) -> Result<(), DcapError> {
+    let tcb_info_next_update = collateral.tcb_info()?.next_update;
+    let qe_identity_next_update = collateral.qe_identity_next_update()?;
+
+    if tcb_info_next_update <= time_s || qe_identity_next_update <= time_s {
+        warn!(
+            "Collateral expired by {}: TCB info next update {}, QE identity next update {}",
+            time_s, tcb_info_next_update, qe_identity_next_update
+        );
+        return Err(DcapError::CollateralExpired);
+    }
+
+    Ok(())
+}
+
+/// Checks the quote verification result against the policy. Statuses outside of the policy's
+/// `dcap_accepted_statuses` are only accepted on the platform models (FMSPCs) the policy tolerates
+/// them on.
+pub fn check_dcap_status(
+    status: &SgxQuoteStatus,
+    fmspc: Option<&str>,
+    policy: &AttestationPolicy,
+) -> Result<(), DcapError> {
+    if policy.dcap_accepted_statuses.contains(status) {
+        return Ok(());
+    }
+
+    let tolerated = fmspc
+        .and_then(|fmspc| {
+            policy
+                .tolerated_statuses_by_fmspc
+                .get(&fmspc.to_lowercase())
+        })
+        .map_or(false, |statuses| statuses.contains(status));
+    if !tolerated {
+        warn!(
+            "Quote verification result {:?} is not accepted for FMSPC {:?}",
+            status, fmspc
+        );
+        return Err(DcapError::StatusNotAccepted(status.clone()));
+    }
+
+    Ok(())
+}
+
+/// Checks that the quote is no larger than the policy allows, if it sets a limit, and that the
+/// signature data length in the quote accounts for exactly the bytes after it. The signature data
+/// embeds the PCK cert chain and the QE authentication data, which vary in length, so quotes of
+/// the same attestation type and key type don't all have the same size
+pub fn check_quote_size(quote: &[u8], policy: &AttestationPolicy) -> Result<(), DcapError> {
+    if let Some(max) = policy.max_quote_size {
+        if quote.len() > max {
+            warn!(
+                "Quote is {} bytes long, more than the {} allowed",
+                quote.len(),
+                max
+            );
+            return Err(DcapError::QuoteTooLarge {
+                max,
+                got: quote.len(),
+            });
+        }
+    }
+
+    // off 432, size 4 - the length of the signature data, which takes up the rest of the quote
+    let signature_data_len = match quote.get(432..436) {
+        Some(b) => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize,
+        None => {
+            warn!("Quote is too short to hold a header and an enclave report");
+            return Err(DcapError::MalformedQuote);
+        }
+    };
+    if quote.len() - 436 != signature_data_len {
+        warn!(
+            "Quote has {} bytes of signature data rather than the {} it declares",
+            quote.len() - 436,
+            signature_data_len
+        );
+        return Err(DcapError::MalformedQuote);
+    }
+
+    Ok(())
+}
+
+/// What the verification of a DCAP quote established about the enclave and its platform
+#[derive(Debug, Clone, PartialEq)]
+pub struct DcapVerificationResult {
+    pub mr_enclave: [u8; 32],
+    pub mr_signer: [u8; 32],
+    pub isv_prod_id: u16,
+    pub isv_svn: u16,
+    pub report_data: [u8; 64],
+    /// TCB status of the platform, as determined by the quote verification
+    pub tcb_status: SgxQuoteStatus,
+}
+
+impl DcapVerificationResult {
+    pub fn new(quote: &DcapQuote, tcb_status: SgxQuoteStatus) -> Self {
+        let enclave_report = &quote.isv_enclave_report;
+        Self {
+            mr_enclave: enclave_report.mr_enclave,
+            mr_signer: enclave_report.mr_signer,
+            isv_prod_id: enclave_report.isv_prod_id,
+            isv_svn: enclave_report.isv_svn,
+            report_data: enclave_report.report_data,
+            tcb_status,
+        }
+    }
+}
+
+/// Checks that an EPID and a DCAP attestation are of the same enclave, i.e. that they agree on
+/// its identity and bind the same report data. Meant for nodes that attest with both schemes
+/// while migrating from EPID to DCAP
+pub fn cross_check_attestations(
+    epid: &AttestationReport,
+    dcap: &DcapVerificationResult,
+) -> Result<(), Error> {
+    let enclave_report = &epid.sgx_quote_body.isv_enclave_report;
+
+    if enclave_report.mr_enclave != dcap.mr_enclave || enclave_report.mr_signer != dcap.mr_signer {
+        warn!(
+            "EPID attestation is of enclave {:?} (signer {:?}), DCAP of {:?} (signer {:?})",
+            enclave_report.mr_enclave, enclave_report.mr_signer, dcap.mr_enclave, dcap.mr_signer
+        );
+        return Err(Error::ReportValidationError);
+    }
+
+    if enclave_report.isv_prod_id != dcap.isv_prod_id || enclave_report.isv_svn != dcap.isv_svn {
+        warn!(
+            "EPID attestation is of product {} svn {}, but the DCAP one of product {} svn {}",
+            enclave_report.isv_prod_id, enclave_report.isv_svn, dcap.isv_prod_id, dcap.isv_svn
+        );
+        return Err(Error::ReportValidationError);
+    }
+
+    if enclave_report.report_data[..] != dcap.report_data[..] {
+        warn!("EPID and DCAP attestations bind different report data");
+        return Err(Error::ReportValidationError);
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use std::collections::HashMap;
+
+    use crate::registration::report::tests::{mock_attestation_report, read_fixture};
+
+    use super::*;
+
+    pub fn dcap_quote_fixture() -> Vec<u8> {
+        read_fixture("attestation_dcap.quote")
+    }
+
+    pub fn test_dcap_quote_parse_from() {
+        let quote = DcapQuote::parse_from(&dcap_quote_fixture()).unwrap();
+
+        assert_eq!(quote.attestation_key_type, SgxEcdsaQuoteAkType::P256_256);
+        assert_eq!(quote.qe_svn, 10);
+        assert_eq!(quote.pce_svn, 15);
+        assert_eq!(quote.qe_auth_data.len(), 32);
+        assert_eq!(quote.certification_data_type, 5);
+        assert_eq!(quote.certification_data.len(), 3552);
+    }
+
+    /// The fixture quote in the v4 layout: the same header (but for the version) and enclave
+    /// report, with the QE report and its certification data wrapped in QE report certification
+    /// data
+    fn dcap_quote_v4_fixture() -> Vec<u8> {
+        let v3 = dcap_quote_fixture();
+        let (signature_and_key, qe_report_certification_data) = v3[436..].split_at(64 + 64);
+
+        let mut v4 = v3[..432].to_vec();
+        v4[..2].copy_from_slice(&4u16.to_le_bytes());
+        let signature_data_len = signature_and_key.len() + 6 + qe_report_certification_data.len();
+        v4.extend_from_slice(&(signature_data_len as u32).to_le_bytes());
+        v4.extend_from_slice(signature_and_key);
+        v4.extend_from_slice(&QE_REPORT_CERTIFICATION_DATA_TYPE.to_le_bytes());
+        v4.extend_from_slice(&(qe_report_certification_data.len() as u32).to_le_bytes());
+        v4.extend_from_slice(qe_report_certification_data);
+
+        v4
+    }
+
+    pub fn test_dcap_quote_v4() {
+        let v3 = DcapQuote::parse_from(&dcap_quote_fixture()).unwrap();
+        let v4 = DcapQuote::parse_from(&dcap_quote_v4_fixture()).unwrap();
+        assert_eq!(v3.version, 3);
+        assert_eq!(v4.version, 4);
+        assert_eq!(v4.isv_enclave_report, v3.isv_enclave_report);
+        assert_eq!(v4.qe_report, v3.qe_report);
+        assert_eq!(v4.qe_auth_data, v3.qe_auth_data);
+        assert_eq!(v4.certification_data, v3.certification_data);
+        assert_eq!(v4.verify_pck_chain(), Ok(()));
+
+        let mut tdx = dcap_quote_v4_fixture();
+        tdx[4] = 0x81;
+        assert_eq!(
+            DcapQuote::parse_from(&tdx).err(),
+            Some(DcapError::UnsupportedTeeType(0x81))
+        );
+
+        // the certification data after the attestation key isn't QE report certification data
+        let mut unwrapped = dcap_quote_v4_fixture();
+        unwrapped[436 + 128] = PCK_CERT_CHAIN_TYPE as u8;
+        assert_eq!(
+            DcapQuote::parse_from(&unwrapped).err(),
+            Some(DcapError::MalformedQuote)
+        );
+
+        let mut v5 = dcap_quote_fixture();
+        v5[0] = 5;
+        assert_eq!(
+            DcapQuote::parse_from(&v5).err(),
+            Some(DcapError::UnsupportedQuoteVersion(5))
+        );
+    }
+
+    pub fn test_dcap_quote_key_type_mismatch() {
+        let mut vec_quote = dcap_quote_fixture();
+
+        // declare a P-384 attestation key over P-256 signature data
+        vec_quote[2] = 3;
+
+        assert_eq!(
+            DcapQuote::parse_from(&vec_quote).err(),
+            Some(DcapError::KeyTypeMismatch)
+        );
+    }
+
+    pub fn test_dcap_quote_qe_identity() {
+        let mut vec_quote = dcap_quote_fixture();
+        let quote = DcapQuote::parse_from(&vec_quote).unwrap();
+        assert_eq!(quote.verify_qe_identity(), Ok(()));
+
+        // the QE report is after the ISV signature and the attestation key, and its MRSIGNER is
+        // at offset 128
+        vec_quote[436 + 64 + 64 + 128] ^= 0xff;
+        let quote = DcapQuote::parse_from(&vec_quote).unwrap();
+        assert_eq!(
+            quote.verify_qe_identity(),
+            Err(DcapError::QeIdentityMismatch)
+        );
+    }
+
+    pub fn test_dcap_quote_isv_signature() {
+        let vec_quote = dcap_quote_fixture();
+        let quote = DcapQuote::parse_from(&vec_quote).unwrap();
+        assert_eq!(quote.verify_isv_signature(), Ok(()));
+
+        // the enclave report is at offset 48 of the quote, with MRENCLAVE at offset 64 and the
+        // report data at offset 320
+        for offset in [48 + 64, 48 + 320 + 6].iter() {
+            let mut tampered = vec_quote.clone();
+            tampered[*offset] ^= 4;
+            let quote = DcapQuote::parse_from(&tampered).unwrap();
+            assert_eq!(
+                quote.verify_isv_signature(),
+                Err(DcapError::IsvSignatureInvalid)
+            );
+        }
+    }
+
+    pub fn test_dcap_quote_pck_chain() {
+        let mut quote = DcapQuote::parse_from(&dcap_quote_fixture()).unwrap();
+        let chain = quote.pck_cert_chain().unwrap();
+        assert_eq!(chain.len(), 3);
+        assert_eq!(quote.verify_pck_chain(), Ok(()));
+
+        let to_pem = |certs: &[&Vec<u8>]| -> Vec<u8> {
+            certs
+                .iter()
+                .map(|cert| {
+                    format!(
+                        "-----BEGIN CERTIFICATE-----\n{}\n-----END CERTIFICATE-----\n",
+                        base64::encode(cert)
+                    )
+                })
+                .collect::<String>()
+                .into_bytes()
+        };
+
+        // without the Processor CA
+        quote.certification_data = to_pem(&[&chain[0], &chain[2]]);
+        assert_eq!(quote.pck_cert_chain().unwrap().len(), 2);
+        assert_eq!(quote.verify_pck_chain(), Err(DcapError::InvalidPckChain));
+
+        // the Processor CA posing as the leaf
+        quote.certification_data = to_pem(&[&chain[1], &chain[1], &chain[2]]);
+        assert_eq!(quote.verify_pck_chain(), Err(DcapError::InvalidPckChain));
+
+        // out of order
+        quote.certification_data = to_pem(&[&chain[0], &chain[2], &chain[1]]);
+        assert_eq!(quote.verify_pck_chain(), Err(DcapError::InvalidPckChain));
+
+        quote.certification_data = to_pem(&[&chain[0], &chain[1], &chain[2]]);
+        assert_eq!(quote.verify_pck_chain(), Ok(()));
+
+        quote.certification_data_type = 6;
+        assert_eq!(quote.verify_pck_chain(), Err(DcapError::InvalidPckChain));
+    }
+
+    pub fn dcap_collateral_fixture() -> Vec<u8> {
+        read_fixture("attestation_dcap.collateral")
+    }
+
+    pub fn test_dcap_collateral_parse_from() {
+        let collateral = DcapCollateral::parse_from(&dcap_collateral_fixture()).unwrap();
+        assert_eq!(collateral.tee_type, 0);
+        assert!(collateral.qe_identity.starts_with(b"{\"enclaveIdentity\""));
+
+        let tcb_info = collateral.tcb_info().unwrap();
+        assert_eq!(tcb_info.fmspc, "00906ed50000");
+        assert_eq!(tcb_info.tcb_eval_data_number, 10);
+        assert!(tcb_info.issue_date < tcb_info.next_update);
+        assert_eq!(tcb_info.tcb_levels.len(), 11);
+
+        let quote = DcapQuote::parse_from(&dcap_quote_fixture()).unwrap();
+        assert_eq!(
+            tcb_info.tcb_status(&quote.isv_enclave_report.cpu_svn, quote.pce_svn),
+            Some(SgxQuoteStatus::SwHardeningNeeded)
+        );
+        assert_eq!(tcb_info.tcb_status(&[0u8; 16], quote.pce_svn), None);
+
+        // SVNs that don't fit their fields would otherwise wrap, e.g. 256 to 0
+        let original = parse_collateral_json(&collateral.tcb_info, "TCB info").unwrap();
+        for (field, value) in [("sgxtcbcomp01svn", 256u64), ("pcesvn", 65536)].iter() {
+            let mut tcb_info = original.clone();
+            tcb_info["tcbInfo"]["tcbLevels"][0]["tcb"][*field] = Value::from(*value);
+            let mut tampered = DcapCollateral::parse_from(&dcap_collateral_fixture()).unwrap();
+            tampered.tcb_info = serde_json::to_vec(&tcb_info).unwrap();
+            assert_eq!(
+                tampered.tcb_info().err(),
+                Some(DcapError::MalformedCollateral)
+            );
+        }
+
+        // and so would an evaluation data number, to below any minimum
+        let mut tcb_info = original;
+        tcb_info["tcbInfo"]["tcbEvaluationDataNumber"] = Value::from(65536u64 + 10);
+        let mut tampered = DcapCollateral::parse_from(&dcap_collateral_fixture()).unwrap();
+        tampered.tcb_info = serde_json::to_vec(&tcb_info).unwrap();
+        assert_eq!(
+            tampered.tcb_info().err(),
+            Some(DcapError::MalformedCollateral)
+        );
+    }
+
+    /// Collateral kept in memory, serialized
+    struct InMemoryCollateralStore {
+        collateral: HashMap<(String, String), Vec<u8>>,
+    }
+
+    impl CollateralStore for InMemoryCollateralStore {
+        fn get(&self, fmspc: &str, pceid: &str) -> Option<DcapCollateral> {
+            self.collateral
+                .get(&(fmspc.to_string(), pceid.to_string()))
+                .map(|bytes| DcapCollateral::parse_from(bytes).unwrap())
+        }
+    }
+
+    pub fn test_collateral_store() {
+        let quote = DcapQuote::parse_from(&dcap_quote_fixture()).unwrap();
+        assert_eq!(
+            quote.platform_ids(),
+            Ok(("00906ed50000".to_string(), "0000".to_string()))
+        );
+
+        let mut store = InMemoryCollateralStore {
+            collateral: HashMap::new(),
+        };
+        assert!(matches!(
+            resolve_collateral(&quote, &store),
+            Err(DcapError::CollateralNotFound)
+        ));
+
+        store.collateral.insert(
+            ("00906ed50000".to_string(), "0000".to_string()),
+            dcap_collateral_fixture(),
+        );
+        let collateral = resolve_collateral(&quote, &store).unwrap();
+        assert_eq!(collateral.tcb_info().unwrap().fmspc, "00906ed50000");
+        // the serialized collateral is what the quote verification library is handed
+        assert_eq!(collateral.to_bytes(), dcap_collateral_fixture());
+
+        // collateral of another platform model, stored under the FMSPC of the quote
+        let mut collateral = DcapCollateral::parse_from(&dcap_collateral_fixture()).unwrap();
+        let tcb_info = String::from_utf8(collateral.tcb_info.clone()).unwrap();
+        collateral.tcb_info = tcb_info
+            .replace("00906ed50000", "00606a000000")
+            .into_bytes();
+        store.collateral.insert(
+            ("00906ed50000".to_string(), "0000".to_string()),
+            collateral.to_bytes(),
+        );
+        assert!(matches!(
+            resolve_collateral(&quote, &store),
+            Err(DcapError::FmspcMismatch)
+        ));
+    }
+
+    pub fn test_dcap_status_tolerated_for_fmspc() {
+        let collateral = DcapCollateral::parse_from(&dcap_collateral_fixture()).unwrap();
+        let fmspc = collateral.tcb_info().unwrap().fmspc;
+
+        let mut tolerated_statuses_by_fmspc = HashMap::new();
+        tolerated_statuses_by_fmspc.insert(
+            "00906ed50000".to_string(),
+            vec![SgxQuoteStatus::ConfigurationNeeded],
+        );
+        let policy = AttestationPolicy {
+            tolerated_statuses_by_fmspc,
+            ..Default::default()
+        };
+
+        let status = SgxQuoteStatus::ConfigurationNeeded;
+        assert_eq!(check_dcap_status(&status, Some(&fmspc), &policy), Ok(()));
+        assert_eq!(
+            check_dcap_status(&status, Some("00606a000000"), &policy),
+            Err(DcapError::StatusNotAccepted(status.clone()))
+        );
+        assert_eq!(
+            check_dcap_status(&status, None, &policy),
+            Err(DcapError::StatusNotAccepted(status.clone()))
+        );
+
+        // only the tolerated status is accepted on the whitelisted platform
+        assert!(check_dcap_status(&SgxQuoteStatus::OutOfDate, Some(&fmspc), &policy).is_err());
+        assert_eq!(
+            check_dcap_status(&SgxQuoteStatus::OK, Some("00606a000000"), &policy),
+            Ok(())
+        );
+    }
+
+    pub fn test_check_collateral_window() {
+        let collateral = DcapCollateral::parse_from(&dcap_collateral_fixture()).unwrap();
+        let tcb_info = collateral.tcb_info().unwrap();
+        let before_issue = tcb_info.issue_date - 1;
+
+        let mut policy = AttestationPolicy::default();
+        assert_eq!(
+            check_collateral_window(Some(before_issue), &collateral, &policy),
+            Ok(())
+        );
+        assert_eq!(check_collateral_window(None, &collateral, &policy), Ok(()));
+
+        policy.require_timestamp_within_collateral = true;
+        assert_eq!(
+            check_collateral_window(Some(before_issue), &collateral, &policy),
+            Err(DcapError::TimestampOutsideCollateral)
+        );
+        assert_eq!(
+            check_collateral_window(Some(tcb_info.next_update + 1), &collateral, &policy),
+            Err(DcapError::TimestampOutsideCollateral)
+        );
+        assert_eq!(
+            check_collateral_window(Some(tcb_info.issue_date), &collateral, &policy),
+            Ok(())
+        );
+        assert_eq!(
+            check_collateral_window(Some(tcb_info.next_update), &collateral, &policy),
+            Ok(())
+        );
+        // without the time the quote was produced, the check fails closed
+        assert_eq!(
+            check_collateral_window(None, &collateral, &policy),
+            Err(DcapError::TimestampOutsideCollateral)
+        );
+    }
+
+    pub fn test_check_collateral_expiry() {
+        let collateral = DcapCollateral::parse_from(&dcap_collateral_fixture()).unwrap();
+        let tcb_info = collateral.tcb_info().unwrap();
+        let qe_identity_next_update = collateral.qe_identity_next_update().unwrap();
+        // the fixture TCB info is due to be updated before the QE identity
+        assert!(tcb_info.next_update < qe_identity_next_update);
+
+        assert_eq!(
+            check_collateral_expiry(tcb_info.issue_date, &collateral),
+            Ok(())
+        );
+        assert_eq!(
+            check_collateral_expiry(tcb_info.next_update - 1, &collateral),
+            Ok(())
+        );
+        assert_eq!(
+            check_collateral_expiry(tcb_info.next_update, &collateral),
+            Err(DcapError::CollateralExpired)
+        );
+        // 2100-01-01
+        assert_eq!(
+            check_collateral_expiry(4102444800, &collateral),
+            Err(DcapError::CollateralExpired)
+        );
+
+        let mut stale_qe_identity = DcapCollateral::parse_from(&dcap_collateral_fixture()).unwrap();
+        stale_qe_identity.qe_identity = String::from_utf8(collateral.qe_identity.clone())
+            .unwrap()
+            .replace("\"nextUpdate\":\"2021-07-31", "\"nextUpdate\":\"2021-04-01")
+            .into_bytes();
+        assert_eq!(
+            check_collateral_expiry(tcb_info.next_update - 1, &stale_qe_identity),
+            Err(DcapError::CollateralExpired)
+        );
+    }
+
+    pub fn test_check_quote_size() {
+        let quote = dcap_quote_fixture();
+        let mut policy = AttestationPolicy::default();
+        assert_eq!(check_quote_size(&quote, &policy), Ok(()));
+
+        policy.max_quote_size = Some(quote.len());
+        assert_eq!(check_quote_size(&quote, &policy), Ok(()));
+
+        // A longer PCK chain makes for a longer quote, which is fine as long as the signature
+        // data length accounts for it and the quote is within the limit
+        let mut longer = quote.clone();
+        longer.extend_from_slice(&[0u8; 16]);
+        let signature_data_len = (longer.len() - 436) as u32;
+        longer[432..436].copy_from_slice(&signature_data_len.to_le_bytes());
+        policy.max_quote_size = None;
+        assert_eq!(check_quote_size(&longer, &policy), Ok(()));
+
+        policy.max_quote_size = Some(quote.len());
+        assert_eq!(
+            check_quote_size(&longer, &policy),
+            Err(DcapError::QuoteTooLarge {
+                max: quote.len(),
+                got: quote.len() + 16
+            })
+        );
+
+        // Bytes the signature data length doesn't account for
+        let mut padded = quote.clone();
+        padded.extend_from_slice(&[0u8; 16]);
+        policy.max_quote_size = None;
+        assert_eq!(
+            check_quote_size(&padded, &policy),
+            Err(DcapError::MalformedQuote)
+        );
+        assert_eq!(
+            check_quote_size(&quote[..quote.len() - 1], &policy),
+            Err(DcapError::MalformedQuote)
+        );
+        assert_eq!(
+            check_quote_size(&quote[..400], &policy),
+            Err(DcapError::MalformedQuote)
+        );
+    }
+
+    pub fn test_cross_check_attestations() {
+        let quote = DcapQuote::parse_from(&dcap_quote_fixture()).unwrap();
+        let dcap = DcapVerificationResult::new(&quote, SgxQuoteStatus::SwHardeningNeeded);
+
+        // an EPID attestation of the same enclave, binding the same report data
+        let mut epid = mock_attestation_report();
+        epid.sgx_quote_body.isv_enclave_report.mr_enclave = quote.isv_enclave_report.mr_enclave;
+        epid.sgx_quote_body.isv_enclave_report.mr_signer = quote.isv_enclave_report.mr_signer;
+        epid.sgx_quote_body.isv_enclave_report.isv_prod_id = quote.isv_enclave_report.isv_prod_id;
+        epid.sgx_quote_body.isv_enclave_report.isv_svn = quote.isv_enclave_report.isv_svn;
+        epid.sgx_quote_body.isv_enclave_report.report_data = quote.isv_enclave_report.report_data;
+        assert!(cross_check_attestations(&epid, &dcap).is_ok());
+
+        let mut other_data = dcap.clone();
+        other_data.report_data[0] ^= 0xff;
+        assert!(cross_check_attestations(&epid, &other_data).is_err());
+
+        let mut other_enclave = dcap.clone();
+        other_enclave.mr_enclave[0] ^= 0xff;
+        assert!(cross_check_attestations(&epid, &other_enclave).is_err());
+
+        let mut other_svn = dcap.clone();
+        other_svn.isv_svn = other_svn.isv_svn.wrapping_add(1);
+        assert!(cross_check_attestations(&epid, &other_svn).is_err());
+
+        // the unrelated EPID fixture
+        assert!(cross_check_attestations(&mock_attestation_report(), &dcap).is_err());
+    }
+}
@@ -0,0 +1,300 @@
+//! Decoding of the hex and base64 fields of attestation evidence.
+//!
+//! Everything that decodes untrusted attestation input - IAS reports and the certs they come in,
+//! JWS reports and DCAP quotes - goes through the functions here. They use the `hex` and `base64`
+//! crates by default. With the `minimal-deps` feature the small decoders here are used instead,
+//! so the enclave doesn't have to trust the crates for parsing untrusted input. Both accept and
+//! reject exactly the same inputs, and decode them to the same bytes.
+//!
+//! The feature doesn't drop the crates from the build: the enclave still uses them to encode its
+//! own output, and to decode input that isn't attestation evidence.
+
+/// Why an input couldn't be decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input can't be the encoding of a whole number of bytes
+    InvalidLength,
+    /// The byte at the index isn't valid where it is
+    InvalidByte(usize),
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::InvalidLength => write!(f, "invalid input length"),
+            DecodeError::InvalidByte(index) => write!(f, "invalid byte at offset {}", index),
+        }
+    }
+}
+
+/// Decodes standard base64, with optional padding, like `base64::decode`
+#[cfg(not(feature = "minimal-deps"))]
+pub fn decode_base64(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    base64::decode(input).map_err(|e| match e {
+        base64::DecodeError::InvalidByte(index, _)
+        | base64::DecodeError::InvalidLastSymbol(index, _) => DecodeError::InvalidByte(index),
+        base64::DecodeError::InvalidLength => DecodeError::InvalidLength,
+    })
+}
+
+/// Decodes standard base64, with optional padding, like `base64::decode`
+#[cfg(feature = "minimal-deps")]
+pub fn decode_base64(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    decode_base64_minimal(input)
+}
+
+/// Decodes URL-safe base64, with optional padding, like `base64::decode_config` with
+/// `base64::URL_SAFE_NO_PAD`
+#[cfg(not(feature = "minimal-deps"))]
+pub fn decode_base64url(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    base64::decode_config(input, base64::URL_SAFE_NO_PAD).map_err(|e| match e {
+        base64::DecodeError::InvalidByte(index, _)
+        | base64::DecodeError::InvalidLastSymbol(index, _) => DecodeError::InvalidByte(index),
+        base64::DecodeError::InvalidLength => DecodeError::InvalidLength,
+    })
+}
+
+/// Decodes URL-safe base64, with optional padding, like `base64::decode_config` with
+/// `base64::URL_SAFE_NO_PAD`
+#[cfg(feature = "minimal-deps")]
+pub fn decode_base64url(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    decode_base64url_minimal(input)
+}
+
+/// Decodes hex of either case, like `hex::decode`
+#[cfg(not(feature = "minimal-deps"))]
+pub fn decode_hex(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    hex::decode(input).map_err(|e| match e {
+        hex::FromHexError::InvalidHexCharacter { index, .. } => DecodeError::InvalidByte(index),
+        hex::FromHexError::OddLength | hex::FromHexError::InvalidStringLength => {
+            DecodeError::InvalidLength
+        }
+    })
+}
+
+/// Decodes hex of either case, like `hex::decode`
+#[cfg(feature = "minimal-deps")]
+pub fn decode_hex(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    decode_hex_minimal(input)
+}
+
+fn base64_value(symbol: u8) -> Option<u8> {
+    match symbol {
+        b'A'..=b'Z' => Some(symbol - b'A'),
+        b'a'..=b'z' => Some(symbol - b'a' + 26),
+        b'0'..=b'9' => Some(symbol - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+fn base64url_value(symbol: u8) -> Option<u8> {
+    match symbol {
+        b'-' => Some(62),
+        b'_' => Some(63),
+        b'+' | b'/' => None,
+        _ => base64_value(symbol),
+    }
+}
+
+#[cfg_attr(not(any(feature = "minimal-deps", feature = "test")), allow(dead_code))]
+fn decode_base64_minimal(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    decode_base64_symbols(input, base64_value)
+}
+
+#[cfg_attr(not(any(feature = "minimal-deps", feature = "test")), allow(dead_code))]
+fn decode_base64url_minimal(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    decode_base64_symbols(input, base64url_value)
+}
+
+/// Decodes base64 whose alphabet maps symbols to their values with `value`
+fn decode_base64_symbols(
+    input: &[u8],
+    value: fn(u8) -> Option<u8>,
+) -> Result<Vec<u8>, DecodeError> {
+    // a single symbol of a group doesn't make up a byte
+    if input.len() % 4 == 1 {
+        return Err(DecodeError::InvalidLength);
+    }
+
+    // padding is optional, but when it's there it may only complete the last group of symbols
+    let symbols = match input.iter().position(|byte| *byte == b'=') {
+        Some(start) => {
+            if start % 4 < 2 || input.len() > start - start % 4 + 4 {
+                return Err(DecodeError::InvalidByte(start));
+            }
+            if let Some(offset) = input[start..].iter().position(|byte| *byte != b'=') {
+                return Err(DecodeError::InvalidByte(start + offset));
+            }
+            &input[..start]
+        }
+        None => input,
+    };
+
+    let mut decoded = Vec::with_capacity(symbols.len() * 3 / 4);
+    let mut bits: u32 = 0;
+    let mut bit_count = 0;
+    for (index, symbol) in symbols.iter().enumerate() {
+        let symbol_value = value(*symbol).ok_or(DecodeError::InvalidByte(index))?;
+        bits = (bits << 6) | symbol_value as u32;
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            decoded.push((bits >> bit_count) as u8);
+            bits &= (1 << bit_count) - 1;
+        }
+    }
+
+    // the bits of the last symbol that don't make up a byte must be zero, so that every byte
+    // string has a single encoding
+    if bits != 0 {
+        return Err(DecodeError::InvalidByte(symbols.len() - 1));
+    }
+
+    Ok(decoded)
+}
+
+fn hex_value(digit: u8) -> Option<u8> {
+    match digit {
+        b'0'..=b'9' => Some(digit - b'0'),
+        b'a'..=b'f' => Some(digit - b'a' + 10),
+        b'A'..=b'F' => Some(digit - b'A' + 10),
+        _ => None,
+    }
+}
+
+#[cfg_attr(not(any(feature = "minimal-deps", feature = "test")), allow(dead_code))]
+fn decode_hex_minimal(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    if input.len() % 2 != 0 {
+        return Err(DecodeError::InvalidLength);
+    }
+
+    input
+        .chunks(2)
+        .enumerate()
+        .map(|(i, pair)| {
+            let high = hex_value(pair[0]).ok_or(DecodeError::InvalidByte(2 * i))?;
+            let low = hex_value(pair[1]).ok_or(DecodeError::InvalidByte(2 * i + 1))?;
+            Ok((high << 4) | low)
+        })
+        .collect()
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use sgx_rand::{os, Rng};
+
+    use super::*;
+
+    const BASE64_ALPHABET: &[u8] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/=";
+    const BASE64URL_ALPHABET: &[u8] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_=+/";
+    const HEX_ALPHABET: &[u8] = b"0123456789abcdefABCDEF";
+
+    /// Up to 64 bytes that are mostly from `alphabet`, with the odd byte of any value
+    fn random_input(rng: &mut os::SgxRng, alphabet: &[u8]) -> Vec<u8> {
+        let len = rng.next_u32() as usize % 65;
+        (0..len)
+            .map(|_| {
+                let r = rng.next_u32();
+                if r % 32 == 0 {
+                    (r >> 8) as u8
+                } else {
+                    alphabet[(r >> 8) as usize % alphabet.len()]
+                }
+            })
+            .collect()
+    }
+
+    fn assert_base64_matches(input: &[u8]) {
+        assert_eq!(
+            decode_base64_minimal(input).ok(),
+            base64::decode(input).ok(),
+            "decoders disagree on {:?}",
+            String::from_utf8_lossy(input)
+        );
+    }
+
+    fn assert_base64url_matches(input: &[u8]) {
+        assert_eq!(
+            decode_base64url_minimal(input).ok(),
+            base64::decode_config(input, base64::URL_SAFE_NO_PAD).ok(),
+            "decoders disagree on {:?}",
+            String::from_utf8_lossy(input)
+        );
+    }
+
+    fn assert_hex_matches(input: &[u8]) {
+        assert_eq!(
+            decode_hex_minimal(input).ok(),
+            hex::decode(input).ok(),
+            "decoders disagree on {:?}",
+            String::from_utf8_lossy(input)
+        );
+    }
+
+    pub fn test_minimal_decoders_match_crates() {
+        for input in [
+            "", "A", "AA", "AA=", "AA==", "AAA", "AAA=", "AB==", "AAB=", "AA=A", "AA===", "AAAA=",
+            "AAAA==", "A===", "AAAAA=", "Zm9vYmFy", "Zm9vYg==", "Zm9vYg", " Zm9v", "Zm9v\n",
+        ]
+        .iter()
+        {
+            assert_base64_matches(input.as_bytes());
+            assert_base64url_matches(input.as_bytes());
+        }
+        for input in ["-_8", "+/8", "Zm9v-w", "Zm9v_w==", "Zm9v+w"].iter() {
+            assert_base64url_matches(input.as_bytes());
+        }
+        for input in ["", "0", "00", "fF", "Ab", "0g", " 00", "abc"].iter() {
+            assert_hex_matches(input.as_bytes());
+        }
+
+        let mut rng = os::SgxRng::new().unwrap();
+        for _ in 0..10_000 {
+            assert_base64_matches(&random_input(&mut rng, BASE64_ALPHABET));
+            assert_base64url_matches(&random_input(&mut rng, BASE64URL_ALPHABET));
+            assert_hex_matches(&random_input(&mut rng, HEX_ALPHABET));
+
+            // random garbage is rarely valid, so also check encodings of random bytes, whole
+            // and with a byte flipped
+            let mut bytes = vec![0u8; rng.next_u32() as usize % 65];
+            rng.fill_bytes(&mut bytes);
+            let mut encoded = base64::encode(&bytes).into_bytes();
+            assert_eq!(decode_base64_minimal(&encoded), Ok(bytes.clone()));
+            let unpadded = encoded.iter().position(|b| *b == b'=');
+            assert_base64_matches(&encoded[..unpadded.unwrap_or(encoded.len())]);
+            let url_encoded = base64::encode_config(&bytes, base64::URL_SAFE_NO_PAD);
+            assert_eq!(
+                decode_base64url_minimal(url_encoded.as_bytes()),
+                Ok(bytes.clone())
+            );
+            let mut hex_encoded = hex::encode(&bytes).into_bytes();
+            assert_eq!(decode_hex_minimal(&hex_encoded), Ok(bytes));
+            if !encoded.is_empty() {
+                let i = rng.next_u32() as usize % encoded.len();
+                encoded[i] ^= 1 << (rng.next_u32() % 8);
+                assert_base64_matches(&encoded);
+                let i = rng.next_u32() as usize % hex_encoded.len();
+                hex_encoded[i] ^= 1 << (rng.next_u32() % 8);
+                assert_hex_matches(&hex_encoded);
+            }
+        }
+    }
+
+    pub fn test_decode_helpers() {
+        assert_eq!(decode_base64(b"Zm9vYg=="), Ok(b"foob".to_vec()));
+        assert_eq!(decode_base64(b"Zm9vY"), Err(DecodeError::InvalidLength));
+        assert!(matches!(
+            decode_base64(b"Zm9vYh=="),
+            Err(DecodeError::InvalidByte(_))
+        ));
+        assert_eq!(decode_base64url(b"-_8"), Ok(vec![0xfb, 0xff]));
+        assert_eq!(decode_base64url(b"+/8"), Err(DecodeError::InvalidByte(0)));
+        assert_eq!(decode_hex(b"00fF"), Ok(vec![0x00, 0xff]));
+        assert_eq!(decode_hex(b"0"), Err(DecodeError::InvalidLength));
+        assert_eq!(decode_hex(b"0g"), Err(DecodeError::InvalidByte(1)));
+    }
+}
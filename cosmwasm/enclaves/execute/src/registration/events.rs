@@ -0,0 +1,172 @@
+//! Notifications about attestation verifications, for services that want a live feed of them
+//! (e.g. a dashboard) rather than polling.
+
+use std::sync::mpsc::Sender;
+use std::sync::SgxMutex;
+
+use lazy_static::lazy_static;
+use log::*;
+
+use enclave_ffi_types::NodeAuthResult;
+
+use super::policy::AttestationPolicy;
+use super::report::{AttestationReport, SgxQuoteStatus};
+
+/// Record of a single verification of an attestation report
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerificationEvent {
+    /// Measurement of the attested enclave
+    pub mr_enclave: [u8; 32],
+    /// Signer of the attested enclave
+    pub mr_signer: [u8; 32],
+    /// Quote status reported by the attestation service
+    pub quote_status: SgxQuoteStatus,
+    /// Time the report was issued at, in seconds since epoch
+    pub report_timestamp: u64,
+    /// The outcome of the verification
+    pub result: NodeAuthResult,
+}
+
+impl VerificationEvent {
+    pub fn new(report: &AttestationReport, result: NodeAuthResult) -> Self {
+        let enclave_report = &report.sgx_quote_body.isv_enclave_report;
+        Self {
+            mr_enclave: enclave_report.mr_enclave,
+            mr_signer: enclave_report.mr_signer,
+            quote_status: report.sgx_quote_status.clone(),
+            report_timestamp: report.timestamp,
+            result,
+        }
+    }
+}
+
+/// Receives an event at the end of every verification
+pub trait EventSink {
+    /// Called synchronously, so implementations should return quickly
+    fn on_verification(&self, event: &VerificationEvent);
+}
+
+/// An `EventSink` that forwards the events over a channel, to be consumed by another thread
+pub struct ChannelEventSink {
+    sender: Sender<VerificationEvent>,
+}
+
+impl ChannelEventSink {
+    pub fn new(sender: Sender<VerificationEvent>) -> Self {
+        Self { sender }
+    }
+}
+
+impl EventSink for ChannelEventSink {
+    fn on_verification(&self, event: &VerificationEvent) {
+        // the receiving side going away shouldn't affect verification
+        if self.sender.send(event.clone()).is_err() {
+            debug!("Verification event receiver is gone, dropping event");
+        }
+    }
+}
+
+lazy_static! {
+    /// The sink `verify_ra_cert` notifies, if the service registered one
+    static ref EVENT_SINK: SgxMutex<Option<Box<dyn EventSink + Send>>> = SgxMutex::new(None);
+}
+
+/// Registers the sink to notify at the end of every verification of a registering node's RA
+/// cert, replacing the previous one. `None` stops the notifications
+pub fn set_event_sink(sink: Option<Box<dyn EventSink + Send>>) {
+    match EVENT_SINK.lock() {
+        Ok(mut current) => *current = sink,
+        Err(_) => error!("Verification event sink lock is poisoned, sink not set"),
+    }
+}
+
+/// Notifies the registered sink, if any, that `report` was verified with `result`
+pub(crate) fn notify_verification(report: &AttestationReport, result: NodeAuthResult) {
+    match EVENT_SINK.lock() {
+        Ok(sink) => {
+            if let Some(sink) = sink.as_ref() {
+                sink.on_verification(&VerificationEvent::new(report, result));
+            }
+        }
+        Err(_) => error!("Verification event sink lock is poisoned, dropping event"),
+    }
+}
+
+/// Evaluate the report against the policy, and notify the sink about the outcome
+pub fn evaluate_with_sink(
+    report: &AttestationReport,
+    policy: &AttestationPolicy,
+    sink: &dyn EventSink,
+) -> Result<(), NodeAuthResult> {
+    let res = report.evaluate(policy);
+
+    let result = match res {
+        Ok(()) => NodeAuthResult::Success,
+        Err(e) => e,
+    };
+    sink.on_verification(&VerificationEvent::new(report, result));
+
+    res
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use std::sync::mpsc::channel;
+
+    use crate::registration::report::tests::mock_attestation_report;
+
+    use super::*;
+
+    pub fn test_events_emitted_in_order() {
+        let (sender, receiver) = channel();
+        let sink = ChannelEventSink::new(sender);
+        let policy = AttestationPolicy {
+            accepted_statuses: vec![SgxQuoteStatus::OK],
+            ..Default::default()
+        };
+
+        let mut reports = vec![];
+        for i in 0..3u8 {
+            let mut report = mock_attestation_report();
+            report.sgx_quote_body.isv_enclave_report.mr_enclave = [i; 32];
+            if i != 1 {
+                report.sgx_quote_status = SgxQuoteStatus::OK;
+            }
+            reports.push(report);
+        }
+
+        for report in reports.iter() {
+            let _ = evaluate_with_sink(report, &policy, &sink);
+        }
+
+        let events: Vec<VerificationEvent> = receiver.try_iter().collect();
+        assert_eq!(events.len(), 3);
+        for (i, event) in events.iter().enumerate() {
+            assert_eq!(event.mr_enclave, [i as u8; 32]);
+        }
+        assert_eq!(events[0].result, NodeAuthResult::Success);
+        assert_eq!(events[1].result, NodeAuthResult::GroupOutOfDate);
+        assert_eq!(events[2].result, NodeAuthResult::Success);
+    }
+
+    pub fn test_registered_event_sink() {
+        let report = mock_attestation_report();
+
+        // no sink registered, so there's no one to notify
+        notify_verification(&report, NodeAuthResult::Success);
+
+        let (sender, receiver) = channel();
+        set_event_sink(Some(Box::new(ChannelEventSink::new(sender))));
+        notify_verification(&report, NodeAuthResult::Success);
+        notify_verification(&report, NodeAuthResult::BadQuoteStatus);
+
+        set_event_sink(None);
+        notify_verification(&report, NodeAuthResult::Success);
+
+        let results: Vec<NodeAuthResult> = receiver.try_iter().map(|event| event.result).collect();
+        assert_eq!(
+            results,
+            vec![NodeAuthResult::Success, NodeAuthResult::BadQuoteStatus]
+        );
+    }
+}
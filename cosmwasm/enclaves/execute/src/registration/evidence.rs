@@ -0,0 +1,131 @@
+//! A compact record of an accepted DCAP attestation.
+//!
+//! Keeping the whole quote around isn't needed to keep track of a node - what we care about later
+//! is which enclave it runs, and which TCB level its platform is at. With the latter, the
+//! platform's status can be re-evaluated against fresher collateral, without a new quote.
+
+use log::*;
+
+use super::dcap::{DcapCollateral, DcapError, DcapQuote, TcbInfo};
+use super::report::SgxQuoteStatus;
+
+/// What we store of an accepted DCAP quote
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttestationEvidence {
+    pub mr_enclave: [u8; 32],
+    pub mr_signer: [u8; 32],
+    pub isv_svn: u16,
+    /// Platform model, as lowercase hex
+    pub fmspc: String,
+    /// TCB of the platform - the CPU SVN of the enclave report, and the PCE SVN of the quote
+    pub cpu_svn: [u8; 16],
+    pub pce_svn: u16,
+    /// TCB status of the platform, as of the last verification
+    pub tcb_status: SgxQuoteStatus,
+    /// Time of the last verification, in seconds since epoch
+    pub verified_at: i64,
+}
+
+impl AttestationEvidence {
+    pub fn new(
+        quote: &DcapQuote,
+        tcb_info: &TcbInfo,
+        tcb_status: SgxQuoteStatus,
+        verified_at: i64,
+    ) -> Self {
+        let enclave_report = &quote.isv_enclave_report;
+        Self {
+            mr_enclave: enclave_report.mr_enclave,
+            mr_signer: enclave_report.mr_signer,
+            isv_svn: enclave_report.isv_svn,
+            fmspc: tcb_info.fmspc.clone(),
+            cpu_svn: enclave_report.cpu_svn,
+            pce_svn: quote.pce_svn,
+            tcb_status,
+            verified_at,
+        }
+    }
+
+    /// Recomputes the TCB status of the platform against `vec_coll`, returning the updated
+    /// evidence. Whether the new status is acceptable is up to the caller (see
+    /// `check_dcap_status`).
+    ///
+    /// The collateral is expected to have been verified already - only its TCB info is used here
+    pub fn reverify(&self, vec_coll: &[u8], now: i64) -> Result<Self, DcapError> {
+        let tcb_info = DcapCollateral::parse_from(vec_coll)?.tcb_info()?;
+
+        if tcb_info.fmspc != self.fmspc {
+            warn!(
+                "Collateral is for FMSPC {}, but the evidence is of FMSPC {}",
+                tcb_info.fmspc, self.fmspc
+            );
+            return Err(DcapError::FmspcMismatch);
+        }
+
+        let tcb_status = tcb_info
+            .tcb_status(&self.cpu_svn, self.pce_svn)
+            .ok_or_else(|| {
+                warn!("Platform TCB is below all the TCB levels in the collateral");
+                DcapError::TcbLevelNotSupported
+            })?;
+
+        if tcb_status != self.tcb_status {
+            info!(
+                "Platform TCB status changed from {:?} to {:?}",
+                self.tcb_status, tcb_status
+            );
+        }
+
+        Ok(Self {
+            tcb_status,
+            verified_at: now,
+            ..self.clone()
+        })
+    }
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use crate::registration::dcap::tests::{dcap_collateral_fixture, dcap_quote_fixture};
+
+    use super::*;
+
+    fn stored_evidence() -> AttestationEvidence {
+        let quote = DcapQuote::parse_from(&dcap_quote_fixture()).unwrap();
+        let collateral = DcapCollateral::parse_from(&dcap_collateral_fixture()).unwrap();
+
+        AttestationEvidence::new(
+            &quote,
+            &collateral.tcb_info().unwrap(),
+            SgxQuoteStatus::SwHardeningNeeded,
+            1000,
+        )
+    }
+
+    pub fn test_reverify_evidence() {
+        let evidence = stored_evidence();
+
+        let reverified = evidence.reverify(&dcap_collateral_fixture(), 2000).unwrap();
+        assert_eq!(reverified.tcb_status, SgxQuoteStatus::SwHardeningNeeded);
+        assert_eq!(reverified.verified_at, 2000);
+        assert_eq!(reverified.mr_enclave, evidence.mr_enclave);
+    }
+
+    pub fn test_reverify_evidence_out_of_date() {
+        // accepted back when its microcode was the latest, but the collateral has since
+        // published newer TCB levels
+        let mut evidence = stored_evidence();
+        evidence.cpu_svn[0] = 15;
+        evidence.cpu_svn[1] = 15;
+
+        let reverified = evidence.reverify(&dcap_collateral_fixture(), 2000).unwrap();
+        assert_eq!(reverified.tcb_status, SgxQuoteStatus::OutOfDate);
+
+        let mut evidence = stored_evidence();
+        evidence.fmspc = "00606a000000".to_string();
+        assert_eq!(
+            evidence.reverify(&dcap_collateral_fixture(), 2000),
+            Err(DcapError::FmspcMismatch)
+        );
+    }
+}
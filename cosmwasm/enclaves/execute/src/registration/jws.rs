@@ -0,0 +1,147 @@
+//! Attestation reports delivered as a JWS (JSON Web Signature).
+//!
+//! Some attestation services don't follow the IAS scheme of a report signed by a certificate, and
+//! instead return the report as the payload of a JWS in compact serialization, signed by a key
+//! the service publishes as a JWK. Only RS256 signatures are supported.
+
+use log::*;
+use serde::Deserialize;
+use serde_json::Value;
+
+use ring::signature::{RsaPublicKeyComponents, RSA_PKCS1_2048_8192_SHA256};
+
+use super::encoding;
+use super::report::{AttestationReport, Error};
+
+/// The RSA public key of an attestation service, as a JSON Web Key
+#[derive(Debug, Clone, Deserialize)]
+pub struct Jwk {
+    pub kty: String,
+    #[serde(default)]
+    pub kid: Option<String>,
+    /// Modulus, base64url encoded
+    pub n: String,
+    /// Public exponent, base64url encoded
+    pub e: String,
+}
+
+#[derive(Deserialize)]
+struct JwsHeader {
+    alg: String,
+    #[serde(default)]
+    kid: Option<String>,
+}
+
+fn decode_base64url(encoded: &str) -> Result<Vec<u8>, Error> {
+    encoding::decode_base64url(encoded.as_bytes()).map_err(|e| {
+        warn!("Failed to decode JWS component: {}", e);
+        Error::ReportParseError
+    })
+}
+
+impl AttestationReport {
+    /// Construct an AttestationReport from a JWS in compact serialization, whose payload is the
+    /// report body, after verifying its signature with `jwk`
+    pub fn from_jws(token: &str, jwk: &Jwk) -> Result<Self, Error> {
+        let parts: Vec<&str> = token.trim().split('.').collect();
+        if parts.len() != 3 {
+            warn!("JWS has {} components rather than 3", parts.len());
+            return Err(Error::ReportParseError);
+        }
+
+        let header: JwsHeader = serde_json::from_slice(&decode_base64url(parts[0])?)?;
+        if header.alg != "RS256" || jwk.kty != "RSA" {
+            warn!(
+                "Unsupported JWS algorithm {} with a key of type {}",
+                header.alg, jwk.kty
+            );
+            return Err(Error::ReportValidationError);
+        }
+
+        if let (Some(token_kid), Some(jwk_kid)) = (&header.kid, &jwk.kid) {
+            if token_kid != jwk_kid {
+                warn!("JWS is signed by key {}, not {}", token_kid, jwk_kid);
+                return Err(Error::ReportValidationError);
+            }
+        }
+
+        // the signature is over the encoded header and payload, as they appear in the token
+        let signing_input_len = parts[0].len() + 1 + parts[1].len();
+        let signing_input = &token.trim().as_bytes()[..signing_input_len];
+
+        let public_key = RsaPublicKeyComponents {
+            n: decode_base64url(&jwk.n)?,
+            e: decode_base64url(&jwk.e)?,
+        };
+        public_key
+            .verify(
+                &RSA_PKCS1_2048_8192_SHA256,
+                signing_input,
+                &decode_base64url(parts[2])?,
+            )
+            .map_err(|_| {
+                warn!("JWS signature verification failed");
+                Error::BadSignature
+            })?;
+
+        let attn_report: Value = serde_json::from_slice(&decode_base64url(parts[1])?)?;
+        trace!("attn_report: {}", attn_report);
+
+        Self::from_report_json(&attn_report)
+    }
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use crate::registration::report::tests::read_fixture;
+    use crate::registration::report::SgxQuoteStatus;
+
+    use super::*;
+
+    fn jwk_fixture() -> Jwk {
+        serde_json::from_slice(&read_fixture("attestation_report.jwk")).unwrap()
+    }
+
+    fn jws_fixture() -> String {
+        String::from_utf8(read_fixture("attestation_report.jws")).unwrap()
+    }
+
+    pub fn test_attestation_report_from_jws() {
+        let token = jws_fixture();
+
+        let report = AttestationReport::from_jws(&token, &jwk_fixture()).unwrap();
+        assert_eq!(report.sgx_quote_status, SgxQuoteStatus::GroupOutOfDate);
+        assert_eq!(report.advisory_ids.0, vec!["INTEL-SA-00334".to_string()]);
+        assert_eq!(report.tcb_eval_data_number, Some(16));
+        assert_eq!(report.timestamp, 1581459959);
+    }
+
+    pub fn test_attestation_report_from_jws_invalid() {
+        let token = jws_fixture();
+        let parts: Vec<&str> = token.trim().split('.').collect();
+
+        // a payload the signature isn't over
+        let mut payload = decode_base64url(parts[1]).unwrap();
+        let last = payload.len() - 2;
+        payload[last] ^= 0x01;
+        let tampered = format!(
+            "{}.{}.{}",
+            parts[0],
+            base64::encode_config(&payload, base64::URL_SAFE_NO_PAD),
+            parts[2]
+        );
+        assert!(AttestationReport::from_jws(&tampered, &jwk_fixture()).is_err());
+
+        // unsigned
+        let header = base64::encode_config(b"{\"alg\":\"none\"}", base64::URL_SAFE_NO_PAD);
+        let unsigned = format!("{}.{}.", header, parts[1]);
+        assert!(AttestationReport::from_jws(&unsigned, &jwk_fixture()).is_err());
+
+        // a key other than the one the token names
+        let mut jwk = jwk_fixture();
+        jwk.kid = Some("other-key".to_string());
+        assert!(AttestationReport::from_jws(&token, &jwk).is_err());
+
+        assert!(AttestationReport::from_jws(parts[0], &jwk_fixture()).is_err());
+    }
+}
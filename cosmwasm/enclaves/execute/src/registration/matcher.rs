@@ -0,0 +1,412 @@
+//! Composable rules for which enclaves are accepted.
+//!
+//! During a rollout some upgrades only bump the enclave's svn while keeping its measurement, and
+//! others change the measurement under the same signer. Rules like "MRENCLAVE is one of these, or
+//! it's signed by us with svn >= N" are expressed by combining `MeasurementMatcher`s. Builds that
+//! are being deprecated can be grandfathered for the nodes already running them, until a deadline.
+
+use std::collections::HashMap;
+
+use log::*;
+
+use enclave_crypto::{sha_256, HASH_SIZE};
+use enclave_ffi_types::NodeAuthResult;
+
+use super::report::{AttestationReport, Error};
+
+/// A rule on the identity of the enclave in the report
+#[derive(Debug, Clone, PartialEq)]
+pub enum MeasurementMatcher {
+    /// Matches if any of the inner matchers does (never, if there are none)
+    AnyOf(Vec<MeasurementMatcher>),
+    /// Matches if all of the inner matchers do (always, if there are none)
+    AllOf(Vec<MeasurementMatcher>),
+    /// Matches an exact MRENCLAVE
+    MrEnclave([u8; 32]),
+    /// Matches an MRSIGNER, with an enclave svn of at least `min_svn`
+    MrSignerWithMinSvn { mr_signer: [u8; 32], min_svn: u16 },
+}
+
+impl MeasurementMatcher {
+    pub fn matches(&self, report: &AttestationReport) -> bool {
+        let enclave_report = &report.sgx_quote_body.isv_enclave_report;
+
+        match self {
+            MeasurementMatcher::AnyOf(matchers) => {
+                matchers.iter().any(|matcher| matcher.matches(report))
+            }
+            MeasurementMatcher::AllOf(matchers) => {
+                matchers.iter().all(|matcher| matcher.matches(report))
+            }
+            MeasurementMatcher::MrEnclave(mr_enclave) => enclave_report.mr_enclave == *mr_enclave,
+            MeasurementMatcher::MrSignerWithMinSvn { mr_signer, min_svn } => {
+                enclave_report.mr_signer == *mr_signer && enclave_report.isv_svn >= *min_svn
+            }
+        }
+    }
+
+    /// The MRSIGNERs named by the signer rules of the matcher, in any of its branches
+    fn signers(&self) -> Vec<&[u8; 32]> {
+        match self {
+            MeasurementMatcher::AnyOf(matchers) | MeasurementMatcher::AllOf(matchers) => matchers
+                .iter()
+                .flat_map(|matcher| matcher.signers())
+                .collect(),
+            MeasurementMatcher::MrEnclave(_) => vec![],
+            MeasurementMatcher::MrSignerWithMinSvn { mr_signer, .. } => vec![mr_signer],
+        }
+    }
+}
+
+/// Accepts enclaves that match at least `required` of the `templates`, for composite services
+/// where a report has to satisfy several measurement templates, but not necessarily all of them
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuorumMeasurementPolicy {
+    pub templates: Vec<MeasurementMatcher>,
+    pub required: usize,
+}
+
+impl QuorumMeasurementPolicy {
+    /// How many of the templates the enclave of the report matches
+    pub fn matching(&self, report: &AttestationReport) -> usize {
+        self.templates
+            .iter()
+            .filter(|template| template.matches(report))
+            .count()
+    }
+
+    /// Checks that the enclave of the report matches at least `required` templates
+    pub fn evaluate(&self, report: &AttestationReport) -> Result<(), NodeAuthResult> {
+        let matching = self.matching(report);
+        if matching < self.required {
+            warn!(
+                "Enclave measurement {:?} matches {} of {} templates, but {} are required",
+                report.sgx_quote_body.isv_enclave_report.mr_enclave,
+                matching,
+                self.templates.len(),
+                self.required
+            );
+            return Err(NodeAuthResult::MrEnclaveMismatch);
+        }
+
+        Ok(())
+    }
+}
+
+/// Measurements of deprecated enclave builds that nodes already registered with them may keep
+/// using until a deadline, while new registrations of them are rejected
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GrandfatheredMeasurements {
+    /// Time (seconds since epoch) after which each measurement is no longer accepted
+    deadlines: HashMap<[u8; 32], u64>,
+}
+
+impl GrandfatheredMeasurements {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, mr_enclave: [u8; 32], deadline: u64) {
+        self.deadlines.insert(mr_enclave, deadline);
+    }
+
+    pub fn deadline(&self, mr_enclave: &[u8; 32]) -> Option<u64> {
+        self.deadlines.get(mr_enclave).copied()
+    }
+
+    /// Whether a node that's already registered may keep using `mr_enclave` at `now`
+    pub fn accepts(&self, mr_enclave: &[u8; 32], now: u64) -> bool {
+        self.deadline(mr_enclave)
+            .map_or(false, |deadline| now <= deadline)
+    }
+}
+
+impl AttestationReport {
+    /// Like `verify_measurement`, but if `matcher` doesn't accept the enclave, it's still
+    /// accepted at `now` if it's grandfathered and the node is `already_registered`
+    pub fn verify_measurement_grandfathered(
+        &self,
+        matcher: &MeasurementMatcher,
+        grandfathered: &GrandfatheredMeasurements,
+        now: u64,
+        already_registered: bool,
+    ) -> Result<(), NodeAuthResult> {
+        if matcher.matches(self) {
+            return Ok(());
+        }
+
+        let mr_enclave = &self.sgx_quote_body.isv_enclave_report.mr_enclave;
+        match grandfathered.deadline(mr_enclave) {
+            Some(deadline) if !already_registered => {
+                warn!(
+                    "Enclave measurement {:?} is deprecated (until {}), and can't be used for new \
+                     registrations",
+                    mr_enclave, deadline
+                );
+            }
+            Some(_) if grandfathered.accepts(mr_enclave, now) => {
+                info!(
+                    "Accepting grandfathered enclave measurement {:?}",
+                    mr_enclave
+                );
+                return Ok(());
+            }
+            Some(deadline) => {
+                warn!(
+                    "Grandfathered enclave measurement {:?} expired at {}",
+                    mr_enclave, deadline
+                );
+            }
+            None => {}
+        }
+
+        self.verify_measurement(matcher)
+    }
+
+    /// Checks that the enclave of the report is accepted by `matcher`. The enclave is rejected as
+    /// `MrSignerMismatch` if the matcher has signer rules and none of them names its signer, and
+    /// as `MrEnclaveMismatch` otherwise
+    pub fn verify_measurement(&self, matcher: &MeasurementMatcher) -> Result<(), NodeAuthResult> {
+        if !matcher.matches(self) {
+            let enclave_report = &self.sgx_quote_body.isv_enclave_report;
+            warn!(
+                "Enclave measurement {:?} (signer {:?}, svn {}) isn't accepted by {:?}",
+                enclave_report.mr_enclave,
+                enclave_report.mr_signer,
+                enclave_report.isv_svn,
+                matcher
+            );
+
+            let signers = matcher.signers();
+            if !signers.is_empty() && !signers.contains(&&enclave_report.mr_signer) {
+                return Err(NodeAuthResult::MrSignerMismatch);
+            }
+            return Err(NodeAuthResult::MrEnclaveMismatch);
+        }
+
+        Ok(())
+    }
+}
+
+/// The commitment to a list of measurements that governance stores on-chain - SHA-256 of the
+/// measurements concatenated in order
+pub fn measurements_commitment(measurements: &[[u8; 32]]) -> [u8; HASH_SIZE] {
+    sha_256(&measurements.concat())
+}
+
+/// Checks that the enclave of the report is one of `measurements`, after checking that the list
+/// is the one committed to by `commitment` (see `measurements_commitment`), so that only
+/// governance-approved measurements are accepted
+pub fn verify_against_committed_measurements(
+    report: &AttestationReport,
+    measurements: &[[u8; 32]],
+    commitment: &[u8; HASH_SIZE],
+) -> Result<(), Error> {
+    if measurements_commitment(measurements) != *commitment {
+        warn!(
+            "List of {} measurements doesn't match the commitment {}",
+            measurements.len(),
+            hex::encode(commitment)
+        );
+        return Err(Error::MeasurementCommitmentMismatch);
+    }
+
+    let mr_enclave = report.sgx_quote_body.isv_enclave_report.mr_enclave;
+    if !measurements.contains(&mr_enclave) {
+        warn!(
+            "Enclave measurement {} isn't one of the committed measurements",
+            hex::encode(mr_enclave)
+        );
+        return Err(Error::MrEnclaveNotAllowed(mr_enclave));
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use crate::registration::report::tests::mock_attestation_report;
+
+    use super::*;
+
+    fn mock_mr_signer() -> [u8; 32] {
+        mock_attestation_report()
+            .sgx_quote_body
+            .isv_enclave_report
+            .mr_signer
+    }
+
+    pub fn test_measurement_matcher_leaves() {
+        let mut report = mock_attestation_report();
+        let mr_enclave = report.sgx_quote_body.isv_enclave_report.mr_enclave;
+        report.sgx_quote_body.isv_enclave_report.isv_svn = 3;
+
+        assert!(MeasurementMatcher::MrEnclave(mr_enclave).matches(&report));
+        assert!(!MeasurementMatcher::MrEnclave([1u8; 32]).matches(&report));
+
+        let signed_by =
+            |mr_signer, min_svn| MeasurementMatcher::MrSignerWithMinSvn { mr_signer, min_svn };
+        assert!(signed_by(mock_mr_signer(), 3).matches(&report));
+        assert!(!signed_by(mock_mr_signer(), 4).matches(&report));
+        assert!(!signed_by([1u8; 32], 0).matches(&report));
+    }
+
+    pub fn test_measurement_matcher_combinators() {
+        let report = mock_attestation_report();
+        let mr_enclave = report.sgx_quote_body.isv_enclave_report.mr_enclave;
+        let matching = MeasurementMatcher::MrEnclave(mr_enclave);
+        let other = MeasurementMatcher::MrEnclave([1u8; 32]);
+
+        assert!(MeasurementMatcher::AnyOf(vec![other.clone(), matching.clone()]).matches(&report));
+        assert!(!MeasurementMatcher::AnyOf(vec![other.clone()]).matches(&report));
+        assert!(!MeasurementMatcher::AnyOf(vec![]).matches(&report));
+
+        assert!(MeasurementMatcher::AllOf(vec![matching.clone()]).matches(&report));
+        assert!(!MeasurementMatcher::AllOf(vec![matching, other]).matches(&report));
+        assert!(MeasurementMatcher::AllOf(vec![]).matches(&report));
+    }
+
+    pub fn test_quorum_measurement_policy() {
+        let mut report = mock_attestation_report();
+        let mr_enclave = report.sgx_quote_body.isv_enclave_report.mr_enclave;
+        report.sgx_quote_body.isv_enclave_report.isv_svn = 2;
+
+        // the report matches the first two templates only
+        let templates = vec![
+            MeasurementMatcher::MrEnclave(mr_enclave),
+            MeasurementMatcher::MrSignerWithMinSvn {
+                mr_signer: mock_mr_signer(),
+                min_svn: 2,
+            },
+            MeasurementMatcher::MrEnclave([1u8; 32]),
+        ];
+        let quorum = |required| QuorumMeasurementPolicy {
+            templates: templates.clone(),
+            required,
+        };
+        assert_eq!(quorum(2).matching(&report), 2);
+
+        // more than enough matches, and exactly enough
+        assert_eq!(quorum(1).evaluate(&report), Ok(()));
+        assert_eq!(quorum(2).evaluate(&report), Ok(()));
+
+        // one short of the quorum
+        assert_eq!(
+            quorum(3).evaluate(&report),
+            Err(NodeAuthResult::MrEnclaveMismatch)
+        );
+
+        report.sgx_quote_body.isv_enclave_report.isv_svn = 1;
+        assert_eq!(
+            quorum(2).evaluate(&report),
+            Err(NodeAuthResult::MrEnclaveMismatch)
+        );
+    }
+
+    pub fn test_measurement_matcher_nested() {
+        // accept if (mr_enclave in {A, B}) or (mr_signer == X and isv_svn >= 2)
+        let matcher = MeasurementMatcher::AnyOf(vec![
+            MeasurementMatcher::AnyOf(vec![
+                MeasurementMatcher::MrEnclave([1u8; 32]),
+                MeasurementMatcher::MrEnclave([2u8; 32]),
+            ]),
+            MeasurementMatcher::AllOf(vec![MeasurementMatcher::MrSignerWithMinSvn {
+                mr_signer: mock_mr_signer(),
+                min_svn: 2,
+            }]),
+        ]);
+
+        let mut report = mock_attestation_report();
+        assert_eq!(
+            report.verify_measurement(&matcher),
+            Err(NodeAuthResult::MrEnclaveMismatch)
+        );
+
+        // a new release under the same signer
+        report.sgx_quote_body.isv_enclave_report.isv_svn = 2;
+        assert_eq!(report.verify_measurement(&matcher), Ok(()));
+
+        // an old release that's pinned by measurement
+        let mut report = mock_attestation_report();
+        report.sgx_quote_body.isv_enclave_report.mr_enclave = [2u8; 32];
+        assert_eq!(report.verify_measurement(&matcher), Ok(()));
+
+        // an enclave of another signer
+        let mut report = mock_attestation_report();
+        report.sgx_quote_body.isv_enclave_report.mr_signer = [3u8; 32];
+        report.sgx_quote_body.isv_enclave_report.isv_svn = 2;
+        assert_eq!(
+            report.verify_measurement(&matcher),
+            Err(NodeAuthResult::MrSignerMismatch)
+        );
+    }
+
+    pub fn test_grandfathered_measurements() {
+        let report = mock_attestation_report();
+        let old_release = report.sgx_quote_body.isv_enclave_report.mr_enclave;
+        let current_release = MeasurementMatcher::MrEnclave([1u8; 32]);
+
+        let mut grandfathered = GrandfatheredMeasurements::new();
+        grandfathered.insert(old_release, 2000);
+        assert!(grandfathered.accepts(&old_release, 2000));
+        assert!(!grandfathered.accepts(&old_release, 2001));
+        assert!(!grandfathered.accepts(&[1u8; 32], 0));
+
+        // an existing node, before the deadline
+        assert_eq!(
+            report.verify_measurement_grandfathered(&current_release, &grandfathered, 1500, true),
+            Ok(())
+        );
+
+        // a new registration of the deprecated build
+        assert_eq!(
+            report.verify_measurement_grandfathered(&current_release, &grandfathered, 1500, false),
+            Err(NodeAuthResult::MrEnclaveMismatch)
+        );
+
+        // an existing node, after the deadline
+        assert_eq!(
+            report.verify_measurement_grandfathered(&current_release, &grandfathered, 2001, true),
+            Err(NodeAuthResult::MrEnclaveMismatch)
+        );
+
+        // the current release isn't affected by the deadlines
+        let mut current = mock_attestation_report();
+        current.sgx_quote_body.isv_enclave_report.mr_enclave = [1u8; 32];
+        assert_eq!(
+            current.verify_measurement_grandfathered(&current_release, &grandfathered, 5000, false),
+            Ok(())
+        );
+    }
+
+    pub fn test_committed_measurements() {
+        let report = mock_attestation_report();
+        let mr_enclave = report.sgx_quote_body.isv_enclave_report.mr_enclave;
+
+        let measurements = [[1u8; 32], mr_enclave];
+        let commitment = measurements_commitment(&measurements);
+        assert!(verify_against_committed_measurements(&report, &measurements, &commitment).is_ok());
+
+        // a list with an extra measurement sneaked in doesn't match the commitment
+        let tampered = [[1u8; 32], mr_enclave, [2u8; 32]];
+        assert!(matches!(
+            verify_against_committed_measurements(&report, &tampered, &commitment),
+            Err(Error::MeasurementCommitmentMismatch)
+        ));
+
+        // neither does one in another order
+        let reordered = [mr_enclave, [1u8; 32]];
+        assert!(verify_against_committed_measurements(&report, &reordered, &commitment).is_err());
+
+        // the list matches, but the enclave isn't in it
+        let others = [[1u8; 32], [2u8; 32]];
+        assert!(matches!(
+            verify_against_committed_measurements(
+                &report,
+                &others,
+                &measurements_commitment(&others)
+            ),
+            Err(Error::MrEnclaveNotAllowed(_))
+        ));
+    }
+}
@@ -0,0 +1,224 @@
+//! Counters of attestation verifications, exported in the Prometheus text format.
+//!
+//! The enclave can't serve HTTP, so the host application scrapes `render_prometheus` and serves
+//! it on its own metrics endpoint. Labels are the canonical IAS quote statuses, advisory IDs and
+//! the stable rejection codes, so dashboards don't break when `NodeAuthResult` changes.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use enclave_ffi_types::NodeAuthResult;
+
+use super::policy::AttestationPolicy;
+use super::rejection::as_code;
+use super::report::AttestationReport;
+
+/// Upper bounds of the latency histogram buckets, in microseconds, along with their value in
+/// seconds as rendered in the `le` label
+const LATENCY_BUCKETS: &[(u64, &str)] = &[
+    (1_000, "0.001"),
+    (5_000, "0.005"),
+    (10_000, "0.01"),
+    (50_000, "0.05"),
+    (100_000, "0.1"),
+    (500_000, "0.5"),
+    (1_000_000, "1"),
+];
+
+/// Counters of the verifications made so far
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VerificationMetrics {
+    /// Keyed by the IAS spelling of the status
+    statuses: BTreeMap<String, u64>,
+    advisories: BTreeMap<String, u64>,
+    /// Keyed by the stable name of the verdict (see `rejection`)
+    verdicts: BTreeMap<&'static str, u64>,
+    /// Verifications that took at most each of `LATENCY_BUCKETS`, not cumulative
+    latency_buckets: [u64; LATENCY_BUCKETS.len()],
+    latency_sum: u64,
+    latency_count: u64,
+}
+
+impl VerificationMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts a verification of `report` that ended in `verdict` after `latency` microseconds
+    pub fn record(
+        &mut self,
+        report: &AttestationReport,
+        verdict: Result<(), NodeAuthResult>,
+        latency: u64,
+    ) {
+        *self
+            .statuses
+            .entry(report.sgx_quote_status.to_string())
+            .or_insert(0) += 1;
+        for advisory in report.advisory_ids.0.iter() {
+            *self.advisories.entry(advisory.clone()).or_insert(0) += 1;
+        }
+
+        let code = as_code(&verdict.err().unwrap_or(NodeAuthResult::Success));
+        *self.verdicts.entry(code.name).or_insert(0) += 1;
+
+        if let Some(bucket) = LATENCY_BUCKETS
+            .iter()
+            .position(|(bound, _)| latency <= *bound)
+        {
+            self.latency_buckets[bucket] += 1;
+        }
+        self.latency_sum = self.latency_sum.saturating_add(latency);
+        self.latency_count += 1;
+    }
+
+    /// The number of verifications that ended in the verdict named `verdict`
+    pub fn verdict_count(&self, verdict: &str) -> u64 {
+        self.verdicts.get(verdict).copied().unwrap_or(0)
+    }
+
+    pub fn verifications(&self) -> u64 {
+        self.latency_count
+    }
+
+    /// The counters in the Prometheus text exposition format
+    pub fn render_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        render_counter(
+            &mut out,
+            "sgx_attestation_verifications_total",
+            "Attestation verifications, by verdict",
+            "verdict",
+            self.verdicts
+                .iter()
+                .map(|(verdict, count)| (*verdict, *count)),
+        );
+        render_counter(
+            &mut out,
+            "sgx_attestation_quote_status_total",
+            "Verified reports, by quote status",
+            "status",
+            self.statuses
+                .iter()
+                .map(|(status, count)| (status.as_str(), *count)),
+        );
+        render_counter(
+            &mut out,
+            "sgx_attestation_advisories_total",
+            "Advisories in verified reports, by advisory ID",
+            "advisory",
+            self.advisories
+                .iter()
+                .map(|(advisory, count)| (advisory.as_str(), *count)),
+        );
+
+        let name = "sgx_attestation_verification_duration_seconds";
+        writeln!(out, "# HELP {} Duration of attestation verifications", name).unwrap();
+        writeln!(out, "# TYPE {} histogram", name).unwrap();
+        let mut cumulative = 0;
+        for ((_, le), count) in LATENCY_BUCKETS.iter().zip(self.latency_buckets.iter()) {
+            cumulative += count;
+            writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, le, cumulative).unwrap();
+        }
+        writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, self.latency_count).unwrap();
+        writeln!(out, "{}_sum {}", name, self.latency_sum as f64 / 1e6).unwrap();
+        writeln!(out, "{}_count {}", name, self.latency_count).unwrap();
+
+        out
+    }
+}
+
+fn render_counter<'a>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    label: &str,
+    values: impl Iterator<Item = (&'a str, u64)>,
+) {
+    // writing to a String can't fail
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} counter", name).unwrap();
+    for (value, count) in values {
+        writeln!(out, "{}{{{}=\"{}\"}} {}", name, label, value, count).unwrap();
+    }
+}
+
+/// Evaluate the report against the policy, counting the verification in `metrics`. `now` is a
+/// clock in microseconds, used to measure the duration of the verification
+pub fn evaluate_with_metrics(
+    report: &AttestationReport,
+    policy: &AttestationPolicy,
+    metrics: &mut VerificationMetrics,
+    now: &dyn Fn() -> u64,
+) -> Result<(), NodeAuthResult> {
+    let start = now();
+    let res = report.evaluate(policy);
+    metrics.record(report, res, now().saturating_sub(start));
+
+    res
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use std::cell::Cell;
+
+    use crate::registration::report::tests::mock_attestation_report;
+    use crate::registration::report::{AdvisoryIDs, SgxQuoteStatus};
+
+    use super::*;
+
+    pub fn test_render_prometheus() {
+        let mut metrics = VerificationMetrics::new();
+        let policy = AttestationPolicy {
+            accepted_statuses: vec![SgxQuoteStatus::OK, SgxQuoteStatus::SwHardeningNeeded],
+            whitelisted_advisories: vec!["INTEL-SA-00334".to_string()],
+            ..Default::default()
+        };
+        // a clock that advances by 2ms on every reading
+        let clock = Cell::new(0);
+        let now = || {
+            clock.set(clock.get() + 2_000);
+            clock.get()
+        };
+
+        let mut ok = mock_attestation_report();
+        ok.sgx_quote_status = SgxQuoteStatus::OK;
+        let mut hardening = mock_attestation_report();
+        hardening.sgx_quote_status = SgxQuoteStatus::SwHardeningNeeded;
+        hardening.advisory_ids = AdvisoryIDs(vec!["INTEL-SA-00334".to_string()]);
+        let out_of_date = mock_attestation_report();
+
+        for report in [&ok, &hardening, &out_of_date, &ok].iter() {
+            let _ = evaluate_with_metrics(report, &policy, &mut metrics, &now);
+        }
+        assert_eq!(metrics.verifications(), 4);
+        assert_eq!(metrics.verdict_count("accepted"), 3);
+
+        let rendered = metrics.render_prometheus();
+        for expected in [
+            "# TYPE sgx_attestation_verifications_total counter",
+            "sgx_attestation_verifications_total{verdict=\"accepted\"} 3",
+            "sgx_attestation_verifications_total{verdict=\"group_out_of_date\"} 1",
+            "# HELP sgx_attestation_quote_status_total ",
+            "sgx_attestation_quote_status_total{status=\"OK\"} 2",
+            "sgx_attestation_quote_status_total{status=\"GROUP_OUT_OF_DATE\"} 1",
+            "sgx_attestation_advisories_total{advisory=\"INTEL-SA-00334\"} 1",
+            "# TYPE sgx_attestation_verification_duration_seconds histogram",
+            "sgx_attestation_verification_duration_seconds_bucket{le=\"0.001\"} 0",
+            "sgx_attestation_verification_duration_seconds_bucket{le=\"0.005\"} 4",
+            "sgx_attestation_verification_duration_seconds_bucket{le=\"+Inf\"} 4",
+            "sgx_attestation_verification_duration_seconds_sum 0.008",
+            "sgx_attestation_verification_duration_seconds_count 4",
+        ]
+        .iter()
+        {
+            assert!(
+                rendered.lines().any(|line| line.starts_with(expected)),
+                "missing {} in\n{}",
+                expected,
+                rendered
+            );
+        }
+    }
+}
@@ -17,8 +17,34 @@ mod ocalls;
 #[cfg(feature = "SGX_MODE_HW")]
 pub mod print_report;
 
+pub mod ca_bundle;
+pub mod cache;
 pub mod check_patch_level;
+#[cfg(feature = "contract-attestation")]
+pub mod contract_attestation;
+pub mod dcap;
+pub mod encoding;
+pub mod events;
+pub mod evidence;
+pub mod jws;
+pub mod matcher;
+#[cfg(feature = "attestation-metrics")]
+pub mod metrics;
+pub mod peer_filter;
+pub mod platform_info;
+pub mod policy;
+pub mod rate_limit;
+pub mod registry;
+pub mod rejection;
+pub mod replay;
 pub mod seed_service;
+pub mod signing_key;
+pub mod temporal;
+#[cfg(feature = "test")]
+pub mod test_vectors;
+#[cfg(feature = "attestation-tracing")]
+pub mod tracing;
+pub mod trail;
 
 #[cfg(feature = "test")]
 pub mod tests {
@@ -31,12 +57,136 @@ pub mod tests {
 
         count_failures!(failures, {
             report::tests::test_sgx_quote_parse_from();
+            report::tests::test_chunked_quote_body();
+            report::tests::test_quote_status_to_auth_result();
+            report::tests::test_take_bytes_overflow();
+            report::tests::test_specific_errors();
+            report::tests::test_sgx_quote_to_pem();
+            report::tests::test_sgx_quote_status_display();
+            report::tests::test_epid_group_id();
+            report::tests::test_advisory_ids_malformed();
+            report::tests::test_advisory_ids_with_severity();
+            encoding::tests::test_minimal_decoders_match_crates();
+            encoding::tests::test_decode_helpers();
+            report::tests::test_advisory_ids_new_vs_baseline();
+            report::tests::test_advisory_catalog();
+            report::tests::test_advisory_descriptions();
+            report::tests::test_advisory_ids_vulnerable_against();
+            report::tests::test_advisory_breakdown();
+            report::tests::test_tcb_eval_data_number_as_string();
+            report::tests::test_attestation_report_advisory_url();
+            report::tests::test_attestation_report_no_advisory_url();
+            report::tests::test_from_report_bytes_allocates_less();
+            report::tests::test_from_report_bytes_escaped_strings();
+            report::tests::test_report_data_halves();
+            report::tests::test_expected_report_data_binding();
+            report::tests::test_report_data_expected_length();
+            report::tests::test_report_data_merkle_root();
+            report::tests::test_report_data_cert_binding();
+            report::tests::test_enclave_report_attributes();
+            report::tests::test_enclave_report_matches_golden();
+            report::tests::test_extended_enclave_report();
             report::tests::test_attestation_report_from_cert();
+            report::tests::test_attestation_report_from_cert_binding();
+            report::tests::test_attestation_report_raw_quote();
+            report::tests::test_measurements_equal();
+            report::tests::test_attestation_report_verify();
+            report::tests::test_attestation_report_verify_signer_identity();
+            report::tests::test_attestation_report_from_cert_with_json();
+            report::tests::test_attestation_report_from_pem_cert();
+            report::tests::test_attestation_report_from_cert_at();
             report::tests::test_attestation_report_from_cert_invalid();
+            report::tests::test_endorsed_report_from_json_bytes();
+            report::tests::test_attestation_report_separate_intermediates();
             report::tests::test_attestation_report_from_cert_api_version_not_compatible();
+            report::tests::test_attestation_report_api_versions();
             report::tests::test_attestation_report_test();
+            report::tests::test_attestation_report_serde_round_trip();
             report::tests::test_attestation_dcap();
+            report::tests::test_attestation_dcap_collateral_expired();
+            report::tests::test_attestation_dcap_key_type_mismatch();
             report::tests::test_attestation_dcap_temper();
+            report::tests::test_attestation_dcap_temper_mr_enclave();
+            report::tests::test_sgx_quote_v4_parse_from();
+            report::tests::test_attestation_report_quote_version();
+            ca_bundle::tests::test_signing_ca_bundle_verifies_report();
+            ca_bundle::tests::test_signing_ca_bundle_rejects_unsigned();
+            cache::tests::test_verification_cache_negative_ttl();
+            cache::tests::test_verification_cache_positive_ttl();
+            cache::tests::test_verification_cache_whitelist_change();
+            cache::tests::test_verification_cache_stats();
+            contract_attestation::tests::test_contract_attestation_encoding();
+            dcap::tests::test_dcap_quote_parse_from();
+            dcap::tests::test_dcap_quote_v4();
+            dcap::tests::test_dcap_quote_key_type_mismatch();
+            dcap::tests::test_dcap_quote_qe_identity();
+            dcap::tests::test_dcap_quote_isv_signature();
+            dcap::tests::test_dcap_quote_pck_chain();
+            dcap::tests::test_dcap_collateral_parse_from();
+            dcap::tests::test_collateral_store();
+            dcap::tests::test_dcap_status_tolerated_for_fmspc();
+            dcap::tests::test_check_collateral_window();
+            dcap::tests::test_check_collateral_expiry();
+            dcap::tests::test_check_quote_size();
+            dcap::tests::test_cross_check_attestations();
+            events::tests::test_events_emitted_in_order();
+            events::tests::test_registered_event_sink();
+            metrics::tests::test_render_prometheus();
+            evidence::tests::test_reverify_evidence();
+            evidence::tests::test_reverify_evidence_out_of_date();
+            jws::tests::test_attestation_report_from_jws();
+            jws::tests::test_attestation_report_from_jws_invalid();
+            matcher::tests::test_measurement_matcher_leaves();
+            matcher::tests::test_measurement_matcher_combinators();
+            matcher::tests::test_measurement_matcher_nested();
+            matcher::tests::test_quorum_measurement_policy();
+            matcher::tests::test_grandfathered_measurements();
+            matcher::tests::test_committed_measurements();
+            peer_filter::tests::test_filter_peers();
+            platform_info::tests::test_platform_info_parse_from();
+            platform_info::tests::test_platform_info_header();
+            platform_info::tests::test_platform_info_body();
+            platform_info::tests::test_platform_info_consistency();
+            platform_info::tests::test_platform_needs_reboot();
+            platform_info::tests::test_verify_min_psw_svn();
+            policy::tests::test_policy_digest();
+            policy::tests::test_assurance_score_ranks_up_to_date_higher();
+            policy::tests::test_reject_critical_advisories();
+            policy::tests::test_evaluate_policy();
+            policy::tests::test_evaluate_with_custom_rule();
+            policy::tests::test_evaluate_malformed_advisories();
+            policy::tests::test_into_auth_result_with_context();
+            policy::tests::test_register_enclave();
+            policy::tests::test_verify_with_trusted_measurements();
+            policy::tests::test_out_of_date_toggles_are_independent();
+            policy::tests::test_verdict_divergence();
+            policy::tests::test_require_zero_report_data_tail();
+            policy::tests::test_allowed_report_data_versions();
+            policy::tests::test_dcap_only_rejects_epid();
+            policy::tests::test_evaluate_with_warnings();
+            policy::tests::test_from_cert_with_verdict();
+            policy::tests::test_allowed_tcb_eval_data_numbers();
+            policy::tests::test_zero_mr_enclave_rejected();
+            policy::tests::test_security_profiles();
+            rate_limit::tests::test_rate_limit_by_pseudonym();
+            replay::tests::test_replay_guard();
+            replay::tests::test_replay_guard_capacity();
+            registry::tests::test_measurement_registry_records_new();
+            registry::tests::test_measurement_registry_recognizes_known();
+            registry::tests::test_svn_monotonicity_tracker();
+            registry::tests::test_measurement_counter();
+            rejection::tests::test_rejection_codes_are_stable();
+            trail::tests::test_verification_trail_is_reproducible();
+            signing_key::tests::test_signing_key_of_cert();
+            signing_key::tests::test_verify_signing_key_minimum();
+            signing_key::tests::test_chain_signature_algorithms();
+            temporal::tests::test_validity_window_of_cert();
+            temporal::tests::test_temporal_consistency();
+            temporal::tests::test_reattest_deadline();
+            temporal::tests::test_reattestation_queue();
+            tracing::tests::test_evaluate_traced();
+            test_vectors::tests::test_quote_vectors();
+            test_vectors::tests::test_emit_quote_vector();
             cert::tests::test_certificate_valid();
             cert::tests::test_certificate_invalid_configuration_needed();
         });
@@ -22,6 +22,7 @@ use sgx_types::sgx_ql_qv_result_t;
 use enclave_crypto::consts::SigningMethod;
 
 use super::cert::verify_ra_cert;
+use super::policy::{AttestationPolicy, AttestationType};
 use super::seed_exchange::encrypt_seed;
 use core::mem;
 use std::slice;
@@ -33,15 +34,16 @@ use enclave_contract_engine::check_cert_in_current_block;
 use block_verifier::VERIFIED_BLOCK_MESSAGES;
 
 #[cfg(feature = "light-client-validation")]
-fn get_current_block_time_s() -> i64 {
+fn get_current_block_time_s() -> Option<i64> {
     let verified_msgs = VERIFIED_BLOCK_MESSAGES.lock().unwrap();
     let tm_ns = verified_msgs.time();
-    (tm_ns / 1000000000) as i64
+    Some((tm_ns / 1000000000) as i64)
 }
 
+/// Without the light client there's no block time the enclave can trust
 #[cfg(not(feature = "light-client-validation"))]
-fn get_current_block_time_s() -> i64 {
-    return 0 as i64;
+fn get_current_block_time_s() -> Option<i64> {
+    None
 }
 
 fn split_combined_cert(cert: *const u8, cert_len: u32) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
@@ -102,7 +104,7 @@ fn verify_attestation_dcap(
     pub_key: &mut [u8; 32],
 ) -> NodeAuthResult {
     let tm_s = get_current_block_time_s();
-    trace!("Current block time: {}", tm_s);
+    trace!("Current block time: {:?}", tm_s);
 
     // test self
     let report_body = match verify_quote_ecdsa(vec_quote, vec_coll, tm_s) {
@@ -114,7 +116,7 @@ fn verify_attestation_dcap(
             r.0
         }
         Err(e) => {
-            trace!("Remote quote verification failed: {}", e);
+            trace!("Remote quote verification failed: {:?}", e);
             return NodeAuthResult::InvalidCert;
         }
     };
@@ -172,6 +174,7 @@ pub unsafe extern "C" fn ecall_authenticate_new_node(
     let mut target_public_key: [u8; 32] = [0u8; 32];
 
     let (vec_cert, vec_quote, vec_coll) = split_combined_cert(cert, cert_len);
+    let policy = AttestationPolicy::default();
 
     if vec_quote.is_empty() || vec_coll.is_empty() {
         if vec_cert.is_empty() {
@@ -181,6 +184,10 @@ pub unsafe extern "C" fn ecall_authenticate_new_node(
 
         trace!("EPID attestation");
 
+        if let Err(e) = policy.check_attestation_type(AttestationType::Epid) {
+            return e;
+        }
+
         let res = verify_attestation_epid(vec_cert.as_slice(), &mut target_public_key);
         if NodeAuthResult::Success != res {
             return res;
@@ -188,6 +195,10 @@ pub unsafe extern "C" fn ecall_authenticate_new_node(
     } else {
         trace!("DCAP attestation");
 
+        if let Err(e) = policy.check_attestation_type(AttestationType::Dcap) {
+            return e;
+        }
+
         let res = verify_attestation_dcap(&vec_quote, &vec_coll, &mut target_public_key);
         if NodeAuthResult::Success != res {
             return res;
@@ -0,0 +1,129 @@
+//! Bulk filtering of the reports of a set of peers by their enclave measurement.
+//!
+//! When the registration layer refreshes its peers it checks every report against both the
+//! allowlist of measurements and the list of revoked ones. Doing that in one pass over the reports
+//! keeps the sets from being rebuilt for each peer.
+
+use std::collections::HashSet;
+use std::iter::FromIterator;
+
+use log::*;
+
+use super::report::AttestationReport;
+
+/// A set of MRENCLAVE values
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MrEnclaveSet {
+    measurements: HashSet<[u8; 32]>,
+}
+
+impl MrEnclaveSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, mr_enclave: [u8; 32]) {
+        self.measurements.insert(mr_enclave);
+    }
+
+    pub fn contains(&self, mr_enclave: &[u8; 32]) -> bool {
+        self.measurements.contains(mr_enclave)
+    }
+
+    pub fn len(&self) -> usize {
+        self.measurements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.measurements.is_empty()
+    }
+}
+
+impl FromIterator<[u8; 32]> for MrEnclaveSet {
+    fn from_iter<I: IntoIterator<Item = [u8; 32]>>(iter: I) -> Self {
+        Self {
+            measurements: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// The reports of `filter_peers`, split by the outcome of the check
+#[derive(Debug, Default)]
+pub struct PeerFilterResult<'a> {
+    /// Reports of enclaves that are allowed and not revoked
+    pub accepted: Vec<&'a AttestationReport>,
+    /// Reports of enclaves that are revoked, whether they are allowed or not
+    pub revoked: Vec<&'a AttestationReport>,
+    /// Reports of enclaves that aren't revoked, but aren't allowed either
+    pub not_allowed: Vec<&'a AttestationReport>,
+}
+
+/// Splits `reports` into the ones whose MRENCLAVE is in `allow`, the ones whose MRENCLAVE is in
+/// `deny`, and the rest. Revocation takes precedence over the allowlist
+pub fn filter_peers<'a>(
+    reports: &'a [AttestationReport],
+    allow: &MrEnclaveSet,
+    deny: &MrEnclaveSet,
+) -> PeerFilterResult<'a> {
+    let mut result = PeerFilterResult::default();
+
+    for report in reports {
+        let mr_enclave = &report.sgx_quote_body.isv_enclave_report.mr_enclave;
+        if deny.contains(mr_enclave) {
+            warn!("Peer enclave measurement {:?} is revoked", mr_enclave);
+            result.revoked.push(report);
+        } else if allow.contains(mr_enclave) {
+            result.accepted.push(report);
+        } else {
+            warn!("Peer enclave measurement {:?} isn't allowed", mr_enclave);
+            result.not_allowed.push(report);
+        }
+    }
+
+    result
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use crate::registration::report::tests::mock_attestation_report;
+
+    use super::*;
+
+    fn report_of(mr_enclave: [u8; 32]) -> AttestationReport {
+        let mut report = mock_attestation_report();
+        report.sgx_quote_body.isv_enclave_report.mr_enclave = mr_enclave;
+
+        report
+    }
+
+    pub fn test_filter_peers() {
+        let reports = vec![
+            report_of([1u8; 32]),
+            report_of([2u8; 32]),
+            report_of([3u8; 32]),
+            report_of([4u8; 32]),
+            report_of([1u8; 32]),
+        ];
+
+        let allow: MrEnclaveSet = vec![[1u8; 32], [2u8; 32]].into_iter().collect();
+        // allowed, but revoked since
+        let deny: MrEnclaveSet = vec![[2u8; 32], [3u8; 32]].into_iter().collect();
+
+        let result = filter_peers(&reports, &allow, &deny);
+        let measurements = |reports: &[&AttestationReport]| -> Vec<[u8; 32]> {
+            reports
+                .iter()
+                .map(|report| report.sgx_quote_body.isv_enclave_report.mr_enclave)
+                .collect()
+        };
+
+        assert_eq!(measurements(&result.accepted), vec![[1u8; 32], [1u8; 32]]);
+        assert_eq!(measurements(&result.revoked), vec![[2u8; 32], [3u8; 32]]);
+        assert_eq!(measurements(&result.not_allowed), vec![[4u8; 32]]);
+
+        let result = filter_peers(&reports, &MrEnclaveSet::new(), &MrEnclaveSet::new());
+        assert!(result.accepted.is_empty());
+        assert!(result.revoked.is_empty());
+        assert_eq!(result.not_allowed.len(), reports.len());
+    }
+}
@@ -0,0 +1,352 @@
+//! Parsing of the platform info blob IAS attaches to the report when the platform's TCB isn't
+//! up to date.
+//!
+//! The blob is the input of `sgx_report_attestation_status`, and tells which components of the
+//! platform need an update. Since the advisories in the report describe the same issues, the two
+//! are expected to agree with each other.
+
+use std::convert::TryFrom;
+
+use log::*;
+
+use super::report::{AttestationReport, Error, SgxQuoteStatus};
+
+/// `epid_group_flags` - the EPID group of the platform is out of date
+pub const QE_EPID_GROUP_OUT_OF_DATE: u8 = 0x04;
+
+/// `tcb_evaluation_flags` - the CPU microcode is out of date
+pub const QUOTE_CPUSVN_OUT_OF_DATE: u16 = 0x0001;
+/// `tcb_evaluation_flags` - the Quoting Enclave (part of the PSW) is out of date
+pub const QUOTE_ISVSVN_QE_OUT_OF_DATE: u16 = 0x0002;
+/// `tcb_evaluation_flags` - the Provisioning Certification Enclave (part of the PSW) is out of date
+pub const QUOTE_ISVSVN_PCE_OUT_OF_DATE: u16 = 0x0004;
+/// `tcb_evaluation_flags` - the platform needs additional configuration (e.g. in the BIOS)
+pub const PLATFORM_CONFIGURATION_NEEDED: u16 = 0x0008;
+
+/// Size of the TLV header that precedes the platform info in the blob - the type (1 byte), version
+/// (1 byte) and size (2 bytes) of the platform info
+const HEADER_SIZE: usize = 4;
+/// TLV type of `sgx_platform_info_t`
+pub const PLATFORM_INFO_TLV_TYPE: u16 = 21;
+/// Size of `sgx_platform_info_t`
+const PLATFORM_INFO_SIZE: usize = 101;
+
+/// The `sgx_platform_info_t` in the platform info blob, without the TLV header - what
+/// `sgx_report_attestation_status` takes for TCB recovery. The header must be of platform info,
+/// and declare the size of the rest of the blob
+pub fn platform_info_body(blob: &[u8]) -> Result<&[u8], Error> {
+    if blob.len() < HEADER_SIZE {
+        warn!(
+            "Platform info blob parsing error - {} bytes is too short for the header",
+            blob.len()
+        );
+        return Err(Error::ReportParseError);
+    }
+
+    let tlv_type = u16::from(blob[0]);
+    if tlv_type != PLATFORM_INFO_TLV_TYPE {
+        warn!(
+            "Platform info blob parsing error - unexpected TLV type {} (version {})",
+            tlv_type, blob[1]
+        );
+        return Err(Error::ReportParseError);
+    }
+
+    let declared_size = usize::from(u16::from_be_bytes([blob[2], blob[3]]));
+    let body = &blob[HEADER_SIZE..];
+    if declared_size != body.len() || body.len() != PLATFORM_INFO_SIZE {
+        warn!(
+            "Platform info blob parsing error - declared size {}, got {} bytes of platform info",
+            declared_size,
+            body.len()
+        );
+        return Err(Error::ReportParseError);
+    }
+
+    Ok(body)
+}
+
+/// The platform info blob, as returned by IAS in the attestation report
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlatformInfoBlob {
+    /// TLV type and version from the header of the blob
+    tlv_type: u16,
+    tlv_version: u8,
+    pub epid_group_flags: u8,
+    pub tcb_evaluation_flags: u16,
+    pub pse_evaluation_flags: u16,
+    /// CPU SVN (16 bytes) followed by the PCE SVN (2 bytes) of the latest equivalent TCB
+    pub latest_equivalent_tcb_psvn: [u8; 18],
+    pub latest_pse_isvsvn: u16,
+    pub latest_psda_svn: u32,
+    pub xeid: u32,
+    pub gid: u32,
+    /// Signature of IAS over the platform info
+    pub signature: Vec<u8>,
+}
+
+impl PlatformInfoBlob {
+    /// Parse the bytes of the platform info blob. Fields are big endian.
+    pub fn parse_from<'a>(bytes: &'a [u8]) -> Result<Self, Error> {
+        let body = platform_info_body(bytes)?;
+        let tlv_type = u16::from(bytes[0]);
+        let tlv_version = bytes[1];
+
+        let mut pos: usize = 0;
+        let mut take = |n: usize| -> &'a [u8] {
+            let ret = &body[pos..pos + n];
+            pos += n;
+            ret
+        };
+
+        let epid_group_flags = take(1)[0];
+        let tcb_evaluation_flags = u16::from_be_bytes(<[u8; 2]>::try_from(take(2))?);
+        let pse_evaluation_flags = u16::from_be_bytes(<[u8; 2]>::try_from(take(2))?);
+        let latest_equivalent_tcb_psvn = <[u8; 18]>::try_from(take(18))?;
+        let latest_pse_isvsvn = u16::from_be_bytes(<[u8; 2]>::try_from(take(2))?);
+        let latest_psda_svn = u32::from_be_bytes(<[u8; 4]>::try_from(take(4))?);
+        let xeid = u32::from_be_bytes(<[u8; 4]>::try_from(take(4))?);
+        let gid = u32::from_be_bytes(<[u8; 4]>::try_from(take(4))?);
+        let signature = take(64).to_vec();
+
+        Ok(Self {
+            tlv_type,
+            tlv_version,
+            epid_group_flags,
+            tcb_evaluation_flags,
+            pse_evaluation_flags,
+            latest_equivalent_tcb_psvn,
+            latest_pse_isvsvn,
+            latest_psda_svn,
+            xeid,
+            gid,
+            signature,
+        })
+    }
+
+    /// The TLV type and version of the blob, which identify the structure of the platform info
+    pub fn header(&self) -> (u16, u8) {
+        (self.tlv_type, self.tlv_version)
+    }
+
+    /// Whether any component of the platform (microcode, PSW or the EPID group) is out of date
+    pub fn needs_update(&self) -> bool {
+        self.epid_group_flags & QE_EPID_GROUP_OUT_OF_DATE != 0
+            || self.tcb_evaluation_flags
+                & (QUOTE_CPUSVN_OUT_OF_DATE
+                    | QUOTE_ISVSVN_QE_OUT_OF_DATE
+                    | QUOTE_ISVSVN_PCE_OUT_OF_DATE)
+                != 0
+    }
+
+    /// Whether the PSW (QE or PCE) is out of date
+    pub fn needs_psw_update(&self) -> bool {
+        self.tcb_evaluation_flags & (QUOTE_ISVSVN_QE_OUT_OF_DATE | QUOTE_ISVSVN_PCE_OUT_OF_DATE)
+            != 0
+    }
+
+    /// Whether the platform needs additional configuration
+    pub fn needs_configuration(&self) -> bool {
+        self.tcb_evaluation_flags & PLATFORM_CONFIGURATION_NEEDED != 0
+    }
+
+    /// Whether fixing the platform takes a reboot - a microcode update comes with a BIOS update,
+    /// and configuration is changed in the BIOS. A PSW update alone is only a software patch
+    pub fn needs_reboot(&self) -> bool {
+        self.tcb_evaluation_flags & (QUOTE_CPUSVN_OUT_OF_DATE | PLATFORM_CONFIGURATION_NEEDED) != 0
+    }
+}
+
+impl AttestationReport {
+    /// The parsed platform info blob, if the report has one
+    pub fn platform_info(&self) -> Option<Result<PlatformInfoBlob, Error>> {
+        self.platform_info_blob
+            .as_ref()
+            .map(|blob| PlatformInfoBlob::parse_from(blob))
+    }
+
+    /// Whether fixing the platform takes a reboot, according to its platform info blob (see
+    /// `PlatformInfoBlob::needs_reboot`). `None` if the report has no blob, or one that can't be
+    /// parsed
+    pub fn platform_needs_reboot(&self) -> Option<bool> {
+        match self.platform_info()? {
+            Ok(platform_info) => Some(platform_info.needs_reboot()),
+            Err(_) => None,
+        }
+    }
+
+    /// SVN of the platform software, i.e. of the Quoting Enclave of the PSW that produced the
+    /// quote. The platform info blob only has the latest SVNs Intel advertises, not the platform's
+    pub fn psw_svn(&self) -> u16 {
+        self.sgx_quote_body.isv_svn_qe
+    }
+
+    /// Checks that the PSW of the platform is at least at `min`. The SVN comes from the quote, so
+    /// reports are checked whether or not IAS attached a platform info blob
+    pub fn verify_min_psw_svn(&self, min: u16) -> Result<(), Error> {
+        if self.psw_svn() < min {
+            warn!(
+                "Platform software SVN {} is below the minimum {}",
+                self.psw_svn(),
+                min
+            );
+            return Err(Error::ReportValidationError);
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the platform info blob agrees with the advisories of the report - the blob
+    /// should flag something to fix on the platform if and only if there are advisories. A
+    /// mismatch means the report was malformed, or spliced together from different reports.
+    ///
+    /// `SW_HARDENING_NEEDED` advisories are mitigated in the enclave rather than on the platform,
+    /// so they aren't required to be reflected in the blob.
+    pub fn verify_platform_info_consistency(&self) -> Result<(), Error> {
+        let platform_info = match self.platform_info() {
+            Some(platform_info) => platform_info?,
+            None => return Ok(()),
+        };
+
+        let platform_flagged = platform_info.needs_update() || platform_info.needs_configuration();
+        let has_advisories = !self.advisory_ids.0.is_empty();
+
+        if platform_flagged && !has_advisories {
+            warn!("Platform info blob flags an out of date platform, but there are no advisories");
+            return Err(Error::ReportValidationError);
+        }
+
+        if has_advisories
+            && !platform_flagged
+            && self.sgx_quote_status != SgxQuoteStatus::SwHardeningNeeded
+        {
+            warn!(
+                "Report has advisories {:?}, but the platform info blob flags nothing to fix",
+                self.advisory_ids.0
+            );
+            return Err(Error::ReportValidationError);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use crate::registration::report::tests::{mock_attestation_report, mock_platform_info_blob};
+    use crate::registration::report::AdvisoryIDs;
+
+    use super::*;
+
+    pub fn test_platform_info_parse_from() {
+        let platform_info = PlatformInfoBlob::parse_from(&mock_platform_info_blob()).unwrap();
+
+        assert_eq!(platform_info.epid_group_flags, QE_EPID_GROUP_OUT_OF_DATE);
+        assert_eq!(
+            platform_info.tcb_evaluation_flags,
+            QUOTE_CPUSVN_OUT_OF_DATE | PLATFORM_CONFIGURATION_NEEDED
+        );
+        assert_eq!(platform_info.latest_pse_isvsvn, 0x0b);
+        assert_eq!(platform_info.gid, 0x0b2f);
+        assert!(platform_info.needs_update());
+        assert!(!platform_info.needs_psw_update());
+        assert!(platform_info.needs_configuration());
+
+        assert!(PlatformInfoBlob::parse_from(&mock_platform_info_blob()[..100]).is_err());
+    }
+
+    pub fn test_platform_info_header() {
+        let platform_info = PlatformInfoBlob::parse_from(&mock_platform_info_blob()).unwrap();
+        assert_eq!(platform_info.header(), (PLATFORM_INFO_TLV_TYPE, 2));
+
+        let mut blob = mock_platform_info_blob();
+        blob[0] = 22;
+        assert!(PlatformInfoBlob::parse_from(&blob).is_err());
+    }
+
+    pub fn test_platform_info_body() {
+        let blob = mock_platform_info_blob();
+        let body = platform_info_body(&blob).unwrap();
+        let declared_size = u16::from_be_bytes([blob[2], blob[3]]) as usize;
+        assert_eq!(declared_size, blob.len() - HEADER_SIZE);
+        assert_eq!(body.len(), declared_size);
+        assert_eq!(body, &blob[HEADER_SIZE..]);
+
+        // the declared size doesn't match the rest of the blob
+        let mut wrong_size = blob.clone();
+        wrong_size[3] += 1;
+        assert!(platform_info_body(&wrong_size).is_err());
+        assert!(PlatformInfoBlob::parse_from(&wrong_size).is_err());
+
+        // consistent, but too short to be a `sgx_platform_info_t`
+        let mut short = blob[..HEADER_SIZE + 96].to_vec();
+        short[2..4].copy_from_slice(&96u16.to_be_bytes());
+        assert!(platform_info_body(&short).is_err());
+
+        assert!(platform_info_body(&blob[..3]).is_err());
+    }
+
+    pub fn test_platform_info_consistency() {
+        let mut report = mock_attestation_report();
+        report.platform_info_blob = Some(mock_platform_info_blob());
+        report.advisory_ids = AdvisoryIDs(vec!["INTEL-SA-00334".to_string()]);
+        assert!(report.verify_platform_info_consistency().is_ok());
+
+        // the blob says the platform is out of date, but no advisory explains why
+        report.advisory_ids = AdvisoryIDs(vec![]);
+        assert!(report.verify_platform_info_consistency().is_err());
+
+        // advisories, but a blob of a platform with nothing to fix
+        let mut blob = mock_platform_info_blob();
+        blob[4] = 0; // epid_group_flags
+        blob[5..7].copy_from_slice(&[0, 0]); // tcb_evaluation_flags
+        report.platform_info_blob = Some(blob);
+        report.advisory_ids = AdvisoryIDs(vec!["INTEL-SA-00334".to_string()]);
+        assert!(report.verify_platform_info_consistency().is_err());
+
+        report.sgx_quote_status = SgxQuoteStatus::SwHardeningNeeded;
+        assert!(report.verify_platform_info_consistency().is_ok());
+    }
+
+    pub fn test_platform_needs_reboot() {
+        let mut report = mock_attestation_report();
+        assert_eq!(report.platform_needs_reboot(), None);
+
+        // the fixture's platform has out of date microcode, and needs configuration
+        report.platform_info_blob = Some(mock_platform_info_blob());
+        assert_eq!(report.platform_needs_reboot(), Some(true));
+
+        let mut blob = mock_platform_info_blob();
+        blob[5..7].copy_from_slice(&PLATFORM_CONFIGURATION_NEEDED.to_be_bytes());
+        report.platform_info_blob = Some(blob.clone());
+        assert_eq!(report.platform_needs_reboot(), Some(true));
+
+        // only the PSW is out of date
+        blob[5..7].copy_from_slice(&QUOTE_ISVSVN_QE_OUT_OF_DATE.to_be_bytes());
+        report.platform_info_blob = Some(blob);
+        assert_eq!(report.platform_needs_reboot(), Some(false));
+
+        report.platform_info_blob = Some(mock_platform_info_blob()[..100].to_vec());
+        assert_eq!(report.platform_needs_reboot(), None);
+    }
+
+    pub fn test_verify_min_psw_svn() {
+        let mut report = mock_attestation_report();
+        report.sgx_quote_body.isv_svn_qe = 0x0b;
+        assert_eq!(report.psw_svn(), 0x0b);
+
+        // a report without a platform info blob is checked all the same
+        assert!(report.platform_info_blob.is_none());
+        assert!(report.verify_min_psw_svn(0x0a).is_ok());
+        assert!(report.verify_min_psw_svn(0x0b).is_ok());
+        assert!(report.verify_min_psw_svn(0x0c).is_err());
+
+        // the latest PSE SVN in the blob is what Intel advertises, not what the platform runs
+        report.platform_info_blob = Some(mock_platform_info_blob());
+        let platform_info = report.platform_info().unwrap().unwrap();
+        assert!(report
+            .verify_min_psw_svn(platform_info.latest_pse_isvsvn + 1)
+            .is_err());
+        assert!(report.verify_min_psw_svn(0x0b).is_ok());
+    }
+}
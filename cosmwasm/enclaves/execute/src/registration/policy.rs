@@ -0,0 +1,1597 @@
+//! Policy knobs used when deciding whether to trust an attestation report.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use log::*;
+
+use enclave_crypto::{sha_256, HASH_SIZE};
+use enclave_ffi_types::NodeAuthResult;
+
+use super::dcap::DCAP_ACCEPTED_STATUSES;
+use super::matcher::MeasurementMatcher;
+use super::report::{
+    AttestationReport, EnclaveIdentity, EndorsedAttestationReport, EpidGroupId, Error,
+    SgxQuoteStatus, WHITELISTED_ADVISORIES,
+};
+
+/// Minimal TCB evaluation data number we accept from IAS
+pub const MIN_TCB_EVAL_DATA_NUMBER: u16 = 16;
+
+/// Version of the encoding `AttestationPolicy::digest` hashes. Bumped whenever the encoding of a
+/// field changes, or a field is added or removed, so digests of different layouts never collide
+pub const POLICY_DIGEST_VERSION: u8 = 1;
+
+/// The schemes a platform can attest with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttestationType {
+    /// A quote verified by IAS, in a signed attestation report
+    Epid,
+    /// A quote verified in the enclave against Intel's collateral
+    Dcap,
+}
+
+/// The attestation types the network accepts. Networks that completed the migration to DCAP are
+/// built with `dcap-only`, so that EPID reports are rejected outright
+#[cfg(not(feature = "dcap-only"))]
+pub const ACCEPTED_ATTESTATION_TYPES: &[AttestationType] =
+    &[AttestationType::Epid, AttestationType::Dcap];
+#[cfg(feature = "dcap-only")]
+pub const ACCEPTED_ATTESTATION_TYPES: &[AttestationType] = &[AttestationType::Dcap];
+
+/// Describes what a verifier expects from the attestation report of a remote node
+#[derive(Debug, Clone)]
+pub struct AttestationPolicy {
+    /// The attestation types that are accepted at all, e.g. only DCAP after the EPID cutover
+    pub accepted_attestation_types: Vec<AttestationType>,
+    /// EPID quote statuses that are accepted, as long as all the advisories are whitelisted
+    pub accepted_statuses: Vec<SgxQuoteStatus>,
+    /// DCAP quote verification results that are accepted on any platform. Kept apart from
+    /// `accepted_statuses`, so that e.g. tolerating out of date EPID platforms doesn't affect DCAP
+    pub dcap_accepted_statuses: Vec<SgxQuoteStatus>,
+    /// Advisories that may be present on the platform without it being rejected
+    pub whitelisted_advisories: Vec<String>,
+    /// Minimal accepted TCB evaluation data number
+    pub min_tcb_eval_data_number: u16,
+    /// If set, the TCB evaluation data numbers that are accepted instead of any at least
+    /// `min_tcb_eval_data_number`, e.g. both the old and the new one during a TCB recovery
+    pub allowed_tcb_eval_data_numbers: Option<Vec<u16>>,
+    /// The most recent TCB evaluation data number published by Intel
+    pub latest_tcb_eval_data_number: u16,
+    /// The security version of the most recent enclave release
+    pub latest_isv_svn: u16,
+    /// DCAP quote verification results that are tolerated only on specific platform models, keyed
+    /// by FMSPC as lowercase hex (e.g. models that always report `CONFIGURATION_NEEDED`)
+    pub tolerated_statuses_by_fmspc: HashMap<String, Vec<SgxQuoteStatus>>,
+    /// Whether to reject reports with malformed or duplicate advisory IDs, rather than only
+    /// logging them
+    pub reject_malformed_advisories: bool,
+    /// Whether to reject reports with any advisory of `Critical` severity, even a whitelisted one.
+    /// Advisories the enclave doesn't have a severity for yet count as critical
+    pub reject_critical_advisories: bool,
+    /// Minimal size in bits of the RSA key of the report signing cert
+    pub min_signing_key_rsa_bits: usize,
+    /// Minimal size in bits of the ECDSA key of the report signing cert
+    pub min_signing_key_ecdsa_bits: usize,
+    /// Whether the last 32 bytes of `report_data` must be zero, for deployments that only bind
+    /// the public key in its first 32 bytes. Reports bound to a challenge (see
+    /// `challenge_report_data`) have a non-zero tail, and are rejected when this is set
+    pub require_zero_report_data_tail: bool,
+    /// If set, the layout versions of `report_data` (its last byte, see
+    /// `SgxEnclaveReport::report_data_version`) that are accepted, for deployments whose enclaves
+    /// tag the layout. Reports of any other version are rejected
+    pub allowed_report_data_versions: Option<Vec<u8>>,
+    /// If set, the largest size in bytes of the DCAP quotes that are accepted. Quotes embed a PCK
+    /// cert chain and QE authentication data of varying length, so this is a bound, not a size
+    pub max_quote_size: Option<usize>,
+    /// Whether the time a DCAP quote was produced at must be within the validity window of the
+    /// collateral it's verified against, from its issue date to its next update. Quotes produced
+    /// at an unknown time are rejected
+    pub require_timestamp_within_collateral: bool,
+}
+
+impl Default for AttestationPolicy {
+    fn default() -> Self {
+        let mut accepted_statuses = vec![
+            SgxQuoteStatus::OK,
+            SgxQuoteStatus::SwHardeningNeeded,
+            SgxQuoteStatus::ConfigurationAndSwHardeningNeeded,
+        ];
+        // we allow GROUP_OUT_OF_DATE for testnet machines to make joining a bit easier
+        if cfg!(not(feature = "production")) {
+            accepted_statuses.push(SgxQuoteStatus::GroupOutOfDate);
+        }
+
+        Self {
+            accepted_attestation_types: ACCEPTED_ATTESTATION_TYPES.to_vec(),
+            accepted_statuses,
+            dcap_accepted_statuses: DCAP_ACCEPTED_STATUSES.to_vec(),
+            whitelisted_advisories: WHITELISTED_ADVISORIES
+                .iter()
+                .map(|advisory| advisory.to_string())
+                .collect(),
+            min_tcb_eval_data_number: MIN_TCB_EVAL_DATA_NUMBER,
+            allowed_tcb_eval_data_numbers: None,
+            latest_tcb_eval_data_number: MIN_TCB_EVAL_DATA_NUMBER,
+            latest_isv_svn: 0,
+            tolerated_statuses_by_fmspc: HashMap::new(),
+            reject_malformed_advisories: true,
+            reject_critical_advisories: false,
+            min_signing_key_rsa_bits: 2048,
+            min_signing_key_ecdsa_bits: 256,
+            require_zero_report_data_tail: false,
+            allowed_report_data_versions: None,
+            max_quote_size: None,
+            require_timestamp_within_collateral: false,
+        }
+    }
+}
+
+/// Named presets of `AttestationPolicy`, for operators who don't want to configure every field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecurityProfile {
+    /// Only fully up to date platforms - `OK` status, no advisories, and the latest TCB
+    /// evaluation data number
+    Strict,
+    /// Platforms that only need mitigations we whitelisted, never out of date ones
+    Balanced,
+    /// The default policy, which tolerates out of date EPID groups outside of production
+    Permissive,
+}
+
+fn set_accepted(statuses: &mut Vec<SgxQuoteStatus>, status: SgxQuoteStatus, accept: bool) {
+    statuses.retain(|accepted| *accepted != status);
+    if accept {
+        statuses.push(status);
+    }
+}
+
+impl AttestationPolicy {
+    /// The policy of a named preset
+    pub fn profile(profile: SecurityProfile) -> Self {
+        Self::default().with_profile(profile)
+    }
+
+    /// This policy, narrowed down to a named preset. The deployment specific fields, like the
+    /// latest TCB evaluation data number that `Strict` requires, are kept
+    pub fn with_profile(self, profile: SecurityProfile) -> Self {
+        match profile {
+            SecurityProfile::Strict => Self {
+                accepted_statuses: vec![SgxQuoteStatus::OK],
+                dcap_accepted_statuses: vec![SgxQuoteStatus::OK],
+                whitelisted_advisories: vec![],
+                min_tcb_eval_data_number: self.latest_tcb_eval_data_number,
+                ..self
+            },
+            SecurityProfile::Balanced => {
+                let mut policy = self;
+                policy.accept_epid_group_out_of_date(false);
+                policy.accept_dcap_out_of_date(false);
+                policy
+            }
+            SecurityProfile::Permissive => self,
+        }
+    }
+
+    /// Whether EPID reports with `GROUP_OUT_OF_DATE` are accepted. DCAP is not affected
+    pub fn accept_epid_group_out_of_date(&mut self, accept: bool) {
+        set_accepted(
+            &mut self.accepted_statuses,
+            SgxQuoteStatus::GroupOutOfDate,
+            accept,
+        );
+    }
+
+    /// Whether DCAP quotes with `OUT_OF_DATE` are accepted on any platform. EPID is not affected
+    pub fn accept_dcap_out_of_date(&mut self, accept: bool) {
+        set_accepted(
+            &mut self.dcap_accepted_statuses,
+            SgxQuoteStatus::OutOfDate,
+            accept,
+        );
+    }
+
+    /// Rejects attestations of a type the policy doesn't accept. Meant to be checked before the
+    /// attestation is even parsed
+    pub fn check_attestation_type(
+        &self,
+        attestation_type: AttestationType,
+    ) -> Result<(), NodeAuthResult> {
+        if !self.accepted_attestation_types.contains(&attestation_type) {
+            error!(
+                "{:?} attestation is not accepted, only {:?}",
+                attestation_type, self.accepted_attestation_types
+            );
+            return Err(NodeAuthResult::AttestationTypeNotAccepted);
+        }
+
+        Ok(())
+    }
+
+    /// SHA-256 of a canonical encoding of every field of the policy, prefixed with
+    /// `POLICY_DIGEST_VERSION`. Equal policies have the same digest, and the digest doesn't
+    /// depend on how any of the types involved are formatted
+    pub fn digest(&self) -> [u8; HASH_SIZE] {
+        // destructured without `..`, so that a new field doesn't build until it's encoded here
+        let Self {
+            accepted_attestation_types,
+            accepted_statuses,
+            dcap_accepted_statuses,
+            whitelisted_advisories,
+            min_tcb_eval_data_number,
+            allowed_tcb_eval_data_numbers,
+            latest_tcb_eval_data_number,
+            latest_isv_svn,
+            tolerated_statuses_by_fmspc,
+            reject_malformed_advisories,
+            reject_critical_advisories,
+            min_signing_key_rsa_bits,
+            min_signing_key_ecdsa_bits,
+            require_zero_report_data_tail,
+            allowed_report_data_versions,
+            max_quote_size,
+            require_timestamp_within_collateral,
+        } = self;
+
+        let mut encoder = PolicyEncoder(vec![POLICY_DIGEST_VERSION]);
+
+        encoder.length(accepted_attestation_types.len());
+        for attestation_type in accepted_attestation_types {
+            encoder.u8(match attestation_type {
+                AttestationType::Epid => 1,
+                AttestationType::Dcap => 2,
+            });
+        }
+        encoder.statuses(accepted_statuses);
+        encoder.statuses(dcap_accepted_statuses);
+        encoder.length(whitelisted_advisories.len());
+        for advisory in whitelisted_advisories {
+            encoder.str(advisory);
+        }
+        encoder.u16(*min_tcb_eval_data_number);
+        encoder.option(
+            allowed_tcb_eval_data_numbers.as_ref(),
+            |encoder, numbers| {
+                encoder.length(numbers.len());
+                for number in numbers {
+                    encoder.u16(*number);
+                }
+            },
+        );
+        encoder.u16(*latest_tcb_eval_data_number);
+        encoder.u16(*latest_isv_svn);
+
+        // the iteration order of a HashMap isn't stable, so the map is encoded sorted by FMSPC
+        let tolerated: BTreeMap<_, _> = tolerated_statuses_by_fmspc.iter().collect();
+        encoder.length(tolerated.len());
+        for (fmspc, statuses) in tolerated {
+            encoder.str(fmspc);
+            encoder.statuses(statuses);
+        }
+
+        encoder.bool(*reject_malformed_advisories);
+        encoder.bool(*reject_critical_advisories);
+        encoder.u64(*min_signing_key_rsa_bits as u64);
+        encoder.u64(*min_signing_key_ecdsa_bits as u64);
+        encoder.bool(*require_zero_report_data_tail);
+        encoder.option(
+            allowed_report_data_versions.as_ref(),
+            |encoder, versions| {
+                encoder.length(versions.len());
+                versions.iter().for_each(|version| encoder.u8(*version));
+            },
+        );
+        encoder.option(*max_quote_size, |encoder, size| encoder.u64(size as u64));
+        encoder.bool(*require_timestamp_within_collateral);
+
+        sha_256(&encoder.0)
+    }
+}
+
+/// Encodes the fields of a policy for `AttestationPolicy::digest`. Integers are little endian,
+/// lists and strings are prefixed with their length as a u32, options with a 0 or 1 byte, and
+/// quote statuses are encoded as IAS spells them
+struct PolicyEncoder(Vec<u8>);
+
+impl PolicyEncoder {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    fn length(&mut self, length: usize) {
+        self.0.extend_from_slice(&(length as u32).to_le_bytes());
+    }
+
+    fn str(&mut self, value: &str) {
+        self.length(value.len());
+        self.0.extend_from_slice(value.as_bytes());
+    }
+
+    fn statuses(&mut self, statuses: &[SgxQuoteStatus]) {
+        self.length(statuses.len());
+        for status in statuses {
+            self.str(&status.to_string());
+        }
+    }
+
+    fn option<T>(&mut self, value: Option<T>, encode: impl FnOnce(&mut Self, T)) {
+        match value {
+            Some(value) => {
+                self.u8(1);
+                encode(self, value);
+            }
+            None => self.u8(0),
+        }
+    }
+}
+
+/// The outcome of each of the checks the policy makes on a report
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerificationContext {
+    /// Whether the policy accepts EPID attestation at all
+    pub attestation_type_accepted: bool,
+    pub mr_enclave: [u8; 32],
+    pub mr_signer: [u8; 32],
+    pub quote_status: SgxQuoteStatus,
+    /// Whether the policy accepts the quote status
+    pub status_accepted: bool,
+    /// Advisory IDs that are malformed or appear more than once
+    pub malformed_advisories: Vec<String>,
+    /// Whether malformed advisory IDs fail the verification
+    pub reject_malformed_advisories: bool,
+    /// Advisories that aren't whitelisted by the policy
+    pub vulnerable_advisories: Vec<String>,
+    /// Advisories of `Critical` severity, whitelisted or not
+    pub critical_advisories: Vec<String>,
+    /// Whether critical advisories fail the verification
+    pub reject_critical_advisories: bool,
+    /// 0 for reports of API version 4, which predates TCB evaluation data numbers
+    pub tcb_eval_data_number: u16,
+    /// Whether the TCB evaluation data number is recent enough
+    pub tcb_eval_data_number_accepted: bool,
+    /// Whether the last 32 bytes of `report_data` are zero
+    pub report_data_tail_zero: bool,
+    /// Whether a non-zero `report_data` tail fails the verification
+    pub require_zero_report_data_tail: bool,
+    /// The layout version `report_data` is tagged with
+    pub report_data_version: u8,
+    /// Whether the policy accepts the layout version of `report_data`
+    pub report_data_version_accepted: bool,
+}
+
+impl VerificationContext {
+    /// Whether the advisories of the report are acceptable
+    pub fn advisories_passed(&self) -> bool {
+        self.vulnerable_advisories.is_empty()
+            && (self.malformed_advisories.is_empty() || !self.reject_malformed_advisories)
+            && (self.critical_advisories.is_empty() || !self.reject_critical_advisories)
+    }
+
+    /// The verdict of the checks, returning the reason for the rejection if the report isn't
+    /// acceptable
+    pub fn result(&self) -> Result<(), NodeAuthResult> {
+        if !self.attestation_type_accepted {
+            error!("EPID attestation is not accepted");
+            return Err(NodeAuthResult::AttestationTypeNotAccepted);
+        }
+
+        // the measurement of a simulated or failed build, which no allowlist should let through
+        if self.mr_enclave == [0u8; 32] {
+            error!("Enclave measurement is all zeros");
+            return Err(NodeAuthResult::MrEnclaveMismatch);
+        }
+
+        if !self.status_accepted {
+            error!(
+                "Invalid attestation quote status - cannot verify remote node: {:?}",
+                &self.quote_status
+            );
+            return Err(NodeAuthResult::from(&self.quote_status));
+        }
+
+        if !self.malformed_advisories.is_empty() {
+            warn!(
+                "Report has malformed advisory IDs: {:?}",
+                self.malformed_advisories
+            );
+            if self.reject_malformed_advisories {
+                return Err(NodeAuthResult::MalformedAdvisoryIds);
+            }
+        }
+
+        if !self.vulnerable_advisories.is_empty() {
+            error!(
+                "The following vulnerabilities must be mitigated: {:?}",
+                self.vulnerable_advisories
+            );
+            return Err(NodeAuthResult::from(&self.quote_status));
+        }
+
+        if self.reject_critical_advisories && !self.critical_advisories.is_empty() {
+            error!(
+                "The following critical vulnerabilities must be mitigated: {:?}",
+                self.critical_advisories
+            );
+            return Err(NodeAuthResult::CriticalAdvisory);
+        }
+
+        if !self.tcb_eval_data_number_accepted {
+            info!("Got an outdated certificate");
+            return Err(NodeAuthResult::GroupOutOfDate);
+        }
+
+        if self.require_zero_report_data_tail && !self.report_data_tail_zero {
+            warn!("Report data has non-zero bytes after the public key");
+            return Err(NodeAuthResult::MalformedPublicKey);
+        }
+
+        if !self.report_data_version_accepted {
+            warn!(
+                "Report data layout version {} is not supported",
+                self.report_data_version
+            );
+            return Err(NodeAuthResult::UnsupportedReportDataVersion);
+        }
+
+        Ok(())
+    }
+}
+
+/// MRENCLAVEs whose reports were already fully verified, and which are trusted since
+#[derive(Debug, Default)]
+pub struct TrustedMeasurements {
+    measurements: HashSet<[u8; 32]>,
+}
+
+impl TrustedMeasurements {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, mr_enclave: [u8; 32]) {
+        self.measurements.insert(mr_enclave);
+    }
+
+    pub fn contains(&self, mr_enclave: &[u8; 32]) -> bool {
+        self.measurements.contains(mr_enclave)
+    }
+}
+
+/// Verifies an endorsed report, skipping the measurement checks for enclaves in `trusted`.
+///
+/// The signature of the report, its freshness (at most `max_age` seconds old at `now`) and the
+/// policy checks of the platform are enforced either way. Measurements that pass the full
+/// verification are added to `trusted`
+pub fn verify_with_trusted_measurements(
+    endorsed: &EndorsedAttestationReport,
+    policy: &AttestationPolicy,
+    matcher: &MeasurementMatcher,
+    trusted: &mut TrustedMeasurements,
+    now: u64,
+    max_age: u64,
+) -> Result<AttestationReport, NodeAuthResult> {
+    let report =
+        AttestationReport::from_endorsed(endorsed).map_err(|_| NodeAuthResult::InvalidCert)?;
+
+    if report.timestamp > now || now - report.timestamp > max_age {
+        warn!(
+            "Attestation report from {} is not fresh at {}",
+            report.timestamp, now
+        );
+        return Err(NodeAuthResult::InvalidCert);
+    }
+
+    let mr_enclave = report.sgx_quote_body.isv_enclave_report.mr_enclave;
+    let is_trusted = trusted.contains(&mr_enclave);
+    if is_trusted {
+        trace!("Enclave measurement {:?} is trusted", mr_enclave);
+    } else {
+        report.verify_measurement(matcher)?;
+    }
+
+    report.evaluate(policy)?;
+
+    if !is_trusted {
+        trusted.insert(mr_enclave);
+    }
+
+    Ok(report)
+}
+
+/// Verifies the RA cert of a registering node at `time` (seconds since epoch), checks that its
+/// enclave is accepted by `matcher` and evaluates its report against `policy`, returning the
+/// identity of the node's enclave and the public key it bound to the report. `time` decides
+/// whether the certs are still valid, so consensus-critical callers must pass one every node
+/// agrees on, like `AttestationReport::from_cert_at`
+pub fn register_enclave(
+    cert: &[u8],
+    policy: &AttestationPolicy,
+    matcher: &MeasurementMatcher,
+    time: u64,
+) -> Result<(EnclaveIdentity, [u8; 32]), NodeAuthResult> {
+    let report = AttestationReport::from_cert_at(cert, Some(time)).map_err(|e| {
+        warn!(
+            "Failed to verify the RA cert of the registering node: {}",
+            e
+        );
+        NodeAuthResult::InvalidCert
+    })?;
+
+    report.verify_measurement(matcher)?;
+    report.evaluate(policy)?;
+
+    let enclave_report = &report.sgx_quote_body.isv_enclave_report;
+    Ok((
+        enclave_report.identity(),
+        *enclave_report.report_data_pubkey(),
+    ))
+}
+
+/// An attestation path that accepted a platform which the other path rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Divergence {
+    /// EPID accepted the platform, and DCAP rejected it for the given reason
+    EpidOnly(NodeAuthResult),
+    /// DCAP accepted the platform, and EPID rejected it for the given reason
+    DcapOnly(NodeAuthResult),
+}
+
+/// Compares the verdicts of the EPID and DCAP attestations of the same node. While both are in
+/// use, a platform accepted by one and rejected by the other points at an inconsistency between
+/// the policies of the two, or between the data behind them, worth investigating. Verdicts that
+/// agree, including rejections for different reasons, aren't a divergence
+pub fn verdict_divergence(
+    epid_verdict: Result<(), NodeAuthResult>,
+    dcap_verdict: Result<(), NodeAuthResult>,
+) -> Option<Divergence> {
+    let divergence = match (epid_verdict, dcap_verdict) {
+        (Ok(()), Err(reason)) => Divergence::EpidOnly(reason),
+        (Err(reason), Ok(())) => Divergence::DcapOnly(reason),
+        _ => return None,
+    };
+
+    warn!("EPID and DCAP verdicts diverge: {:?}", divergence);
+    Some(divergence)
+}
+
+/// A risk the policy tolerated when it accepted a report
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerificationWarning {
+    /// The quote status isn't `OK`, but the policy accepts it (e.g. `GROUP_OUT_OF_DATE`)
+    StatusTolerated(SgxQuoteStatus),
+    /// The platform is affected by an advisory the policy whitelists
+    AdvisoryWhitelisted(String),
+}
+
+/// The verdict of a policy on a report, along with what it tolerated if it accepted it
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    /// The platform is fully up to date
+    Accept,
+    /// The report is acceptable, but only thanks to the leniency of the policy. The warnings are
+    /// meant to be surfaced to the operator
+    AcceptWithWarnings(Vec<VerificationWarning>),
+    Reject(NodeAuthResult),
+}
+
+impl Verdict {
+    pub fn is_accepted(&self) -> bool {
+        !matches!(self, Verdict::Reject(_))
+    }
+
+    pub fn warnings(&self) -> &[VerificationWarning] {
+        match self {
+            Verdict::AcceptWithWarnings(warnings) => warnings,
+            _ => &[],
+        }
+    }
+}
+
+impl AttestationReport {
+    /// Checks the report against the policy, returning the reason for the rejection if it isn't
+    /// acceptable
+    pub fn evaluate(&self, policy: &AttestationPolicy) -> Result<(), NodeAuthResult> {
+        self.verification_context(policy).result()
+    }
+
+    /// Like `evaluate`, but lists what the policy tolerated when it accepts the report - a quote
+    /// status other than `OK`, and the (whitelisted) advisories of the platform
+    pub fn evaluate_with_warnings(&self, policy: &AttestationPolicy) -> Verdict {
+        if let Err(e) = self.evaluate(policy) {
+            return Verdict::Reject(e);
+        }
+
+        let mut warnings = vec![];
+        if self.sgx_quote_status != SgxQuoteStatus::OK {
+            warnings.push(VerificationWarning::StatusTolerated(
+                self.sgx_quote_status.clone(),
+            ));
+        }
+        for advisory in self.advisory_ids.0.iter() {
+            warnings.push(VerificationWarning::AdvisoryWhitelisted(advisory.clone()));
+        }
+
+        if warnings.is_empty() {
+            return Verdict::Accept;
+        }
+        warn!("Attestation report accepted with warnings: {:?}", warnings);
+        Verdict::AcceptWithWarnings(warnings)
+    }
+
+    /// Runs all the checks of the policy on the report, without stopping at the first failure
+    pub fn verification_context(&self, policy: &AttestationPolicy) -> VerificationContext {
+        let enclave_report = &self.sgx_quote_body.isv_enclave_report;
+
+        VerificationContext {
+            attestation_type_accepted: policy
+                .accepted_attestation_types
+                .contains(&AttestationType::Epid),
+            mr_enclave: enclave_report.mr_enclave,
+            mr_signer: enclave_report.mr_signer,
+            quote_status: self.sgx_quote_status.clone(),
+            status_accepted: policy.accepted_statuses.contains(&self.sgx_quote_status),
+            malformed_advisories: self.advisory_ids.malformed().into_iter().cloned().collect(),
+            reject_malformed_advisories: policy.reject_malformed_advisories,
+            vulnerable_advisories: self
+                .advisory_ids
+                .0
+                .iter()
+                .filter(|advisory| !policy.whitelisted_advisories.contains(advisory))
+                .cloned()
+                .collect(),
+            critical_advisories: self.advisory_ids.critical().into_iter().cloned().collect(),
+            reject_critical_advisories: policy.reject_critical_advisories,
+            tcb_eval_data_number: self.tcb_eval_data_number.unwrap_or(0),
+            tcb_eval_data_number_accepted: match &policy.allowed_tcb_eval_data_numbers {
+                Some(allowed) => self
+                    .tcb_eval_data_number
+                    .map_or(false, |number| allowed.contains(&number)),
+                None => self.tcb_eval_data_number.unwrap_or(0) >= policy.min_tcb_eval_data_number,
+            },
+            report_data_tail_zero: *enclave_report.report_data_tail() == [0u8; 32],
+            require_zero_report_data_tail: policy.require_zero_report_data_tail,
+            report_data_version: enclave_report.report_data_version(),
+            report_data_version_accepted: match &policy.allowed_report_data_versions {
+                Some(allowed) => allowed.contains(&enclave_report.report_data_version()),
+                None => true,
+            },
+        }
+    }
+
+    /// Parses and verifies the signature of the report in `cert` like `from_cert`, but checks it
+    /// against `policy` too. The report is returned along with the verdict of the policy, so that
+    /// a report the policy rejects can still be inspected. Only reports that can't be parsed or
+    /// whose signature is invalid are an error
+    pub fn from_cert_with_verdict(
+        cert: &[u8],
+        policy: &AttestationPolicy,
+    ) -> Result<(Self, Result<(), NodeAuthResult>), Error> {
+        let report = Self::from_cert(cert)?;
+        let verdict = report.evaluate(policy);
+
+        Ok((report, verdict))
+    }
+
+    /// Like `NodeAuthResult::from(&SgxQuoteStatus)`, but according to the whole default policy,
+    /// and along with the outcome of each check that led to the result
+    pub fn into_auth_result_with_context(self) -> (NodeAuthResult, VerificationContext) {
+        let context = self.verification_context(&AttestationPolicy::default());
+        let result = match context.result() {
+            Ok(()) => NodeAuthResult::Success,
+            Err(e) => e,
+        };
+
+        (result, context)
+    }
+
+    /// Rejects the report as `GroupRevoked` if the EPID group of the platform is in
+    /// `revoked_groups`, e.g. when the list is more recent than the one IAS checked against
+    pub fn verify_group_not_revoked(
+        &self,
+        revoked_groups: &[EpidGroupId],
+    ) -> Result<(), NodeAuthResult> {
+        let gid = self.sgx_quote_body.epid_group_id();
+        if gid.is_revoked(revoked_groups) {
+            error!("EPID group {} of the platform is revoked", gid);
+            return Err(NodeAuthResult::GroupRevoked);
+        }
+
+        Ok(())
+    }
+
+    /// Checks the report with custom logic, for site-specific rules `AttestationPolicy` doesn't
+    /// model. The closure sees the whole report, and returns the reason for the rejection if
+    /// there is one
+    pub fn evaluate_with(
+        &self,
+        f: impl Fn(&AttestationReport) -> Result<(), NodeAuthResult>,
+    ) -> Result<(), NodeAuthResult> {
+        f(self).map_err(|e| {
+            error!("Attestation report rejected by custom policy: {:?}", e);
+            e
+        })
+    }
+
+    /// Rates how much the report can be trusted on a scale of 0 to 100, for ranking nodes.
+    ///
+    /// A status that means the quote is not trustworthy (revoked, bad signature, unknown) always
+    /// scores 0. Otherwise the score is the sum of:
+    /// * quote status, up to 50 - `OK` 50, `SW_HARDENING_NEEDED` 40, `CONFIGURATION_NEEDED` 35,
+    ///   `CONFIGURATION_AND_SW_HARDENING_NEEDED` 30, `GROUP_OUT_OF_DATE`/`OUT_OF_DATE` 20,
+    ///   `OUT_OF_DATE_CONFIGURATION_NEEDED` 15
+    /// * advisories, up to 20 - none 20, only whitelisted ones 10, otherwise 0
+    /// * enclave svn, up to 15 - at least the latest release 15, older 5
+    /// * TCB evaluation data number, up to 15 - at least the latest 15, at least the minimum 8,
+    ///   otherwise 0
+    pub fn assurance_score(&self, policy: &AttestationPolicy) -> u8 {
+        let status_score = match self.sgx_quote_status {
+            SgxQuoteStatus::OK => 50,
+            SgxQuoteStatus::SwHardeningNeeded => 40,
+            SgxQuoteStatus::ConfigurationNeeded => 35,
+            SgxQuoteStatus::ConfigurationAndSwHardeningNeeded => 30,
+            SgxQuoteStatus::GroupOutOfDate | SgxQuoteStatus::OutOfDate => 20,
+            SgxQuoteStatus::OutOfDateConfigurationNeeded => 15,
+            _ => return 0,
+        };
+
+        let advisories_score = if self.advisory_ids.0.is_empty() {
+            20
+        } else if self
+            .advisory_ids
+            .0
+            .iter()
+            .all(|advisory| policy.whitelisted_advisories.contains(advisory))
+        {
+            10
+        } else {
+            0
+        };
+
+        let svn_score = if self.sgx_quote_body.isv_enclave_report.isv_svn >= policy.latest_isv_svn {
+            15
+        } else {
+            5
+        };
+
+        let tcb_eval_data_number = self.tcb_eval_data_number.unwrap_or(0);
+        let tcb_score = if tcb_eval_data_number >= policy.latest_tcb_eval_data_number {
+            15
+        } else if tcb_eval_data_number >= policy.min_tcb_eval_data_number {
+            8
+        } else {
+            0
+        };
+
+        status_score + advisories_score + svn_score + tcb_score
+    }
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use crate::registration::dcap::{check_dcap_status, DcapError};
+    use crate::registration::report::tests::{
+        endorsed_report_v4, mock_attestation_report, tls_ra_cert_der_v4,
+    };
+    use crate::registration::report::{
+        expected_report_data, AdvisoryIDs, REPORT_DATA_VERSION_OFFSET,
+    };
+
+    use super::*;
+
+    pub fn test_policy_digest() {
+        let mut tolerated_statuses_by_fmspc = HashMap::new();
+        tolerated_statuses_by_fmspc.insert(
+            "00906ED50000".to_string(),
+            vec![
+                SgxQuoteStatus::ConfigurationNeeded,
+                SgxQuoteStatus::SwHardeningNeeded,
+            ],
+        );
+        tolerated_statuses_by_fmspc
+            .insert("00606A000000".to_string(), vec![SgxQuoteStatus::OutOfDate]);
+        let policy = AttestationPolicy {
+            accepted_attestation_types: vec![AttestationType::Epid, AttestationType::Dcap],
+            accepted_statuses: vec![SgxQuoteStatus::OK, SgxQuoteStatus::SwHardeningNeeded],
+            dcap_accepted_statuses: vec![SgxQuoteStatus::OK],
+            whitelisted_advisories: vec![
+                "INTEL-SA-00334".to_string(),
+                "INTEL-SA-00615".to_string(),
+            ],
+            min_tcb_eval_data_number: 16,
+            allowed_tcb_eval_data_numbers: Some(vec![17, 18]),
+            latest_tcb_eval_data_number: 18,
+            latest_isv_svn: 3,
+            tolerated_statuses_by_fmspc,
+            reject_malformed_advisories: true,
+            reject_critical_advisories: false,
+            min_signing_key_rsa_bits: 3072,
+            min_signing_key_ecdsa_bits: 384,
+            require_zero_report_data_tail: true,
+            allowed_report_data_versions: Some(vec![0, 1]),
+            max_quote_size: None,
+            require_timestamp_within_collateral: true,
+        };
+
+        // pinned, so that a change to the encoding is caught and comes with a version bump
+        assert_eq!(
+            hex::encode(policy.digest()),
+            "05123c6d1214620958c9ce14d95d5859acf6134080fa9da1e3dd51c1fcdaea25"
+        );
+
+        // the FMSPCs were inserted out of order, and the digest doesn't depend on it
+        let mut reinserted = policy.clone();
+        reinserted.tolerated_statuses_by_fmspc = policy
+            .tolerated_statuses_by_fmspc
+            .iter()
+            .map(|(fmspc, statuses)| (fmspc.clone(), statuses.clone()))
+            .collect();
+        assert_eq!(reinserted.digest(), policy.digest());
+
+        let mut changed = policy.clone();
+        changed.max_quote_size = Some(8192);
+        assert_ne!(changed.digest(), policy.digest());
+
+        // a status moved from one list to the next isn't the same policy
+        let mut moved = policy.clone();
+        moved.accepted_statuses.pop();
+        moved
+            .dcap_accepted_statuses
+            .insert(0, SgxQuoteStatus::SwHardeningNeeded);
+        assert_ne!(moved.digest(), policy.digest());
+    }
+
+    pub fn test_assurance_score_ranks_up_to_date_higher() {
+        let policy = AttestationPolicy {
+            whitelisted_advisories: vec!["INTEL-SA-00334".to_string()],
+            min_tcb_eval_data_number: 14,
+            latest_tcb_eval_data_number: 16,
+            latest_isv_svn: 0,
+            ..Default::default()
+        };
+
+        let mut up_to_date = mock_attestation_report();
+        up_to_date.sgx_quote_status = SgxQuoteStatus::OK;
+        up_to_date.tcb_eval_data_number = Some(16);
+
+        let mut out_of_date = mock_attestation_report();
+        out_of_date.sgx_quote_status = SgxQuoteStatus::GroupOutOfDate;
+        out_of_date.tcb_eval_data_number = Some(14);
+        out_of_date.advisory_ids = AdvisoryIDs(vec![
+            "INTEL-SA-00334".to_string(),
+            "INTEL-SA-00161".to_string(),
+        ]);
+
+        assert_eq!(up_to_date.assurance_score(&policy), 100);
+        assert_eq!(out_of_date.assurance_score(&policy), 43);
+        assert!(up_to_date.assurance_score(&policy) > out_of_date.assurance_score(&policy));
+
+        let mut revoked = mock_attestation_report();
+        revoked.sgx_quote_status = SgxQuoteStatus::GroupRevoked;
+        assert_eq!(revoked.assurance_score(&policy), 0);
+    }
+
+    pub fn test_reject_critical_advisories() {
+        let critical = "INTEL-SA-00233".to_string();
+        let informational = "INTEL-SA-00334".to_string();
+        let unclassified = "INTEL-SA-99999".to_string();
+        let mut policy = AttestationPolicy {
+            accepted_statuses: vec![SgxQuoteStatus::SwHardeningNeeded],
+            whitelisted_advisories: vec![
+                critical.clone(),
+                informational.clone(),
+                unclassified.clone(),
+            ],
+            ..Default::default()
+        };
+
+        let mut report = mock_attestation_report();
+        report.sgx_quote_status = SgxQuoteStatus::SwHardeningNeeded;
+        report.advisory_ids = AdvisoryIDs(vec![critical.clone()]);
+        let mut low = mock_attestation_report();
+        low.sgx_quote_status = SgxQuoteStatus::SwHardeningNeeded;
+        low.advisory_ids = AdvisoryIDs(vec![informational]);
+        let mut unknown = mock_attestation_report();
+        unknown.sgx_quote_status = SgxQuoteStatus::SwHardeningNeeded;
+        unknown.advisory_ids = AdvisoryIDs(vec![unclassified]);
+
+        // the whitelist is all that counts by default
+        assert_eq!(report.evaluate(&policy), Ok(()));
+        assert_eq!(low.evaluate(&policy), Ok(()));
+        assert_eq!(unknown.evaluate(&policy), Ok(()));
+
+        policy.reject_critical_advisories = true;
+        let context = report.verification_context(&policy);
+        assert_eq!(context.critical_advisories, vec![critical]);
+        assert!(!context.advisories_passed());
+        assert_eq!(
+            report.evaluate(&policy),
+            Err(NodeAuthResult::CriticalAdvisory)
+        );
+        assert_eq!(low.evaluate(&policy), Ok(()));
+        assert_eq!(
+            unknown.evaluate(&policy),
+            Err(NodeAuthResult::CriticalAdvisory)
+        );
+    }
+
+    pub fn test_evaluate_policy() {
+        let policy = AttestationPolicy {
+            accepted_statuses: vec![SgxQuoteStatus::OK],
+            whitelisted_advisories: vec!["INTEL-SA-00334".to_string()],
+            ..Default::default()
+        };
+
+        let mut report = mock_attestation_report();
+        report.sgx_quote_status = SgxQuoteStatus::OK;
+        report.advisory_ids = AdvisoryIDs(vec!["INTEL-SA-00334".to_string()]);
+        assert_eq!(report.evaluate(&policy), Ok(()));
+
+        report.advisory_ids = AdvisoryIDs(vec!["INTEL-SA-00161".to_string()]);
+        assert_eq!(
+            report.evaluate(&policy),
+            Err(NodeAuthResult::BadQuoteStatus)
+        );
+
+        report.advisory_ids = AdvisoryIDs(vec![]);
+        report.tcb_eval_data_number = Some(MIN_TCB_EVAL_DATA_NUMBER - 1);
+        assert_eq!(
+            report.evaluate(&policy),
+            Err(NodeAuthResult::GroupOutOfDate)
+        );
+
+        let mut report = mock_attestation_report();
+        report.sgx_quote_status = SgxQuoteStatus::GroupOutOfDate;
+        assert_eq!(
+            report.evaluate(&policy),
+            Err(NodeAuthResult::GroupOutOfDate)
+        );
+    }
+
+    pub fn test_evaluate_with_custom_rule() {
+        // only allow out of date platforms to run the enclave with the product id 0
+        let rule = |report: &AttestationReport| {
+            let enclave_report = &report.sgx_quote_body.isv_enclave_report;
+            if report.sgx_quote_status != SgxQuoteStatus::OK && enclave_report.isv_prod_id != 0 {
+                return Err(NodeAuthResult::BadQuoteStatus);
+            }
+            Ok(())
+        };
+
+        let mut report = mock_attestation_report();
+        assert_eq!(report.evaluate_with(rule), Ok(()));
+
+        report.sgx_quote_body.isv_enclave_report.isv_prod_id = 1;
+        assert_eq!(
+            report.evaluate_with(rule),
+            Err(NodeAuthResult::BadQuoteStatus)
+        );
+
+        report.sgx_quote_status = SgxQuoteStatus::OK;
+        assert_eq!(report.evaluate_with(rule), Ok(()));
+    }
+
+    pub fn test_evaluate_malformed_advisories() {
+        let mut policy = AttestationPolicy {
+            accepted_statuses: vec![SgxQuoteStatus::OK],
+            whitelisted_advisories: vec!["INTEL-SA-00334".to_string(), "SA-00334".to_string()],
+            ..Default::default()
+        };
+
+        let mut report = mock_attestation_report();
+        report.sgx_quote_status = SgxQuoteStatus::OK;
+        report.advisory_ids = AdvisoryIDs(vec!["INTEL-SA-00334".to_string()]);
+        assert_eq!(report.evaluate(&policy), Ok(()));
+
+        report.advisory_ids = AdvisoryIDs(vec!["SA-00334".to_string()]);
+        assert_eq!(
+            report.evaluate(&policy),
+            Err(NodeAuthResult::MalformedAdvisoryIds)
+        );
+
+        report.advisory_ids = AdvisoryIDs(vec![
+            "INTEL-SA-00334".to_string(),
+            "INTEL-SA-00334".to_string(),
+        ]);
+        assert_eq!(
+            report.evaluate(&policy),
+            Err(NodeAuthResult::MalformedAdvisoryIds)
+        );
+
+        // only warn
+        policy.reject_malformed_advisories = false;
+        assert_eq!(report.evaluate(&policy), Ok(()));
+        report.advisory_ids = AdvisoryIDs(vec!["SA-00334".to_string()]);
+        assert_eq!(report.evaluate(&policy), Ok(()));
+    }
+
+    pub fn test_into_auth_result_with_context() {
+        let mut report = mock_attestation_report();
+        report.sgx_quote_status = SgxQuoteStatus::OK;
+        report.advisory_ids = AdvisoryIDs(vec!["INTEL-SA-99999".to_string()]);
+        assert_eq!(
+            NodeAuthResult::from(&report.sgx_quote_status),
+            NodeAuthResult::BadQuoteStatus
+        );
+
+        let (result, context) = report.into_auth_result_with_context();
+        assert_eq!(result, NodeAuthResult::BadQuoteStatus);
+        assert!(context.status_accepted);
+        assert!(!context.advisories_passed());
+        assert_eq!(
+            context.vulnerable_advisories,
+            vec!["INTEL-SA-99999".to_string()]
+        );
+        assert!(context.tcb_eval_data_number_accepted);
+
+        let mut report = mock_attestation_report();
+        report.sgx_quote_status = SgxQuoteStatus::OK;
+        let (result, context) = report.into_auth_result_with_context();
+        assert_eq!(result, NodeAuthResult::Success);
+        assert!(context.advisories_passed());
+    }
+
+    pub fn test_register_enclave() {
+        let cert = tls_ra_cert_der_v4();
+        let report = AttestationReport::from_cert(&cert).unwrap();
+        let enclave_report = &report.sgx_quote_body.isv_enclave_report;
+        let time = report.timestamp;
+
+        let policy = AttestationPolicy {
+            accepted_statuses: vec![report.sgx_quote_status.clone()],
+            whitelisted_advisories: report.advisory_ids.0.clone(),
+            min_tcb_eval_data_number: report.tcb_eval_data_number.unwrap_or(0),
+            ..Default::default()
+        };
+        let matcher = MeasurementMatcher::MrSignerWithMinSvn {
+            mr_signer: enclave_report.mr_signer,
+            min_svn: enclave_report.isv_svn,
+        };
+        let (identity, public_key) = register_enclave(&cert, &policy, &matcher, time).unwrap();
+        assert_eq!(identity.mr_enclave, enclave_report.mr_enclave);
+        assert_eq!(identity.mr_signer, enclave_report.mr_signer);
+        assert_eq!(identity.isv_prod_id, enclave_report.isv_prod_id);
+        assert_eq!(identity.isv_svn, enclave_report.isv_svn);
+        assert_eq!(&public_key, enclave_report.report_data_pubkey());
+
+        let violations = [
+            (
+                AttestationPolicy {
+                    accepted_attestation_types: vec![AttestationType::Dcap],
+                    ..policy.clone()
+                },
+                NodeAuthResult::AttestationTypeNotAccepted,
+            ),
+            (
+                AttestationPolicy {
+                    accepted_statuses: vec![SgxQuoteStatus::OK],
+                    ..policy.clone()
+                },
+                NodeAuthResult::from(&report.sgx_quote_status),
+            ),
+            (
+                AttestationPolicy {
+                    min_tcb_eval_data_number: report.tcb_eval_data_number.unwrap_or(0) + 1,
+                    ..policy.clone()
+                },
+                NodeAuthResult::GroupOutOfDate,
+            ),
+            (
+                AttestationPolicy {
+                    allowed_report_data_versions: Some(vec![enclave_report
+                        .report_data_version()
+                        .wrapping_add(1)]),
+                    ..policy.clone()
+                },
+                NodeAuthResult::UnsupportedReportDataVersion,
+            ),
+        ];
+        for (violating, result) in violations.iter() {
+            assert_eq!(
+                register_enclave(&cert, violating, &matcher, time),
+                Err(*result)
+            );
+        }
+
+        // an enclave of a signer other than ours, with an otherwise valid report
+        let foreign_signer = MeasurementMatcher::MrSignerWithMinSvn {
+            mr_signer: [0xab; 32],
+            min_svn: 0,
+        };
+        assert_eq!(
+            register_enclave(&cert, &policy, &foreign_signer, time),
+            Err(NodeAuthResult::MrSignerMismatch)
+        );
+        let other_enclave = MeasurementMatcher::MrEnclave([0xab; 32]);
+        assert_eq!(
+            register_enclave(&cert, &policy, &other_enclave, time),
+            Err(NodeAuthResult::MrEnclaveMismatch)
+        );
+
+        // the signing cert has expired by 2100
+        assert_eq!(
+            register_enclave(&cert, &policy, &matcher, 4102444800),
+            Err(NodeAuthResult::InvalidCert)
+        );
+        assert_eq!(
+            register_enclave(&cert[..cert.len() / 2], &policy, &matcher, time),
+            Err(NodeAuthResult::InvalidCert)
+        );
+    }
+
+    pub fn test_verify_with_trusted_measurements() {
+        let endorsed = endorsed_report_v4();
+        let report = AttestationReport::from_endorsed(&endorsed).unwrap();
+        let mr_enclave = report.sgx_quote_body.isv_enclave_report.mr_enclave;
+        let now = report.timestamp + 60;
+        let max_age = 3600;
+
+        let mut policy = AttestationPolicy {
+            accepted_statuses: vec![report.sgx_quote_status.clone()],
+            whitelisted_advisories: report.advisory_ids.0.clone(),
+            min_tcb_eval_data_number: report.tcb_eval_data_number.unwrap_or(0),
+            ..Default::default()
+        };
+        let rejecting = MeasurementMatcher::AnyOf(vec![]);
+        let accepting = MeasurementMatcher::MrEnclave(mr_enclave);
+        let mut trusted = TrustedMeasurements::new();
+
+        // not trusted yet, so the measurement is checked
+        assert_eq!(
+            verify_with_trusted_measurements(
+                &endorsed,
+                &policy,
+                &rejecting,
+                &mut trusted,
+                now,
+                max_age
+            )
+            .err(),
+            Some(NodeAuthResult::MrEnclaveMismatch)
+        );
+        assert!(!trusted.contains(&mr_enclave));
+
+        assert!(verify_with_trusted_measurements(
+            &endorsed,
+            &policy,
+            &accepting,
+            &mut trusted,
+            now,
+            max_age
+        )
+        .is_ok());
+        assert!(trusted.contains(&mr_enclave));
+
+        // fast path - the measurement checks are skipped
+        assert!(verify_with_trusted_measurements(
+            &endorsed,
+            &policy,
+            &rejecting,
+            &mut trusted,
+            now,
+            max_age
+        )
+        .is_ok());
+
+        // but not the freshness
+        assert_eq!(
+            verify_with_trusted_measurements(
+                &endorsed,
+                &policy,
+                &rejecting,
+                &mut trusted,
+                report.timestamp + max_age + 1,
+                max_age
+            )
+            .err(),
+            Some(NodeAuthResult::InvalidCert)
+        );
+
+        // nor the signature
+        let mut tampered = endorsed_report_v4();
+        tampered.signature[0] ^= 0xff;
+        assert_eq!(
+            verify_with_trusted_measurements(
+                &tampered,
+                &policy,
+                &rejecting,
+                &mut trusted,
+                now,
+                max_age
+            )
+            .err(),
+            Some(NodeAuthResult::InvalidCert)
+        );
+
+        // nor the quote status
+        policy.accepted_statuses = vec![SgxQuoteStatus::OK];
+        assert_eq!(
+            verify_with_trusted_measurements(
+                &endorsed,
+                &policy,
+                &rejecting,
+                &mut trusted,
+                now,
+                max_age
+            )
+            .err(),
+            Some(NodeAuthResult::from(&report.sgx_quote_status))
+        );
+    }
+
+    pub fn test_out_of_date_toggles_are_independent() {
+        let mut policy = AttestationPolicy {
+            accepted_statuses: vec![SgxQuoteStatus::OK],
+            ..Default::default()
+        };
+        let dcap_out_of_date = SgxQuoteStatus::OutOfDate;
+
+        let mut report = mock_attestation_report();
+        report.sgx_quote_status = SgxQuoteStatus::GroupOutOfDate;
+        assert_eq!(
+            report.evaluate(&policy),
+            Err(NodeAuthResult::GroupOutOfDate)
+        );
+        assert_eq!(
+            check_dcap_status(&dcap_out_of_date, None, &policy),
+            Err(DcapError::StatusNotAccepted(dcap_out_of_date.clone()))
+        );
+
+        policy.accept_epid_group_out_of_date(true);
+        assert_eq!(report.evaluate(&policy), Ok(()));
+        assert!(check_dcap_status(&dcap_out_of_date, None, &policy).is_err());
+
+        policy.accept_epid_group_out_of_date(false);
+        policy.accept_dcap_out_of_date(true);
+        assert!(report.evaluate(&policy).is_err());
+        assert_eq!(check_dcap_status(&dcap_out_of_date, None, &policy), Ok(()));
+
+        // the DCAP toggle doesn't let an EPID report with the DCAP status through either
+        report.sgx_quote_status = SgxQuoteStatus::OutOfDate;
+        assert!(report.evaluate(&policy).is_err());
+    }
+
+    pub fn test_verdict_divergence() {
+        let mut policy = AttestationPolicy::profile(SecurityProfile::Strict);
+        policy.accept_epid_group_out_of_date(true);
+
+        // the same outdated platform, through both paths
+        let epid_verdict = mock_attestation_report().evaluate(&policy);
+        let dcap_status = SgxQuoteStatus::OutOfDate;
+        let dcap_verdict = check_dcap_status(&dcap_status, None, &policy)
+            .map_err(|_| NodeAuthResult::from(&dcap_status));
+        assert_eq!(epid_verdict, Ok(()));
+        assert!(dcap_verdict.is_err());
+
+        assert_eq!(
+            verdict_divergence(epid_verdict, dcap_verdict),
+            Some(Divergence::EpidOnly(NodeAuthResult::OutOfDate))
+        );
+        assert_eq!(
+            verdict_divergence(Err(NodeAuthResult::GroupOutOfDate), Ok(())),
+            Some(Divergence::DcapOnly(NodeAuthResult::GroupOutOfDate))
+        );
+
+        // agreeing verdicts
+        assert_eq!(verdict_divergence(Ok(()), Ok(())), None);
+        assert_eq!(
+            verdict_divergence(
+                Err(NodeAuthResult::GroupOutOfDate),
+                Err(NodeAuthResult::BadQuoteStatus)
+            ),
+            None
+        );
+    }
+
+    pub fn test_require_zero_report_data_tail() {
+        let mut policy = AttestationPolicy {
+            accepted_statuses: vec![SgxQuoteStatus::GroupOutOfDate],
+            ..Default::default()
+        };
+
+        let mut report = mock_attestation_report();
+        report.sgx_quote_body.isv_enclave_report.report_data =
+            expected_report_data(&[3u8; 32], None);
+        let mut with_tail = mock_attestation_report();
+        with_tail.sgx_quote_body.isv_enclave_report.report_data =
+            expected_report_data(&[3u8; 32], Some(&[4u8; 32]));
+
+        // not required by default
+        assert_eq!(report.evaluate(&policy), Ok(()));
+        assert_eq!(with_tail.evaluate(&policy), Ok(()));
+
+        policy.require_zero_report_data_tail = true;
+        assert_eq!(report.evaluate(&policy), Ok(()));
+        assert_eq!(
+            with_tail.evaluate(&policy),
+            Err(NodeAuthResult::MalformedPublicKey)
+        );
+
+        // a single stray byte is enough
+        with_tail.sgx_quote_body.isv_enclave_report.report_data =
+            expected_report_data(&[3u8; 32], None);
+        with_tail.sgx_quote_body.isv_enclave_report.report_data[63] = 1;
+        assert!(
+            !with_tail
+                .verification_context(&policy)
+                .report_data_tail_zero
+        );
+        assert!(with_tail.evaluate(&policy).is_err());
+    }
+
+    pub fn test_allowed_report_data_versions() {
+        let mut policy = AttestationPolicy {
+            accepted_statuses: vec![SgxQuoteStatus::GroupOutOfDate],
+            ..Default::default()
+        };
+
+        let mut report = mock_attestation_report();
+        let pubkey = *report
+            .sgx_quote_body
+            .isv_enclave_report
+            .report_data_pubkey();
+        report.sgx_quote_body.isv_enclave_report.report_data[REPORT_DATA_VERSION_OFFSET] = 2;
+        assert_eq!(
+            report
+                .sgx_quote_body
+                .isv_enclave_report
+                .report_data_version(),
+            2
+        );
+
+        // any version is accepted by default
+        assert_eq!(report.evaluate(&policy), Ok(()));
+
+        policy.allowed_report_data_versions = Some(vec![1, 2]);
+        assert_eq!(report.evaluate(&policy), Ok(()));
+
+        report.sgx_quote_body.isv_enclave_report.report_data[REPORT_DATA_VERSION_OFFSET] = 3;
+        let context = report.verification_context(&policy);
+        assert_eq!(context.report_data_version, 3);
+        assert!(!context.report_data_version_accepted);
+        assert_eq!(
+            report.evaluate(&policy),
+            Err(NodeAuthResult::UnsupportedReportDataVersion)
+        );
+
+        // the tag doesn't touch the key
+        assert_eq!(
+            report
+                .sgx_quote_body
+                .isv_enclave_report
+                .report_data_pubkey(),
+            &pubkey
+        );
+
+        // untagged reports are version 0
+        report.sgx_quote_body.isv_enclave_report.report_data[REPORT_DATA_VERSION_OFFSET] = 0;
+        assert_eq!(
+            report.evaluate(&policy),
+            Err(NodeAuthResult::UnsupportedReportDataVersion)
+        );
+        policy.allowed_report_data_versions = Some(vec![0]);
+        assert_eq!(report.evaluate(&policy), Ok(()));
+
+        policy.allowed_report_data_versions = Some(vec![]);
+        report.sgx_quote_body.isv_enclave_report.report_data[REPORT_DATA_VERSION_OFFSET] = 1;
+        assert!(report.evaluate(&policy).is_err());
+    }
+
+    pub fn test_dcap_only_rejects_epid() {
+        let policy = AttestationPolicy {
+            accepted_attestation_types: vec![AttestationType::Dcap],
+            accepted_statuses: vec![SgxQuoteStatus::GroupOutOfDate],
+            min_tcb_eval_data_number: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            policy.check_attestation_type(AttestationType::Epid),
+            Err(NodeAuthResult::AttestationTypeNotAccepted)
+        );
+        assert_eq!(policy.check_attestation_type(AttestationType::Dcap), Ok(()));
+
+        // the v2 EPID report is rejected for its type, before any of its content is checked
+        let (report, verdict) =
+            AttestationReport::from_cert_with_verdict(&tls_ra_cert_der_v4(), &policy).unwrap();
+        assert_eq!(verdict, Err(NodeAuthResult::AttestationTypeNotAccepted));
+
+        let policy = AttestationPolicy {
+            whitelisted_advisories: report.advisory_ids.0.clone(),
+            accepted_attestation_types: vec![AttestationType::Epid, AttestationType::Dcap],
+            ..policy
+        };
+        assert_eq!(policy.check_attestation_type(AttestationType::Epid), Ok(()));
+        assert_eq!(report.evaluate(&policy), Ok(()));
+    }
+
+    pub fn test_evaluate_with_warnings() {
+        let policy = AttestationPolicy::default();
+
+        let mut report = mock_attestation_report();
+        assert_eq!(
+            report.evaluate_with_warnings(&policy),
+            Verdict::AcceptWithWarnings(vec![VerificationWarning::StatusTolerated(
+                SgxQuoteStatus::GroupOutOfDate
+            )])
+        );
+
+        report.advisory_ids = AdvisoryIDs(vec!["INTEL-SA-00334".to_string()]);
+        let verdict = report.evaluate_with_warnings(&policy);
+        assert!(verdict.is_accepted());
+        assert_eq!(
+            verdict.warnings(),
+            &[
+                VerificationWarning::StatusTolerated(SgxQuoteStatus::GroupOutOfDate),
+                VerificationWarning::AdvisoryWhitelisted("INTEL-SA-00334".to_string()),
+            ][..]
+        );
+
+        report.sgx_quote_status = SgxQuoteStatus::OK;
+        report.advisory_ids = AdvisoryIDs(vec![]);
+        assert_eq!(report.evaluate_with_warnings(&policy), Verdict::Accept);
+
+        let strict = AttestationPolicy::profile(SecurityProfile::Strict);
+        let rejected = mock_attestation_report().evaluate_with_warnings(&strict);
+        assert_eq!(rejected, Verdict::Reject(NodeAuthResult::GroupOutOfDate));
+        assert!(!rejected.is_accepted());
+        assert!(rejected.warnings().is_empty());
+    }
+
+    pub fn test_from_cert_with_verdict() {
+        let policy = AttestationPolicy {
+            accepted_statuses: vec![SgxQuoteStatus::OK],
+            ..Default::default()
+        };
+
+        // rejected by the policy, but still parsed
+        let (report, verdict) =
+            AttestationReport::from_cert_with_verdict(&tls_ra_cert_der_v4(), &policy).unwrap();
+        assert_eq!(verdict, Err(NodeAuthResult::GroupOutOfDate));
+        assert_eq!(report.sgx_quote_status, SgxQuoteStatus::GroupOutOfDate);
+        assert_ne!(
+            report.sgx_quote_body.isv_enclave_report.mr_enclave,
+            [0u8; 32]
+        );
+
+        let policy = AttestationPolicy {
+            accepted_statuses: vec![SgxQuoteStatus::GroupOutOfDate],
+            whitelisted_advisories: report.advisory_ids.0.clone(),
+            min_tcb_eval_data_number: 0,
+            ..Default::default()
+        };
+        let (_, verdict) =
+            AttestationReport::from_cert_with_verdict(&tls_ra_cert_der_v4(), &policy).unwrap();
+        assert_eq!(verdict, Ok(()));
+
+        // a report that can't be parsed isn't returned at all
+        assert!(AttestationReport::from_cert_with_verdict(&[0x30, 0x82], &policy).is_err());
+    }
+
+    pub fn test_allowed_tcb_eval_data_numbers() {
+        let mut policy = AttestationPolicy {
+            accepted_statuses: vec![SgxQuoteStatus::GroupOutOfDate],
+            allowed_tcb_eval_data_numbers: Some(vec![15, 17]),
+            ..Default::default()
+        };
+
+        let mut report = mock_attestation_report();
+        report.tcb_eval_data_number = Some(15);
+        assert_eq!(report.evaluate(&policy), Ok(()));
+        report.tcb_eval_data_number = Some(17);
+        assert_eq!(report.evaluate(&policy), Ok(()));
+
+        // above the minimum, but not in the set
+        report.tcb_eval_data_number = Some(16);
+        assert_eq!(
+            report.evaluate(&policy),
+            Err(NodeAuthResult::GroupOutOfDate)
+        );
+        report.tcb_eval_data_number = Some(18);
+        assert_eq!(
+            report.evaluate(&policy),
+            Err(NodeAuthResult::GroupOutOfDate)
+        );
+
+        policy.allowed_tcb_eval_data_numbers = None;
+        assert_eq!(report.evaluate(&policy), Ok(()));
+    }
+
+    pub fn test_zero_mr_enclave_rejected() {
+        let policy = AttestationPolicy {
+            accepted_statuses: vec![SgxQuoteStatus::GroupOutOfDate],
+            ..Default::default()
+        };
+        // an allowlist that accepts anything
+        let matcher = MeasurementMatcher::AllOf(vec![]);
+
+        let mut report = mock_attestation_report();
+        assert_eq!(report.verify_measurement(&matcher), Ok(()));
+        assert_eq!(report.evaluate(&policy), Ok(()));
+
+        report.sgx_quote_body.isv_enclave_report.mr_enclave = [0u8; 32];
+        assert_eq!(report.verify_measurement(&matcher), Ok(()));
+        assert_eq!(
+            report.evaluate(&policy),
+            Err(NodeAuthResult::MrEnclaveMismatch)
+        );
+        assert_eq!(
+            report.into_auth_result_with_context().0,
+            NodeAuthResult::MrEnclaveMismatch
+        );
+    }
+
+    pub fn test_security_profiles() {
+        let strict = AttestationPolicy::profile(SecurityProfile::Strict);
+        let balanced = AttestationPolicy::profile(SecurityProfile::Balanced);
+        let permissive = AttestationPolicy::profile(SecurityProfile::Permissive);
+
+        // up to date, without advisories
+        let mut report = mock_attestation_report();
+        report.sgx_quote_status = SgxQuoteStatus::OK;
+        assert_eq!(report.evaluate(&strict), Ok(()));
+        assert_eq!(report.evaluate(&balanced), Ok(()));
+        assert_eq!(report.evaluate(&permissive), Ok(()));
+
+        // strict doesn't whitelist any advisory
+        report.advisory_ids = AdvisoryIDs(vec!["INTEL-SA-00334".to_string()]);
+        assert_eq!(
+            report.evaluate(&strict),
+            Err(NodeAuthResult::BadQuoteStatus)
+        );
+
+        // needs software mitigations
+        report.sgx_quote_status = SgxQuoteStatus::SwHardeningNeeded;
+        report.advisory_ids = AdvisoryIDs(vec![]);
+        assert!(report.evaluate(&strict).is_err());
+        assert_eq!(report.evaluate(&balanced), Ok(()));
+        assert_eq!(report.evaluate(&permissive), Ok(()));
+
+        // an out of date EPID group
+        let report = mock_attestation_report();
+        assert_eq!(
+            report.evaluate(&strict),
+            Err(NodeAuthResult::GroupOutOfDate)
+        );
+        assert_eq!(
+            report.evaluate(&balanced),
+            Err(NodeAuthResult::GroupOutOfDate)
+        );
+        assert_eq!(
+            report.evaluate(&permissive).is_ok(),
+            cfg!(not(feature = "production"))
+        );
+
+        // strict requires the latest TCB evaluation data number, not just the minimum
+        let deployment = AttestationPolicy {
+            min_tcb_eval_data_number: MIN_TCB_EVAL_DATA_NUMBER,
+            latest_tcb_eval_data_number: MIN_TCB_EVAL_DATA_NUMBER + 2,
+            ..Default::default()
+        };
+        let strict = deployment.clone().with_profile(SecurityProfile::Strict);
+        let balanced = deployment.clone().with_profile(SecurityProfile::Balanced);
+        let permissive = deployment.with_profile(SecurityProfile::Permissive);
+
+        let mut report = mock_attestation_report();
+        report.sgx_quote_status = SgxQuoteStatus::OK;
+        report.tcb_eval_data_number = Some(MIN_TCB_EVAL_DATA_NUMBER + 1);
+        assert_eq!(
+            report.evaluate(&strict),
+            Err(NodeAuthResult::GroupOutOfDate)
+        );
+        assert_eq!(report.evaluate(&balanced), Ok(()));
+        assert_eq!(report.evaluate(&permissive), Ok(()));
+
+        report.tcb_eval_data_number = Some(MIN_TCB_EVAL_DATA_NUMBER + 2);
+        assert_eq!(report.evaluate(&strict), Ok(()));
+    }
+}
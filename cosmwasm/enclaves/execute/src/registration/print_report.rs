@@ -1,5 +1,6 @@
 use crate::registration::{
     cert::{ocall_get_update_info, verify_quote_status},
+    platform_info::platform_info_body,
     report::AttestationReport,
 };
 
@@ -45,12 +46,21 @@ pub fn print_local_report_info(cert: &[u8]) {
 /// # Safety
 /// Placeholder
 pub unsafe fn print_platform_info(report: &AttestationReport) {
-    if let Some(platform_info) = &report.platform_info_blob {
+    if let Some(blob) = &report.platform_info_blob {
+        // the ocall reads a whole `sgx_platform_info_t`, so the blob must hold one
+        let platform_info = match platform_info_body(blob) {
+            Ok(platform_info) => platform_info,
+            Err(_) => {
+                error!("Malformed platform info blob");
+                return;
+            }
+        };
+
         let mut update_info = sgx_update_info_bit_t::default();
         let mut rt = sgx_status_t::default();
         let res = ocall_get_update_info(
             &mut rt as *mut sgx_status_t,
-            platform_info[4..].as_ptr() as *const sgx_platform_info_t,
+            platform_info.as_ptr() as *const sgx_platform_info_t,
             1,
             &mut update_info,
         );
@@ -0,0 +1,119 @@
+//! Rate limiting of attestation attempts per platform.
+//!
+//! Linkable EPID quotes carry a pseudonym that is stable for the platform, so attempts can be
+//! grouped by the physical machine they come from regardless of the key or nonce they use. This
+//! keeps a single machine from flooding the verifier with attestations.
+//!
+//! Unlinkable quotes can't be told apart by platform, so they aren't rate limited here - limiting
+//! them together would let one client lock all the unlinkable platforms out.
+
+use std::collections::HashMap;
+
+use log::*;
+
+use enclave_crypto::{sha_256, HASH_SIZE};
+use enclave_ffi_types::NodeAuthResult;
+
+use super::report::AttestationReport;
+
+impl AttestationReport {
+    /// Key under which attempts of the platform are rate limited - the hash of its EPID
+    /// pseudonym. `None` for reports without a pseudonym (e.g. of unlinkable quotes), whose
+    /// platform can't be identified
+    pub fn rate_limit_key(&self) -> Option<[u8; HASH_SIZE]> {
+        self.epid_pseudonym
+            .as_ref()
+            .map(|pseudonym| sha_256(pseudonym))
+    }
+}
+
+/// Limits each platform to `max_attempts` attestations in a window of `window` seconds
+pub struct PlatformRateLimiter {
+    max_attempts: usize,
+    window: u64,
+    /// Times (seconds since epoch) of the attempts within the window, per platform
+    attempts: HashMap<[u8; HASH_SIZE], Vec<u64>>,
+}
+
+impl PlatformRateLimiter {
+    pub fn new(max_attempts: usize, window: u64) -> Self {
+        Self {
+            max_attempts,
+            window,
+            attempts: HashMap::new(),
+        }
+    }
+
+    /// Records an attempt of the platform of `report` at `now`, rejecting it if the platform
+    /// already used up its attempts in the current window. Attempts of unidentified platforms
+    /// (see `rate_limit_key`) are always let through
+    pub fn check_and_record(
+        &mut self,
+        report: &AttestationReport,
+        now: u64,
+    ) -> Result<(), NodeAuthResult> {
+        let key = match report.rate_limit_key() {
+            Some(key) => key,
+            None => return Ok(()),
+        };
+
+        let window = self.window;
+        let attempts = self.attempts.entry(key).or_default();
+        attempts.retain(|time| now.saturating_sub(*time) < window);
+
+        if attempts.len() >= self.max_attempts {
+            warn!(
+                "Platform made {} attestation attempts in the last {} seconds",
+                attempts.len(),
+                window
+            );
+            return Err(NodeAuthResult::RateLimited);
+        }
+
+        attempts.push(now);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use crate::registration::report::tests::mock_attestation_report;
+
+    use super::*;
+
+    pub fn test_rate_limit_by_pseudonym() {
+        let mut first = mock_attestation_report();
+        first.epid_pseudonym = Some(vec![1u8; 128]);
+        first.nonce = Some("first".to_string());
+
+        // same platform, different challenge
+        let mut second = mock_attestation_report();
+        second.epid_pseudonym = Some(vec![1u8; 128]);
+        second.nonce = Some("second".to_string());
+
+        let mut other_platform = mock_attestation_report();
+        other_platform.epid_pseudonym = Some(vec![2u8; 128]);
+
+        assert_eq!(first.rate_limit_key(), second.rate_limit_key());
+        assert_ne!(first.rate_limit_key(), other_platform.rate_limit_key());
+
+        let mut limiter = PlatformRateLimiter::new(1, 60);
+        assert_eq!(limiter.check_and_record(&first, 1000), Ok(()));
+        assert_eq!(
+            limiter.check_and_record(&second, 1010),
+            Err(NodeAuthResult::RateLimited)
+        );
+        assert_eq!(limiter.check_and_record(&other_platform, 1010), Ok(()));
+
+        // a new window
+        assert_eq!(limiter.check_and_record(&second, 1060), Ok(()));
+
+        // unlinkable platforms don't share a bucket they could be locked out of
+        let mut unlinkable = mock_attestation_report();
+        unlinkable.epid_pseudonym = None;
+        assert_eq!(unlinkable.rate_limit_key(), None);
+        for now in 1000..1010 {
+            assert_eq!(limiter.check_and_record(&unlinkable, now), Ok(()));
+        }
+    }
+}
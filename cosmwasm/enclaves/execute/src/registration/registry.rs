@@ -0,0 +1,252 @@
+//! Bookkeeping of the enclave measurements that were seen during registration.
+//!
+//! An MRENCLAVE that suddenly shows up on the network without ever being seen before may be a sign
+//! of an attack, so we keep track of when each measurement was first observed.
+
+use std::collections::HashMap;
+
+use log::*;
+
+use enclave_ffi_types::NodeAuthResult;
+
+use super::policy::AttestationPolicy;
+use super::report::{AttestationReport, Error};
+
+/// The result of looking up a measurement in the `MeasurementRegistry`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MeasurementSighting {
+    /// The measurement was first seen before the registry cutoff
+    Known { first_seen: u64 },
+    /// The measurement was first seen after the registry cutoff (possibly right now). The report is
+    /// still accepted, but this should be surfaced to the operator
+    New { first_seen: u64 },
+}
+
+impl MeasurementSighting {
+    pub fn is_new(&self) -> bool {
+        matches!(self, MeasurementSighting::New { .. })
+    }
+}
+
+/// Records the first time each MRENCLAVE was observed
+pub struct MeasurementRegistry {
+    /// Measurements first seen at or after this time (seconds since epoch) are flagged as new
+    cutoff: u64,
+    first_seen: HashMap<[u8; 32], u64>,
+}
+
+impl MeasurementRegistry {
+    pub fn new(cutoff: u64) -> Self {
+        Self {
+            cutoff,
+            first_seen: HashMap::new(),
+        }
+    }
+
+    /// Time (seconds since epoch) at which `mr_enclave` was first observed, if ever
+    pub fn first_seen(&self, mr_enclave: &[u8; 32]) -> Option<u64> {
+        self.first_seen.get(mr_enclave).copied()
+    }
+
+    /// Accepts the report's measurement, recording it at `now` if it was never seen before.
+    ///
+    /// Measurements that were first observed at or after the configured cutoff are flagged as
+    /// `MeasurementSighting::New`
+    pub fn verify_known_or_record(
+        &mut self,
+        report: &AttestationReport,
+        now: u64,
+    ) -> MeasurementSighting {
+        let mr_enclave = report.sgx_quote_body.isv_enclave_report.mr_enclave;
+        let first_seen = *self.first_seen.entry(mr_enclave).or_insert(now);
+
+        if first_seen < self.cutoff {
+            MeasurementSighting::Known { first_seen }
+        } else {
+            warn!(
+                "Enclave measurement {:?} was first seen at {}, after the cutoff {}",
+                mr_enclave, first_seen, self.cutoff
+            );
+            MeasurementSighting::New { first_seen }
+        }
+    }
+}
+
+/// Records the highest enclave svn seen for each (MRSIGNER, product id), to detect rollbacks to
+/// older, possibly vulnerable, releases of an enclave
+#[derive(Default)]
+pub struct SvnMonotonicityTracker {
+    highest_svn: HashMap<([u8; 32], u16), u16>,
+}
+
+impl SvnMonotonicityTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The highest svn seen for the product `isv_prod_id` of `mr_signer`, if any
+    pub fn highest_svn(&self, mr_signer: &[u8; 32], isv_prod_id: u16) -> Option<u16> {
+        self.highest_svn.get(&(*mr_signer, isv_prod_id)).copied()
+    }
+
+    /// Rejects the report if its enclave svn is lower than one seen before for the same product,
+    /// and otherwise records it
+    pub fn check_and_record(&mut self, report: &AttestationReport) -> Result<(), Error> {
+        let enclave_report = &report.sgx_quote_body.isv_enclave_report;
+        let highest_svn = self
+            .highest_svn
+            .entry((enclave_report.mr_signer, enclave_report.isv_prod_id))
+            .or_insert(enclave_report.isv_svn);
+
+        if enclave_report.isv_svn < *highest_svn {
+            warn!(
+                "Enclave svn {} of product {} is lower than the previously seen {}",
+                enclave_report.isv_svn, enclave_report.isv_prod_id, highest_svn
+            );
+            return Err(Error::ReportValidationError);
+        }
+
+        *highest_svn = enclave_report.isv_svn;
+        Ok(())
+    }
+}
+
+/// Counts the nodes that registered with each MRENCLAVE, to plan upgrades
+#[derive(Debug, Default)]
+pub struct MeasurementCounter {
+    counts: HashMap<[u8; 32], u64>,
+    total: u64,
+}
+
+impl MeasurementCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Evaluates the report against the policy, and counts its measurement if it's accepted
+    pub fn verify_and_count(
+        &mut self,
+        report: &AttestationReport,
+        policy: &AttestationPolicy,
+    ) -> Result<(), NodeAuthResult> {
+        report.evaluate(policy)?;
+
+        let mr_enclave = report.sgx_quote_body.isv_enclave_report.mr_enclave;
+        *self.counts.entry(mr_enclave).or_insert(0) += 1;
+        self.total += 1;
+
+        Ok(())
+    }
+
+    /// Number of accepted reports of `mr_enclave`
+    pub fn count(&self, mr_enclave: &[u8; 32]) -> u64 {
+        self.counts.get(mr_enclave).copied().unwrap_or(0)
+    }
+
+    /// Number of accepted reports of each measurement
+    pub fn counts(&self) -> &HashMap<[u8; 32], u64> {
+        &self.counts
+    }
+
+    /// Number of accepted reports, of all measurements
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use crate::registration::report::tests::mock_attestation_report;
+    use crate::registration::report::SgxQuoteStatus;
+
+    use super::*;
+
+    pub fn test_measurement_registry_records_new() {
+        let report = mock_attestation_report();
+        let mut registry = MeasurementRegistry::new(1000);
+
+        let mr_enclave = report.sgx_quote_body.isv_enclave_report.mr_enclave;
+        assert_eq!(registry.first_seen(&mr_enclave), None);
+
+        let sighting = registry.verify_known_or_record(&report, 2000);
+        assert_eq!(sighting, MeasurementSighting::New { first_seen: 2000 });
+        assert_eq!(registry.first_seen(&mr_enclave), Some(2000));
+
+        // seeing it again later doesn't move the first-seen time
+        let sighting = registry.verify_known_or_record(&report, 3000);
+        assert_eq!(sighting, MeasurementSighting::New { first_seen: 2000 });
+    }
+
+    pub fn test_measurement_registry_recognizes_known() {
+        let report = mock_attestation_report();
+        let mut registry = MeasurementRegistry::new(1000);
+
+        let sighting = registry.verify_known_or_record(&report, 500);
+        assert!(!sighting.is_new());
+
+        let sighting = registry.verify_known_or_record(&report, 2000);
+        assert_eq!(sighting, MeasurementSighting::Known { first_seen: 500 });
+
+        let mut other = mock_attestation_report();
+        other.sgx_quote_body.isv_enclave_report.mr_enclave = [7u8; 32];
+        assert!(registry.verify_known_or_record(&other, 2000).is_new());
+    }
+
+    pub fn test_svn_monotonicity_tracker() {
+        let mut tracker = SvnMonotonicityTracker::new();
+
+        let mut report = mock_attestation_report();
+        let mr_signer = report.sgx_quote_body.isv_enclave_report.mr_signer;
+        report.sgx_quote_body.isv_enclave_report.isv_svn = 2;
+        assert!(tracker.check_and_record(&report).is_ok());
+        assert_eq!(tracker.highest_svn(&mr_signer, 0), Some(2));
+
+        // a rollback
+        report.sgx_quote_body.isv_enclave_report.isv_svn = 1;
+        assert!(tracker.check_and_record(&report).is_err());
+        assert_eq!(tracker.highest_svn(&mr_signer, 0), Some(2));
+
+        report.sgx_quote_body.isv_enclave_report.isv_svn = 2;
+        assert!(tracker.check_and_record(&report).is_ok());
+        report.sgx_quote_body.isv_enclave_report.isv_svn = 3;
+        assert!(tracker.check_and_record(&report).is_ok());
+        assert_eq!(tracker.highest_svn(&mr_signer, 0), Some(3));
+
+        // other products are tracked separately
+        report.sgx_quote_body.isv_enclave_report.isv_prod_id = 1;
+        report.sgx_quote_body.isv_enclave_report.isv_svn = 1;
+        assert!(tracker.check_and_record(&report).is_ok());
+    }
+
+    pub fn test_measurement_counter() {
+        let policy = AttestationPolicy {
+            accepted_statuses: vec![SgxQuoteStatus::OK],
+            ..Default::default()
+        };
+        let report_of = |mr_enclave: [u8; 32]| {
+            let mut report = mock_attestation_report();
+            report.sgx_quote_status = SgxQuoteStatus::OK;
+            report.sgx_quote_body.isv_enclave_report.mr_enclave = mr_enclave;
+            report
+        };
+
+        let mut counter = MeasurementCounter::new();
+        for mr_enclave in [[1u8; 32], [2u8; 32], [1u8; 32], [1u8; 32]].iter() {
+            assert_eq!(
+                counter.verify_and_count(&report_of(*mr_enclave), &policy),
+                Ok(())
+            );
+        }
+
+        // rejected reports aren't counted
+        let mut rejected = report_of([2u8; 32]);
+        rejected.sgx_quote_status = SgxQuoteStatus::GroupOutOfDate;
+        assert!(counter.verify_and_count(&rejected, &policy).is_err());
+
+        assert_eq!(counter.count(&[1u8; 32]), 3);
+        assert_eq!(counter.count(&[2u8; 32]), 1);
+        assert_eq!(counter.count(&[3u8; 32]), 0);
+        assert_eq!(counter.counts().len(), 2);
+        assert_eq!(counter.total(), 4);
+    }
+}
@@ -0,0 +1,218 @@
+//! Stable codes for the outcome of a verification, for API consumers.
+//!
+//! `NodeAuthResult` is an FFI type whose variants may be reordered or extended between releases,
+//! so clients shouldn't switch on its discriminant. The codes here are the wire contract instead,
+//! and never change once assigned:
+//!
+//! | code | name                                       | `NodeAuthResult`                    |
+//! |------|--------------------------------------------|-------------------------------------|
+//! | 0    | `accepted`                                 | `Success`                           |
+//! | 101  | `group_out_of_date`                        | `GroupOutOfDate`                    |
+//! | 102  | `signature_invalid`                        | `SignatureInvalid`                  |
+//! | 103  | `signature_revoked`                        | `SignatureRevoked`                  |
+//! | 104  | `group_revoked`                            | `GroupRevoked`                      |
+//! | 105  | `key_revoked`                              | `KeyRevoked`                        |
+//! | 106  | `sigrl_version_mismatch`                   | `SigrlVersionMismatch`              |
+//! | 107  | `configuration_needed`                     | `ConfigurationNeeded`               |
+//! | 108  | `sw_hardening_and_configuration_needed`    | `SwHardeningAndConfigurationNeeded` |
+//! | 109  | `bad_quote_status`                         | `BadQuoteStatus`                    |
+//! | 110  | `unexpected_quote_status`                  | `EnclaveQuoteStatus`                |
+//! | 111  | `out_of_date`                              | `OutOfDate`                         |
+//! | 112  | `out_of_date_configuration_needed`         | `OutOfDateConfigurationNeeded`      |
+//! | 113  | `sw_hardening_needed`                      | `SwHardeningNeeded`                 |
+//! | 114  | `unknown_quote_status`                     | `UnknownQuoteStatus`                |
+//! | 115  | `critical_advisory`                        | `CriticalAdvisory`                  |
+//! | 201  | `mr_enclave_mismatch`                      | `MrEnclaveMismatch`                 |
+//! | 202  | `mr_signer_mismatch`                       | `MrSignerMismatch`                  |
+//! | 301  | `invalid_input`                            | `InvalidInput`                      |
+//! | 302  | `invalid_cert`                             | `InvalidCert`                       |
+//! | 303  | `malformed_public_key`                     | `MalformedPublicKey`                |
+//! | 304  | `attestation_type_not_accepted`            | `AttestationTypeNotAccepted`        |
+//! | 305  | `unsupported_report_data_version`          | `UnsupportedReportDataVersion`      |
+//! | 306  | `malformed_advisory_ids`                   | `MalformedAdvisoryIds`              |
+//! | 307  | `rate_limited`                             | `RateLimited`                       |
+//! | 901  | `storage_error`                            | `CantWriteToStorage`                |
+//! | 902  | `seed_encryption_failed`                   | `SeedEncryptionFailed`              |
+//! | 903  | `allocation_failed`                        | `MemorySafetyAllocationError`       |
+//! | 904  | `internal_error`                           | `Panic`                             |
+//!
+//! Codes in the 100s are about the platform, 200s about the enclave, 300s about the input, and
+//! 900s are failures of the verifier itself.
+
+use enclave_ffi_types::NodeAuthResult;
+
+/// The stable code of a verification outcome
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RejectionCode {
+    pub code: u16,
+    pub name: &'static str,
+}
+
+impl RejectionCode {
+    const fn new(code: u16, name: &'static str) -> Self {
+        Self { code, name }
+    }
+
+    pub fn is_accepted(&self) -> bool {
+        self.code == 0
+    }
+}
+
+impl std::fmt::Display for RejectionCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.name, self.code)
+    }
+}
+
+/// The stable code of `result`, as documented in the module
+pub fn as_code(result: &NodeAuthResult) -> RejectionCode {
+    match result {
+        NodeAuthResult::Success => RejectionCode::new(0, "accepted"),
+        NodeAuthResult::GroupOutOfDate => RejectionCode::new(101, "group_out_of_date"),
+        NodeAuthResult::SignatureInvalid => RejectionCode::new(102, "signature_invalid"),
+        NodeAuthResult::SignatureRevoked => RejectionCode::new(103, "signature_revoked"),
+        NodeAuthResult::GroupRevoked => RejectionCode::new(104, "group_revoked"),
+        NodeAuthResult::KeyRevoked => RejectionCode::new(105, "key_revoked"),
+        NodeAuthResult::SigrlVersionMismatch => RejectionCode::new(106, "sigrl_version_mismatch"),
+        NodeAuthResult::ConfigurationNeeded => RejectionCode::new(107, "configuration_needed"),
+        NodeAuthResult::SwHardeningAndConfigurationNeeded => {
+            RejectionCode::new(108, "sw_hardening_and_configuration_needed")
+        }
+        NodeAuthResult::BadQuoteStatus => RejectionCode::new(109, "bad_quote_status"),
+        NodeAuthResult::EnclaveQuoteStatus => RejectionCode::new(110, "unexpected_quote_status"),
+        NodeAuthResult::OutOfDate => RejectionCode::new(111, "out_of_date"),
+        NodeAuthResult::OutOfDateConfigurationNeeded => {
+            RejectionCode::new(112, "out_of_date_configuration_needed")
+        }
+        NodeAuthResult::SwHardeningNeeded => RejectionCode::new(113, "sw_hardening_needed"),
+        NodeAuthResult::UnknownQuoteStatus => RejectionCode::new(114, "unknown_quote_status"),
+        NodeAuthResult::CriticalAdvisory => RejectionCode::new(115, "critical_advisory"),
+        NodeAuthResult::MrEnclaveMismatch => RejectionCode::new(201, "mr_enclave_mismatch"),
+        NodeAuthResult::MrSignerMismatch => RejectionCode::new(202, "mr_signer_mismatch"),
+        NodeAuthResult::InvalidInput => RejectionCode::new(301, "invalid_input"),
+        NodeAuthResult::InvalidCert => RejectionCode::new(302, "invalid_cert"),
+        NodeAuthResult::MalformedPublicKey => RejectionCode::new(303, "malformed_public_key"),
+        NodeAuthResult::AttestationTypeNotAccepted => {
+            RejectionCode::new(304, "attestation_type_not_accepted")
+        }
+        NodeAuthResult::UnsupportedReportDataVersion => {
+            RejectionCode::new(305, "unsupported_report_data_version")
+        }
+        NodeAuthResult::MalformedAdvisoryIds => RejectionCode::new(306, "malformed_advisory_ids"),
+        NodeAuthResult::RateLimited => RejectionCode::new(307, "rate_limited"),
+        NodeAuthResult::CantWriteToStorage => RejectionCode::new(901, "storage_error"),
+        NodeAuthResult::SeedEncryptionFailed => RejectionCode::new(902, "seed_encryption_failed"),
+        NodeAuthResult::MemorySafetyAllocationError => RejectionCode::new(903, "allocation_failed"),
+        NodeAuthResult::Panic => RejectionCode::new(904, "internal_error"),
+    }
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    pub fn test_rejection_codes_are_stable() {
+        let documented = [
+            (NodeAuthResult::Success, 0, "accepted"),
+            (NodeAuthResult::GroupOutOfDate, 101, "group_out_of_date"),
+            (NodeAuthResult::SignatureInvalid, 102, "signature_invalid"),
+            (NodeAuthResult::SignatureRevoked, 103, "signature_revoked"),
+            (NodeAuthResult::GroupRevoked, 104, "group_revoked"),
+            (NodeAuthResult::KeyRevoked, 105, "key_revoked"),
+            (
+                NodeAuthResult::SigrlVersionMismatch,
+                106,
+                "sigrl_version_mismatch",
+            ),
+            (
+                NodeAuthResult::ConfigurationNeeded,
+                107,
+                "configuration_needed",
+            ),
+            (
+                NodeAuthResult::SwHardeningAndConfigurationNeeded,
+                108,
+                "sw_hardening_and_configuration_needed",
+            ),
+            (NodeAuthResult::BadQuoteStatus, 109, "bad_quote_status"),
+            (
+                NodeAuthResult::EnclaveQuoteStatus,
+                110,
+                "unexpected_quote_status",
+            ),
+            (NodeAuthResult::OutOfDate, 111, "out_of_date"),
+            (
+                NodeAuthResult::OutOfDateConfigurationNeeded,
+                112,
+                "out_of_date_configuration_needed",
+            ),
+            (
+                NodeAuthResult::SwHardeningNeeded,
+                113,
+                "sw_hardening_needed",
+            ),
+            (
+                NodeAuthResult::UnknownQuoteStatus,
+                114,
+                "unknown_quote_status",
+            ),
+            (NodeAuthResult::CriticalAdvisory, 115, "critical_advisory"),
+            (
+                NodeAuthResult::MrEnclaveMismatch,
+                201,
+                "mr_enclave_mismatch",
+            ),
+            (NodeAuthResult::MrSignerMismatch, 202, "mr_signer_mismatch"),
+            (NodeAuthResult::InvalidInput, 301, "invalid_input"),
+            (NodeAuthResult::InvalidCert, 302, "invalid_cert"),
+            (
+                NodeAuthResult::MalformedPublicKey,
+                303,
+                "malformed_public_key",
+            ),
+            (
+                NodeAuthResult::AttestationTypeNotAccepted,
+                304,
+                "attestation_type_not_accepted",
+            ),
+            (
+                NodeAuthResult::UnsupportedReportDataVersion,
+                305,
+                "unsupported_report_data_version",
+            ),
+            (
+                NodeAuthResult::MalformedAdvisoryIds,
+                306,
+                "malformed_advisory_ids",
+            ),
+            (NodeAuthResult::RateLimited, 307, "rate_limited"),
+            (NodeAuthResult::CantWriteToStorage, 901, "storage_error"),
+            (
+                NodeAuthResult::SeedEncryptionFailed,
+                902,
+                "seed_encryption_failed",
+            ),
+            (
+                NodeAuthResult::MemorySafetyAllocationError,
+                903,
+                "allocation_failed",
+            ),
+            (NodeAuthResult::Panic, 904, "internal_error"),
+        ];
+
+        let mut codes = HashSet::new();
+        for (result, code, name) in documented.iter() {
+            assert_eq!(as_code(result), RejectionCode::new(*code, name));
+            assert!(codes.insert(*code), "code {} is assigned twice", code);
+        }
+
+        assert!(as_code(&NodeAuthResult::Success).is_accepted());
+        assert!(!as_code(&NodeAuthResult::InvalidCert).is_accepted());
+        assert_eq!(
+            as_code(&NodeAuthResult::GroupOutOfDate).to_string(),
+            "group_out_of_date (101)"
+        );
+    }
+}
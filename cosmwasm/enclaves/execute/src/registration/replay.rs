@@ -0,0 +1,150 @@
+//! Single-use attestation reports.
+//!
+//! A report stays fresh for a while after it was produced, and anyone who got hold of it could
+//! submit it again within that time. The guard remembers the reports it accepted for as long as
+//! they're fresh, and rejects them when they come again.
+//!
+//! Reports are told apart by the ID IAS assigns to each of them, rather than by their quote: EPID
+//! quotes have no nonce, so a node that attests again with the same key gets the same quote in a
+//! new report.
+
+use std::collections::HashMap;
+
+use log::*;
+
+use enclave_crypto::{sha_256, HASH_SIZE};
+
+use super::report::{AttestationReport, Error};
+
+impl AttestationReport {
+    /// Identifier of the report for replay protection - the hash of the ID IAS assigned to it,
+    /// which is unique to each report. `None` for reports without an ID
+    pub fn replay_key(&self) -> Option<[u8; HASH_SIZE]> {
+        self.id.as_ref().map(|id| sha_256(id.as_bytes()))
+    }
+}
+
+/// Rejects reports that were already consumed in the last `ttl` seconds
+pub struct ReplayGuard {
+    ttl: u64,
+    /// Most reports tracked at once
+    capacity: usize,
+    /// Time (seconds since epoch) after which each consumed report may be accepted again
+    consumed: HashMap<[u8; HASH_SIZE], u64>,
+}
+
+impl ReplayGuard {
+    /// `ttl` should be at least as long as reports are considered fresh. At most `capacity`
+    /// reports are tracked at once - when that many are fresh, new reports are rejected until some
+    /// expire, as forgetting a fresh report would let it be replayed
+    pub fn new(ttl: u64, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity,
+            consumed: HashMap::new(),
+        }
+    }
+
+    /// Consumes `report` at `now`, rejecting it if it was already consumed within the TTL, or if
+    /// it can't be tracked
+    pub fn check_and_consume(&mut self, report: &AttestationReport, now: u64) -> Result<(), Error> {
+        self.consumed.retain(|_, expires_at| now < *expires_at);
+
+        let key = report.replay_key().ok_or_else(|| {
+            warn!("Attestation report has no ID to track it by");
+            Error::ReportValidationError
+        })?;
+        if self.consumed.contains_key(&key) {
+            warn!("Attestation report {} was replayed", hex::encode(key));
+            return Err(Error::ReportReplayed);
+        }
+
+        if self.consumed.len() >= self.capacity {
+            warn!(
+                "Already tracking {} fresh attestation reports",
+                self.consumed.len()
+            );
+            return Err(Error::ReplayCacheFull);
+        }
+
+        self.consumed.insert(key, now.saturating_add(self.ttl));
+        Ok(())
+    }
+
+    /// Number of reports consumed within the TTL, as of the last check
+    pub fn len(&self) -> usize {
+        self.consumed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.consumed.is_empty()
+    }
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use crate::registration::report::tests::mock_attestation_report;
+
+    use super::*;
+
+    pub fn test_replay_guard() {
+        let report = mock_attestation_report();
+        // the same quote in another report, as when a node attests again with the same key
+        let mut other = mock_attestation_report();
+        other.id = Some("53530608302195762335736519878284384789".to_string());
+        assert_eq!(report.raw_quote, other.raw_quote);
+        assert_ne!(report.replay_key(), other.replay_key());
+
+        let mut guard = ReplayGuard::new(60, 16);
+        assert!(guard.check_and_consume(&report, 1000).is_ok());
+        assert!(matches!(
+            guard.check_and_consume(&report, 1059),
+            Err(Error::ReportReplayed)
+        ));
+        assert!(guard.check_and_consume(&other, 1059).is_ok());
+        assert_eq!(guard.len(), 2);
+
+        // once the TTL is over the report is forgotten
+        assert!(guard.check_and_consume(&report, 1060).is_ok());
+        assert!(matches!(
+            guard.check_and_consume(&report, 1061),
+            Err(Error::ReportReplayed)
+        ));
+
+        // reports without an ID can't be tracked
+        let mut without_id = mock_attestation_report();
+        without_id.id = None;
+        assert!(matches!(
+            guard.check_and_consume(&without_id, 1061),
+            Err(Error::ReportValidationError)
+        ));
+    }
+
+    pub fn test_replay_guard_capacity() {
+        let mut guard = ReplayGuard::new(60, 2);
+        let reports: Vec<AttestationReport> = (0..3)
+            .map(|i| {
+                let mut report = mock_attestation_report();
+                report.id = Some(i.to_string());
+                report
+            })
+            .collect();
+
+        assert!(guard.check_and_consume(&reports[0], 1000).is_ok());
+        assert!(guard.check_and_consume(&reports[1], 1030).is_ok());
+        assert!(matches!(
+            guard.check_and_consume(&reports[2], 1059),
+            Err(Error::ReplayCacheFull)
+        ));
+        // a consumed report is still a replay when the guard is full
+        assert!(matches!(
+            guard.check_and_consume(&reports[0], 1059),
+            Err(Error::ReportReplayed)
+        ));
+        assert_eq!(guard.len(), 2);
+
+        // room is made as reports expire
+        assert!(guard.check_and_consume(&reports[2], 1060).is_ok());
+        assert_eq!(guard.len(), 2);
+    }
+}
@@ -4,11 +4,12 @@
 // This product includes software developed at
 // The Apache Software Foundation (http://www.apache.org/).
 //! Types that contain information about attestation report.
-//! The implementation is based on Attestation Service API version 4.
+//! The implementation is based on Attestation Service API versions 4 and 5.
 //! https://api.trustedservices.intel.com/documents/sgx-attestation-api-spec.pdf
 
 use std::array::TryFromSliceError;
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryFrom;
 
 use log::*;
@@ -16,19 +17,91 @@ use log::*;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
+use subtle::ConstantTimeEq;
 use uuid::Uuid;
 
+use enclave_crypto::sha_256;
 use enclave_ffi_types::NodeAuthResult;
 
-use super::cert::{get_ias_auth_config, get_netscape_comment};
+use super::cert::{get_cert_pubkey, get_ias_auth_config, get_netscape_comment};
+use super::encoding::{decode_base64, decode_hex};
+use super::signing_key::verify_chain_signature_algorithms;
 
-#[cfg(feature = "test")]
-use sgx_types::sgx_quote_t;
+use sgx_types::sgx_ql_qv_result_t;
 
 #[derive(Debug)]
 pub enum Error {
+    /// The report, or a structure in it, is malformed in a way none of the variants below describe
     ReportParseError,
+    /// The report is well formed, but doesn't meet an expectation of the verifier
     ReportValidationError,
+    /// The quote header declares a version we can't parse
+    UnsupportedQuoteVersion(u16),
+    /// The quote or enclave report is shorter than its fixed size, or the enclave report is longer
+    TruncatedReport { expected: usize, got: usize },
+    /// The signature over the report (or a certificate in its chain) doesn't verify
+    BadSignature,
+    /// The signing cert can't be parsed, or doesn't chain to the IAS root CA
+    CertChainInvalid,
+    /// The report was produced by a version of the attestation API we don't support
+    UnsupportedApiVersion(u64),
+    /// A timestamp in the report or its certificates can't be decoded
+    TimestampParse(String),
+    /// The quote is of a TEE whose reports we can't parse yet
+    UnsupportedTeeType(TeeType),
+    /// The enclave's MRENCLAVE isn't one of the allowed measurements
+    MrEnclaveNotAllowed([u8; 32]),
+    /// The enclave was signed by a key other than the expected one
+    MrSignerMismatch([u8; 32]),
+    /// The enclave's security version is older than the minimum accepted
+    IsvSvnTooLow { min: u16, got: u16 },
+    /// The enclave's product ID isn't one allowed for its signer
+    IsvProdIdNotAllowed(u16),
+    /// The list of accepted measurements doesn't hash to the commitment it's checked against
+    MeasurementCommitmentMismatch,
+    /// The report was already consumed while it was still fresh
+    ReportReplayed,
+    /// Too many fresh reports were consumed to track another one
+    ReplayCacheFull,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::ReportParseError => write!(f, "malformed attestation report"),
+            Error::ReportValidationError => write!(f, "attestation report failed validation"),
+            Error::UnsupportedQuoteVersion(version) => {
+                write!(f, "unsupported quote version {}", version)
+            }
+            Error::TruncatedReport { expected, got } => {
+                write!(f, "report is {} bytes rather than {}", got, expected)
+            }
+            Error::BadSignature => write!(f, "invalid report signature"),
+            Error::CertChainInvalid => write!(f, "invalid report signing cert chain"),
+            Error::UnsupportedApiVersion(version) => {
+                write!(f, "unsupported attestation API version {}", version)
+            }
+            Error::TimestampParse(timestamp) => write!(f, "invalid timestamp {}", timestamp),
+            Error::UnsupportedTeeType(tee_type) => write!(f, "unsupported TEE type {:?}", tee_type),
+            Error::MrEnclaveNotAllowed(mr_enclave) => {
+                write!(f, "MRENCLAVE {} is not allowed", hex::encode(mr_enclave))
+            }
+            Error::MrSignerMismatch(mr_signer) => {
+                write!(f, "unexpected MRSIGNER {}", hex::encode(mr_signer))
+            }
+            Error::IsvSvnTooLow { min, got } => {
+                write!(f, "ISV SVN {} is lower than the minimum {}", got, min)
+            }
+            Error::IsvProdIdNotAllowed(isv_prod_id) => {
+                write!(f, "ISV product ID {} is not allowed", isv_prod_id)
+            }
+            Error::MeasurementCommitmentMismatch => {
+                write!(f, "measurements don't match their commitment")
+            }
+            Error::ReportReplayed => write!(f, "attestation report was already used"),
+            Error::ReplayCacheFull => write!(f, "too many fresh attestation reports to track"),
+        }
+    }
 }
 
 impl From<std::array::TryFromSliceError> for Error {
@@ -59,6 +132,29 @@ pub struct EndorsedAttestationReport {
     pub signing_cert: Vec<u8>,
 }
 
+impl EndorsedAttestationReport {
+    /// Deserialize an endorsed report from its JSON encoding (e.g. the payload of an RA cert),
+    /// rejecting it if any of its fields is empty
+    pub fn from_json_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let report: Self = serde_json::from_slice(bytes)?;
+
+        for (field, value) in [
+            ("report", &report.report),
+            ("signature", &report.signature),
+            ("signing_cert", &report.signing_cert),
+        ]
+        .iter()
+        {
+            if value.is_empty() {
+                warn!("Endorsed report has an empty {}", field);
+                return Err(Error::ReportParseError);
+            }
+        }
+
+        Ok(report)
+    }
+}
+
 fn as_base64<S>(key: &[u8], serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -83,12 +179,70 @@ where
         where
             E: serde::de::Error,
         {
-            base64::decode(v).map_err(E::custom)
+            decode_base64(v.as_bytes()).map_err(E::custom)
         }
     }
     deserializer.deserialize_str(Base64Visitor)
 }
 
+fn as_optional_base64<S>(bytes: &Option<Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match bytes {
+        Some(bytes) => serializer.serialize_some(&base64::encode(bytes)),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn from_optional_base64<'de, D>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|encoded| decode_base64(encoded.as_bytes()).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// Fixed size fields (measurements, `report_data`...) are serialized as hex, like they're usually
+/// logged and configured
+fn as_hex<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&hex::encode(bytes))
+}
+
+fn from_hex_array<'de, D, const N: usize>(deserializer: D) -> Result<[u8; N], D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let bytes = decode_hex(String::deserialize(deserializer)?.as_bytes())
+        .map_err(serde::de::Error::custom)?;
+    <[u8; N]>::try_from(bytes.as_slice()).map_err(|_| {
+        serde::de::Error::invalid_length(bytes.len(), &format!("{} bytes", N).as_str())
+    })
+}
+
+fn as_uuid<S>(uuid: &Uuid, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_str(&uuid.to_hyphenated())
+}
+
+fn from_uuid<'de, D>(deserializer: D) -> Result<Uuid, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Uuid::parse_str(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+}
+
+/// Size of an enclave report body
+pub const ENCLAVE_REPORT_SIZE: usize = 384;
+/// Size of an EPID quote body, i.e. the header and the enclave report, without the signature
+pub const QUOTE_SIZE: usize = 48 + ENCLAVE_REPORT_SIZE;
+
 type SignatureAlgorithms = &'static [&'static webpki::SignatureAlgorithm];
 static SUPPORTED_SIG_ALGS: SignatureAlgorithms = &[
     &webpki::ECDSA_P256_SHA256,
@@ -115,29 +269,58 @@ static SUPPORTED_SIG_ALGS: SignatureAlgorithms = &[
 /// `SgxQuote`. The quote is then sent to some attestation service (IAS or
 /// DCAP-based AS). The endorsed `SgxQuote` is an attestation report signed by
 /// attestation service's private key, a.k.a., `EndorsedAttestationReport`.
+#[derive(PartialEq, Serialize, Deserialize)]
 pub struct SgxEnclaveReport {
     /// Security version number of host system's CPU
+    #[serde(serialize_with = "as_hex", deserialize_with = "from_hex_array")]
     pub cpu_svn: [u8; 16],
     /// Misc select bits for the target enclave. Reserved for future function
     /// extension.
     pub misc_select: u32,
     /// Attributes of the enclave, for example, whether the enclave is running
     /// in debug mode.
+    #[serde(serialize_with = "as_hex", deserialize_with = "from_hex_array")]
     pub attributes: [u8; 16],
     /// Measurement value of the enclave. See
     /// [`EnclaveMeasurement`](../types/struct.EnclaveMeasurement.html)
+    #[serde(serialize_with = "as_hex", deserialize_with = "from_hex_array")]
     pub mr_enclave: [u8; 32],
     /// Measurement value of the public key that verified the enclave. See
     /// [`EnclaveMeasurement`](../types/struct.EnclaveMeasurement.html)
+    #[serde(serialize_with = "as_hex", deserialize_with = "from_hex_array")]
     pub mr_signer: [u8; 32],
     /// Product ID of the enclave
     pub isv_prod_id: u16,
     /// Security version number of the enclave
     pub isv_svn: u16,
     /// Set of data used for communication between enclave and target enclave
+    #[serde(serialize_with = "as_hex", deserialize_with = "from_hex_array")]
     pub report_data: [u8; 64],
 }
 
+/// The fields of an enclave report that identify the enclave, as opposed to the platform it runs
+/// on or the data it reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnclaveIdentity {
+    pub mr_enclave: [u8; 32],
+    pub mr_signer: [u8; 32],
+    pub isv_prod_id: u16,
+    pub isv_svn: u16,
+}
+
+/// The fields of `SgxEnclaveReport`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportField {
+    CpuSvn,
+    MiscSelect,
+    Attributes,
+    MrEnclave,
+    MrSigner,
+    IsvProdId,
+    IsvSvn,
+    ReportData,
+}
+
 impl std::fmt::Debug for SgxEnclaveReport {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "cpu_svn: {:?}", self.cpu_svn)?;
@@ -151,18 +334,35 @@ impl std::fmt::Debug for SgxEnclaveReport {
     }
 }
 
+/// The `n` bytes of `bytes` at `pos`, advancing `pos` past them. `None` if fewer are left,
+/// including when `pos + n` overflows, since the sizes of some structures come from the untrusted
+/// input itself
+pub(crate) fn take_bytes<'a>(bytes: &'a [u8], pos: &mut usize, n: usize) -> Option<&'a [u8]> {
+    let end = pos.checked_add(n)?;
+    let ret = bytes.get(*pos..end)?;
+    *pos = end;
+
+    Some(ret)
+}
+
 impl SgxEnclaveReport {
     /// Parse bytes of report into `SgxEnclaveReport`.
     pub fn parse_from<'a>(bytes: &'a [u8]) -> Result<Self, Error> {
+        // the fields and reserved ranges taken below, which must cover the whole report
+        const _: () =
+            assert!(16 + 4 + 28 + 16 + 32 + 32 + 32 + 96 + 2 + 2 + 60 + 64 == ENCLAVE_REPORT_SIZE);
+
         let mut pos: usize = 0;
         let mut take = |n: usize| -> Result<&'a [u8], Error> {
-            if n > 0 && bytes.len() >= pos + n {
-                let ret = &bytes[pos..pos + n];
-                pos += n;
-                Ok(ret)
-            } else {
-                error!("Enclave report parsing error - bad report size");
-                Err(Error::ReportParseError)
+            match take_bytes(bytes, &mut pos, n) {
+                Some(ret) if n > 0 => Ok(ret),
+                _ => {
+                    error!("Enclave report parsing error - bad report size");
+                    Err(Error::TruncatedReport {
+                        expected: ENCLAVE_REPORT_SIZE,
+                        got: bytes.len(),
+                    })
+                }
             }
         };
 
@@ -202,16 +402,17 @@ impl SgxEnclaveReport {
         let _reserved = take(60)?;
 
         // off 368, size 64
-        let mut report_data = [0u8; 64];
-        let _report_data = take(64)?;
-        let mut _it = _report_data.iter();
-        for i in report_data.iter_mut() {
-            *i = *_it.next().ok_or(Error::ReportParseError)?;
-        }
+        let report_data = <[u8; 64]>::try_from(take(64)?)?;
 
         if pos != bytes.len() {
-            warn!("Enclave report parsing error.");
-            return Err(Error::ReportParseError);
+            warn!(
+                "Enclave report parsing error - {} trailing bytes",
+                bytes.len() - pos
+            );
+            return Err(Error::TruncatedReport {
+                expected: ENCLAVE_REPORT_SIZE,
+                got: bytes.len(),
+            });
         };
 
         Ok(SgxEnclaveReport {
@@ -225,10 +426,284 @@ impl SgxEnclaveReport {
             report_data,
         })
     }
+
+    /// Serialize the report back to its 384 byte layout. Reserved fields are zeroed, as they are
+    /// in reports produced by the CPU
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(384);
+        bytes.extend_from_slice(&self.cpu_svn);
+        bytes.extend_from_slice(&self.misc_select.to_le_bytes());
+        bytes.extend_from_slice(&[0u8; 28]);
+        bytes.extend_from_slice(&self.attributes);
+        bytes.extend_from_slice(&self.mr_enclave);
+        bytes.extend_from_slice(&[0u8; 32]);
+        bytes.extend_from_slice(&self.mr_signer);
+        bytes.extend_from_slice(&[0u8; 96]);
+        bytes.extend_from_slice(&self.isv_prod_id.to_le_bytes());
+        bytes.extend_from_slice(&self.isv_svn.to_le_bytes());
+        bytes.extend_from_slice(&[0u8; 60]);
+        bytes.extend_from_slice(&self.report_data);
+
+        bytes
+    }
+
+    /// Verify that the report's `report_data` is exactly `expected`, which must be 64 bytes long
+    pub fn verify_report_data(&self, expected: &[u8]) -> Result<(), Error> {
+        let expected = <[u8; 64]>::try_from(expected).map_err(|_| {
+            warn!(
+                "Expected report data is {} bytes long rather than 64",
+                expected.len()
+            );
+            Error::ReportValidationError
+        })?;
+
+        if self.report_data != expected {
+            warn!("Report data does not match the expected value");
+            return Err(Error::ReportValidationError);
+        }
+
+        Ok(())
+    }
+
+    /// The layout version of `report_data`, which enclaves that tag it put in its last byte (see
+    /// `report_data_pubkey`). Untagged reports are version 0. The rest of `report_data` should
+    /// only be interpreted once the version is known to be supported
+    pub fn report_data_version(&self) -> u8 {
+        self.report_data[REPORT_DATA_VERSION_OFFSET]
+    }
+
+    /// The first half of `report_data`. In Secret Network's registration handshake, `report_data`
+    /// is laid out as (see `expected_report_data`):
+    ///
+    /// | bytes  | content                                                |
+    /// |--------|--------------------------------------------------------|
+    /// | 0..32  | the public key of the node                             |
+    /// | 32..64 | a secondary value bound along with the key, or zeros   |
+    ///
+    /// Enclaves that tag the layout with a version keep the last byte of the secondary value for
+    /// the tag, so it never overlaps the key
+    pub fn report_data_pubkey(&self) -> &[u8; 32] {
+        // a 32 byte slice of a 64 byte array always converts
+        <&[u8; 32]>::try_from(&self.report_data[..32]).unwrap()
+    }
+
+    /// The identity of the enclave that produced the report
+    pub fn identity(&self) -> EnclaveIdentity {
+        EnclaveIdentity {
+            mr_enclave: self.mr_enclave,
+            mr_signer: self.mr_signer,
+            isv_prod_id: self.isv_prod_id,
+            isv_svn: self.isv_svn,
+        }
+    }
+
+    /// The second half of `report_data` - see `report_data_pubkey` for the layout
+    pub fn report_data_tail(&self) -> &[u8; 32] {
+        <&[u8; 32]>::try_from(&self.report_data[32..]).unwrap()
+    }
+
+    /// Verify that the first half of `report_data` is the SHA-256 of `pubkey`, for enclaves that
+    /// bind the key of their certificate by its hash
+    pub fn verify_pubkey_hash_binding(&self, pubkey: &[u8]) -> Result<(), Error> {
+        if !measurements_equal(self.report_data_pubkey(), &sha_256(pubkey)) {
+            warn!("Report data is not bound to the public key of the certificate");
+            return Err(Error::ReportValidationError);
+        }
+
+        Ok(())
+    }
+
+    /// Verify that the first half of the report's `report_data` is `root`, for enclaves that commit
+    /// to a set of values (keys, configuration) through the root of a Merkle tree over them
+    pub fn verify_report_data_merkle_root(&self, root: &[u8; 32]) -> Result<(), Error> {
+        if self.report_data[..32] != root[..] {
+            warn!("Report data does not commit to the expected Merkle root");
+            return Err(Error::ReportValidationError);
+        }
+
+        Ok(())
+    }
+
+    /// Verify that the first half of the report's `report_data` is the SHA-256 of `tls_cert_der`,
+    /// for RA-TLS style attestations that are bound to the certificate of the TLS channel they're
+    /// presented on. A report relayed over another channel than the node's own doesn't verify
+    pub fn verify_report_data_cert_binding(&self, tls_cert_der: &[u8]) -> Result<(), Error> {
+        if !measurements_equal(self.report_data_pubkey(), &sha_256(tls_cert_der)) {
+            warn!("Report data does not commit to the TLS certificate of the channel");
+            return Err(Error::ReportValidationError);
+        }
+
+        Ok(())
+    }
+
+    /// Verify that the report's `report_data` binds exactly the given public key and secondary
+    /// value, as laid out by `expected_report_data`. Both must be 32 bytes long
+    pub fn verify_report_data_binding(
+        &self,
+        pubkey: &[u8],
+        secondary: Option<&[u8]>,
+    ) -> Result<(), Error> {
+        let to_half = |value: &[u8]| {
+            <[u8; 32]>::try_from(value).map_err(|_| {
+                warn!(
+                    "Expected report data binding value is {} bytes long rather than 32",
+                    value.len()
+                );
+                Error::ReportValidationError
+            })
+        };
+        let pubkey = to_half(pubkey)?;
+        let secondary = secondary.map(to_half).transpose()?;
+
+        if self.report_data != expected_report_data(&pubkey, secondary.as_ref()) {
+            warn!("Report data does not match the expected public key binding");
+            return Err(Error::ReportValidationError);
+        }
+
+        Ok(())
+    }
+
+    /// Whether the report matches a known-good one field by field, except for the fields in
+    /// `ignore` (typically `CpuSvn` and `ReportData`, which depend on the platform and the node)
+    pub fn matches_golden(&self, golden: &Self, ignore: &[ReportField]) -> bool {
+        let fields = [
+            (ReportField::CpuSvn, self.cpu_svn == golden.cpu_svn),
+            (
+                ReportField::MiscSelect,
+                self.misc_select == golden.misc_select,
+            ),
+            (
+                ReportField::Attributes,
+                self.attributes == golden.attributes,
+            ),
+            (ReportField::MrEnclave, self.mr_enclave == golden.mr_enclave),
+            (ReportField::MrSigner, self.mr_signer == golden.mr_signer),
+            (
+                ReportField::IsvProdId,
+                self.isv_prod_id == golden.isv_prod_id,
+            ),
+            (ReportField::IsvSvn, self.isv_svn == golden.isv_svn),
+            (
+                ReportField::ReportData,
+                self.report_data[..] == golden.report_data[..],
+            ),
+        ];
+
+        fields.iter().all(|(field, matches)| {
+            if !matches && !ignore.contains(field) {
+                warn!(
+                    "Enclave report differs from the golden report in {:?}",
+                    field
+                );
+                return false;
+            }
+            true
+        })
+    }
+
+    /// The attributes as lowercase hex, in the format attributes allowlists are configured in
+    pub fn attributes_hex(&self) -> String {
+        hex::encode(self.attributes)
+    }
+
+    /// Verify that the attributes of the enclave (e.g. whether it's a debug enclave) are exactly
+    /// one of `allowed`
+    pub fn verify_attributes(&self, allowed: &[[u8; 16]]) -> Result<(), Error> {
+        if !allowed.contains(&self.attributes) {
+            warn!(
+                "Enclave attributes {} are not in the allowlist",
+                self.attributes_hex()
+            );
+            return Err(Error::ReportValidationError);
+        }
+
+        Ok(())
+    }
 }
 
-/// SGX Quote structure version
+/// An enclave report along with the Key Separation and Sharing (KSS) fields of newer platforms.
+///
+/// Intel carved these out of space that `SgxEnclaveReport` treats as reserved, so the body is
+/// still 384 bytes - on platforms without KSS they are all zero.
 #[derive(Debug, PartialEq)]
+pub struct SgxExtendedEnclaveReport {
+    pub report: SgxEnclaveReport,
+    /// Extended product ID of the enclave (ISVEXTPRODID)
+    pub isv_ext_prod_id: [u8; 16],
+    /// Configuration the enclave was launched with (CONFIGID)
+    pub config_id: [u8; 64],
+    /// Security version of the configuration (CONFIGSVN)
+    pub config_svn: u16,
+    /// Product family of the enclave (ISVFAMILYID)
+    pub isv_family_id: [u8; 16],
+}
+
+impl SgxExtendedEnclaveReport {
+    /// Parse the bytes of a report body, including the KSS fields
+    pub fn parse_from(bytes: &[u8]) -> Result<Self, Error> {
+        let report = SgxEnclaveReport::parse_from(bytes)?;
+
+        Ok(Self {
+            report,
+            // off 80, size 16
+            isv_ext_prod_id: <[u8; 16]>::try_from(&bytes[32..48])?,
+            // off 240, size 64
+            config_id: <[u8; 64]>::try_from(&bytes[192..256])?,
+            // off 308, size 2
+            config_svn: u16::from_le_bytes(<[u8; 2]>::try_from(&bytes[260..262])?),
+            // off 352, size 16
+            isv_family_id: <[u8; 16]>::try_from(&bytes[304..320])?,
+        })
+    }
+
+    /// Whether any of the KSS fields is set
+    pub fn has_kss_fields(&self) -> bool {
+        self.isv_ext_prod_id != [0u8; 16]
+            || self.config_id.iter().any(|b| *b != 0)
+            || self.config_svn != 0
+            || self.isv_family_id != [0u8; 16]
+    }
+}
+
+/// Where enclaves that tag the layout of `report_data` put its version, see
+/// `SgxEnclaveReport::report_data_version`
+pub const REPORT_DATA_VERSION_OFFSET: usize = 63;
+
+/// Builds the `report_data` that an enclave attests to: the node's public key in the first half,
+/// and an optional secondary value (zeroed when absent) in the second half.
+///
+/// Both the attesting enclave and the verifier use this, so the layout can't drift between them.
+pub fn expected_report_data(pubkey: &[u8; 32], secondary: Option<&[u8; 32]>) -> [u8; 64] {
+    let mut report_data = [0u8; 64];
+    report_data[..32].copy_from_slice(pubkey);
+    if let Some(secondary) = secondary {
+        report_data[32..].copy_from_slice(secondary);
+    }
+
+    report_data
+}
+
+/// The secondary value of `report_data` that binds a challenge of up to 32 bytes, zero padded -
+/// e.g. the 4 byte challenge of the seed service ends up in `report_data[32..36]`. `None` if the
+/// challenge doesn't fit
+pub fn challenge_report_data(challenge: &[u8]) -> Option<[u8; 32]> {
+    let mut secondary = [0u8; 32];
+    secondary
+        .get_mut(..challenge.len())?
+        .copy_from_slice(challenge);
+
+    Some(secondary)
+}
+
+/// Whether two measurements (MRENCLAVE, MRSIGNER) are equal, in time that doesn't depend on where
+/// they differ. The measurement comes from the remote node, so a plain `==` could let it probe the
+/// expected value byte by byte
+pub fn measurements_equal(a: &[u8; 32], b: &[u8; 32]) -> bool {
+    a.ct_eq(b).into()
+}
+
+/// SGX Quote structure version
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub enum SgxQuoteVersion {
     /// EPID quote version
@@ -237,18 +712,40 @@ pub enum SgxQuoteVersion {
     V2(SgxEpidQuoteSigType),
     /// ECDSA quote version
     V3(SgxEcdsaQuoteAkType),
+    /// ECDSA quote version of DCAP 1.x and later, which can be of an SGX enclave or a TDX TD
+    V4(SgxEcdsaQuoteAkType, TeeType),
+}
+
+/// `tee_type` of v4 quotes
+pub const TEE_TYPE_SGX: u32 = 0x00;
+pub const TEE_TYPE_TDX: u32 = 0x81;
+
+/// The trusted execution environment a v4 quote was produced in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TeeType {
+    Sgx,
+    Tdx,
 }
 
 /// Intel EPID attestation signature type
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub enum SgxEpidQuoteSigType {
     Unlinkable,
     Linkable,
 }
 
+impl SgxEpidQuoteSigType {
+    fn to_u16(&self) -> u16 {
+        match self {
+            SgxEpidQuoteSigType::Unlinkable => 0,
+            SgxEpidQuoteSigType::Linkable => 1,
+        }
+    }
+}
+
 /// ECDSA attestation key type
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum SgxEcdsaQuoteAkType {
     /// ECDSA-256-with-P-256 curve
     P256_256,
@@ -257,7 +754,7 @@ pub enum SgxEcdsaQuoteAkType {
 }
 
 /// SGX Quote status
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum SgxQuoteStatus {
     /// EPID signature of the ISV enclave QUOTE was verified correctly and the
     /// TCB level of the SGX platform is up-to-date.
@@ -352,6 +849,12 @@ impl From<&SgxQuoteStatus> for NodeAuthResult {
             SgxQuoteStatus::SigrlVersionMismatch => NodeAuthResult::SigrlVersionMismatch,
             SgxQuoteStatus::SignatureRevoked => NodeAuthResult::SignatureRevoked,
             SgxQuoteStatus::GroupRevoked => NodeAuthResult::GroupRevoked,
+            SgxQuoteStatus::OutOfDate => NodeAuthResult::OutOfDate,
+            SgxQuoteStatus::OutOfDateConfigurationNeeded => {
+                NodeAuthResult::OutOfDateConfigurationNeeded
+            }
+            SgxQuoteStatus::SwHardeningNeeded => NodeAuthResult::SwHardeningNeeded,
+            SgxQuoteStatus::UnknownBadStatus => NodeAuthResult::UnknownQuoteStatus,
             _ => NodeAuthResult::BadQuoteStatus,
         }
     }
@@ -380,23 +883,101 @@ impl From<&str> for SgxQuoteStatus {
     }
 }
 
+impl From<sgx_ql_qv_result_t> for SgxQuoteStatus {
+    /// Convert from the result of the DCAP quote verification library.
+    fn from(result: sgx_ql_qv_result_t) -> Self {
+        match result {
+            sgx_ql_qv_result_t::SGX_QL_QV_RESULT_OK => SgxQuoteStatus::OK,
+            sgx_ql_qv_result_t::SGX_QL_QV_RESULT_CONFIG_NEEDED => {
+                SgxQuoteStatus::ConfigurationNeeded
+            }
+            sgx_ql_qv_result_t::SGX_QL_QV_RESULT_OUT_OF_DATE => SgxQuoteStatus::OutOfDate,
+            sgx_ql_qv_result_t::SGX_QL_QV_RESULT_OUT_OF_DATE_CONFIG_NEEDED => {
+                SgxQuoteStatus::OutOfDateConfigurationNeeded
+            }
+            sgx_ql_qv_result_t::SGX_QL_QV_RESULT_INVALID_SIGNATURE => {
+                SgxQuoteStatus::SignatureInvalid
+            }
+            sgx_ql_qv_result_t::SGX_QL_QV_RESULT_REVOKED => SgxQuoteStatus::KeyRevoked,
+            sgx_ql_qv_result_t::SGX_QL_QV_RESULT_SW_HARDENING_NEEDED => {
+                SgxQuoteStatus::SwHardeningNeeded
+            }
+            sgx_ql_qv_result_t::SGX_QL_QV_RESULT_CONFIG_AND_SW_HARDENING_NEEDED => {
+                SgxQuoteStatus::ConfigurationAndSwHardeningNeeded
+            }
+            _ => SgxQuoteStatus::UnknownBadStatus,
+        }
+    }
+}
+
+impl std::fmt::Display for SgxQuoteStatus {
+    /// The status as IAS spells it in reports. `UnknownBadStatus` has no IAS counterpart, since it
+    /// stands for any status we don't know
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            SgxQuoteStatus::OK => "OK",
+            SgxQuoteStatus::SignatureInvalid => "SIGNATURE_INVALID",
+            SgxQuoteStatus::GroupRevoked => "GROUP_REVOKED",
+            SgxQuoteStatus::SignatureRevoked => "SIGNATURE_REVOKED",
+            SgxQuoteStatus::KeyRevoked => "KEY_REVOKED",
+            SgxQuoteStatus::SigrlVersionMismatch => "SIGRL_VERSION_MISMATCH",
+            SgxQuoteStatus::GroupOutOfDate => "GROUP_OUT_OF_DATE",
+            SgxQuoteStatus::OutOfDate => "OUT_OF_DATE",
+            SgxQuoteStatus::OutOfDateConfigurationNeeded => "OUT_OF_DATE_CONFIGURATION_NEEDED",
+            SgxQuoteStatus::ConfigurationNeeded => "CONFIGURATION_NEEDED",
+            SgxQuoteStatus::SwHardeningNeeded => "SW_HARDENING_NEEDED",
+            SgxQuoteStatus::ConfigurationAndSwHardeningNeeded => {
+                "CONFIGURATION_AND_SW_HARDENING_NEEDED"
+            }
+            SgxQuoteStatus::UnknownBadStatus => "UNKNOWN_BAD_STATUS",
+        };
+
+        f.write_str(status)
+    }
+}
+
+/// Statuses are serialized as IAS spells them, so that they don't depend on the variant names
+impl Serialize for SgxQuoteStatus {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for SgxQuoteStatus {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(SgxQuoteStatus::from(
+            String::deserialize(deserializer)?.as_str(),
+        ))
+    }
+}
+
 /// An application that hosts an enclave can ask the enclave to produce a report
 /// (`SgxEnclaveReport`) and then pass this report to a platform service
 /// (Quoting Enclave) to produce a type of credential that reflects the enclave
 /// and platform state. The quote can be passed to entities off the platform,
 /// and verified using Intel EPID signature verification techniques.
+#[derive(PartialEq, Serialize, Deserialize)]
 pub struct SgxQuote {
     /// Version of the quote structure
     pub version: SgxQuoteVersion,
-    /// ID of the Intel EPID group of the platform belongs to
+    /// ID of the Intel EPID group of the platform belongs to. In v4 quotes this is the raw TEE
+    /// type instead, which is also part of `version`
     pub gid: u32,
     /// Security version number of Quoting Enclave
     pub isv_svn_qe: u16,
     /// Security version number of PCE
     pub isv_svn_pce: u16,
     /// Vendor ID of Quoting Enclave
+    #[serde(serialize_with = "as_uuid", deserialize_with = "from_uuid")]
     pub qe_vendor_id: Uuid,
     /// User data
+    #[serde(serialize_with = "as_hex", deserialize_with = "from_hex_array")]
     pub user_data: [u8; 20],
     /// Report generated by the enclave
     pub isv_enclave_report: SgxEnclaveReport,
@@ -418,104 +999,108 @@ impl SgxQuote {
     /// Parse from bytes to `SgxQuote`.
     // just unused in SW mode
     #[allow(dead_code)]
-    fn parse_from<'a>(bytes: &'a [u8]) -> Result<Self, Error> {
+    pub(crate) fn parse_from<'a>(bytes: &'a [u8]) -> Result<Self, Error> {
         let mut pos: usize = 0;
         let mut take = |n: usize| -> Result<&'a [u8], Error> {
-            if n > 0 && bytes.len() >= pos + n {
-                let ret = &bytes[pos..pos + n];
-                pos += n;
-                Ok(ret)
-            } else {
-                warn!("Quote parsing error.");
-                Err(Error::ReportParseError)
+            match take_bytes(bytes, &mut pos, n) {
+                Some(ret) if n > 0 => Ok(ret),
+                _ => {
+                    warn!("Quote parsing error - quote is truncated");
+                    Err(Error::TruncatedReport {
+                        expected: QUOTE_SIZE,
+                        got: bytes.len(),
+                    })
+                }
             }
         };
 
         // Parse by bytes according to specifications.
-        // off 0, size 2 + 2
-        let version = match u16::from_le_bytes(<[u8; 2]>::try_from(take(2)?)?) {
-            1 => {
-                let signature_type = match u16::from_le_bytes(<[u8; 2]>::try_from(
-                    take(2).map_err(|_| Error::ReportParseError)?,
-                )?) {
-                    0 => SgxEpidQuoteSigType::Unlinkable,
-                    1 => SgxEpidQuoteSigType::Linkable,
-                    _ => {
-                        warn!("Invalid v1 quote signature type");
-                        return Err(Error::ReportParseError);
-                    }
-                };
-                SgxQuoteVersion::V1(signature_type)
-            }
-            2 => {
-                let signature_type = match u16::from_le_bytes(<[u8; 2]>::try_from(
-                    take(2).map_err(|_| Error::ReportParseError)?,
-                )?) {
-                    0 => SgxEpidQuoteSigType::Unlinkable,
-                    1 => SgxEpidQuoteSigType::Linkable,
-                    _ => {
-                        warn!("Invalid v2 quote signature type");
-                        return Err(Error::ReportParseError);
-                    }
-                };
-                SgxQuoteVersion::V2(signature_type)
-            }
-            3 => {
-                let attestation_key_type = match u16::from_le_bytes(<[u8; 2]>::try_from(
-                    take(2).map_err(|_| Error::ReportParseError)?,
-                )?) {
-                    2 => SgxEcdsaQuoteAkType::P256_256,
-                    3 => SgxEcdsaQuoteAkType::P384_384,
+        // off 0, size 2
+        let version = u16::from_le_bytes(<[u8; 2]>::try_from(take(2)?)?);
+
+        // off 2, size 2 - the signature type of EPID quotes, the attestation key type of ECDSA ones
+        let sign_type = u16::from_le_bytes(<[u8; 2]>::try_from(take(2)?)?);
+
+        // off 4, size 4 - the TEE type in v4 quotes
+        let gid = u32::from_le_bytes(<[u8; 4]>::try_from(take(4).map_err(|e| {
+            warn!("Failed to parse quote gid");
+            e
+        })?)?);
+
+        let epid_sig_type = |sign_type: u16| match sign_type {
+            0 => Ok(SgxEpidQuoteSigType::Unlinkable),
+            1 => Ok(SgxEpidQuoteSigType::Linkable),
+            _ => {
+                warn!("Invalid v{} quote signature type", version);
+                Err(Error::ReportParseError)
+            }
+        };
+        let ecdsa_ak_type = |sign_type: u16| match sign_type {
+            2 => Ok(SgxEcdsaQuoteAkType::P256_256),
+            3 => Ok(SgxEcdsaQuoteAkType::P384_384),
+            _ => {
+                warn!("Quote parsing error - ecdsa quote type invalid");
+                Err(Error::ReportParseError)
+            }
+        };
+
+        let version = match version {
+            1 => SgxQuoteVersion::V1(epid_sig_type(sign_type)?),
+            2 => SgxQuoteVersion::V2(epid_sig_type(sign_type)?),
+            3 => SgxQuoteVersion::V3(ecdsa_ak_type(sign_type)?),
+            4 => {
+                let tee_type = match gid {
+                    TEE_TYPE_SGX => TeeType::Sgx,
+                    TEE_TYPE_TDX => TeeType::Tdx,
                     _ => {
-                        warn!("Quote parsing error - ecdsa quote type invalid");
+                        warn!("Quote parsing error - unknown TEE type {:#x}", gid);
                         return Err(Error::ReportParseError);
                     }
                 };
-                SgxQuoteVersion::V3(attestation_key_type)
+                // TDX quotes carry a TD report rather than an enclave report
+                if tee_type == TeeType::Tdx {
+                    warn!("Quote parsing error - TDX quotes are not supported");
+                    return Err(Error::UnsupportedTeeType(tee_type));
+                }
+                SgxQuoteVersion::V4(ecdsa_ak_type(sign_type)?, tee_type)
             }
-            _ => {
-                warn!("Quote parsing error - Unknown quote version");
-                return Err(Error::ReportParseError);
+            version => {
+                warn!("Quote parsing error - Unknown quote version {}", version);
+                return Err(Error::UnsupportedQuoteVersion(version));
             }
         };
 
-        // off 4, size 4
-        let gid = u32::from_le_bytes(<[u8; 4]>::try_from(take(4).map_err(|_| {
-            warn!("Failed to parse quote gid");
-            Error::ReportParseError
-        })?)?);
-
         // off 8, size 2
-        let isv_svn_qe = u16::from_le_bytes(<[u8; 2]>::try_from(take(2).map_err(|_| {
+        let isv_svn_qe = u16::from_le_bytes(<[u8; 2]>::try_from(take(2).map_err(|e| {
             warn!("Failed to parse quote isv svn qe");
-            Error::ReportParseError
+            e
         })?)?);
 
         // off 10, size 2
-        let isv_svn_pce = u16::from_le_bytes(<[u8; 2]>::try_from(take(2).map_err(|_| {
+        let isv_svn_pce = u16::from_le_bytes(<[u8; 2]>::try_from(take(2).map_err(|e| {
             warn!("Failed to parse quote isv svn");
-            Error::ReportParseError
+            e
         })?)?);
 
         // off 12, size 16
-        let qe_vendor_id_raw =
-            <[u8; 16]>::try_from(take(16).map_err(|_| Error::ReportParseError)?)?;
+        let qe_vendor_id_raw = <[u8; 16]>::try_from(take(16)?)?;
         let qe_vendor_id = Uuid::from_slice(&qe_vendor_id_raw).map_err(|_| {
             warn!("Failed to parse quote vendor id");
             Error::ReportParseError
         })?;
 
         // off 28, size 20
-        let user_data = <[u8; 20]>::try_from(take(20).map_err(|_| {
+        let user_data = <[u8; 20]>::try_from(take(20).map_err(|e| {
             warn!("Failed to parse quote user data");
-            Error::ReportParseError
+            e
         })?)?;
 
         // off 48, size 384
-        let isv_enclave_report = SgxEnclaveReport::parse_from(take(384).map_err(|_| {
-            warn!("Failed to parse enclave report");
-            Error::ReportParseError
-        })?)?;
+        let isv_enclave_report =
+            SgxEnclaveReport::parse_from(take(ENCLAVE_REPORT_SIZE).map_err(|e| {
+                warn!("Failed to parse enclave report");
+                e
+            })?)?;
 
         if pos != bytes.len() {
             warn!("Quote parsing error - Quote size different from expected");
@@ -532,10 +1117,73 @@ impl SgxQuote {
             isv_enclave_report,
         })
     }
+
+    /// Serialize the quote back to its 432 byte layout, as found in `isvEnclaveQuoteBody`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (version, sign_type): (u16, u16) = match &self.version {
+            SgxQuoteVersion::V1(sig_type) => (1, sig_type.to_u16()),
+            SgxQuoteVersion::V2(sig_type) => (2, sig_type.to_u16()),
+            SgxQuoteVersion::V3(SgxEcdsaQuoteAkType::P256_256) => (3, 2),
+            SgxQuoteVersion::V3(SgxEcdsaQuoteAkType::P384_384) => (3, 3),
+            SgxQuoteVersion::V4(SgxEcdsaQuoteAkType::P256_256, _) => (4, 2),
+            SgxQuoteVersion::V4(SgxEcdsaQuoteAkType::P384_384, _) => (4, 3),
+        };
+
+        let mut bytes = Vec::with_capacity(432);
+        bytes.extend_from_slice(&version.to_le_bytes());
+        bytes.extend_from_slice(&sign_type.to_le_bytes());
+        bytes.extend_from_slice(&self.gid.to_le_bytes());
+        bytes.extend_from_slice(&self.isv_svn_qe.to_le_bytes());
+        bytes.extend_from_slice(&self.isv_svn_pce.to_le_bytes());
+        bytes.extend_from_slice(self.qe_vendor_id.as_bytes());
+        bytes.extend_from_slice(&self.user_data);
+        bytes.extend_from_slice(&self.isv_enclave_report.to_bytes());
+
+        bytes
+    }
+
+    /// The EPID group of the platform. Only meaningful for EPID (v1/v2) quotes
+    pub fn epid_group_id(&self) -> EpidGroupId {
+        EpidGroupId(self.gid)
+    }
+}
+
+/// ID of the EPID group a platform belongs to. Intel revokes whole groups when their key is
+/// compromised, which IAS reports as `GROUP_REVOKED`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EpidGroupId(pub u32);
+
+impl EpidGroupId {
+    /// Parse a group id in the 8 hex digit format of `Display` (e.g. `00000b2f`)
+    pub fn parse(hex: &str) -> Result<Self, Error> {
+        if hex.len() != 8 {
+            warn!("Invalid EPID group id {}", hex);
+            return Err(Error::ReportParseError);
+        }
+
+        u32::from_str_radix(hex, 16).map(EpidGroupId).map_err(|_| {
+            warn!("Invalid EPID group id {}", hex);
+            Error::ReportParseError
+        })
+    }
+
+    pub fn is_revoked(&self, revoked_groups: &[EpidGroupId]) -> bool {
+        revoked_groups.contains(self)
+    }
+}
+
+impl std::fmt::Display for EpidGroupId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:08x}", self.0)
+    }
 }
 
+/// Versions of the attestation API whose reports we parse. Version 4 reports have no TCB
+/// evaluation data number
+pub const SUPPORTED_API_VERSIONS: &[u64] = &[4, 5];
+
 #[cfg(all(feature = "SGX_MODE_HW", not(feature = "production")))]
-const WHITELISTED_ADVISORIES: &[&str] = &[
+pub(crate) const WHITELISTED_ADVISORIES: &[&str] = &[
     "INTEL-SA-00334",
     "INTEL-SA-00219",
     "INTEL-SA-00615",
@@ -544,7 +1192,7 @@ const WHITELISTED_ADVISORIES: &[&str] = &[
 ];
 
 #[cfg(all(feature = "SGX_MODE_HW", feature = "production"))]
-const WHITELISTED_ADVISORIES: &[&str] = &[
+pub(crate) const WHITELISTED_ADVISORIES: &[&str] = &[
     "INTEL-SA-00334",
     "INTEL-SA-00219",
     "INTEL-SA-00615",
@@ -552,46 +1200,200 @@ const WHITELISTED_ADVISORIES: &[&str] = &[
     "INTEL-SA-00767",
 ];
 
+// reports in software mode don't go through IAS, so there are no advisories to whitelist
+#[cfg(not(feature = "SGX_MODE_HW"))]
+pub(crate) const WHITELISTED_ADVISORIES: &[&str] = &[];
+
+/// Descriptions of the advisories the enclave knows about, keyed by advisory ID. Kept as a JSON
+/// resource so that the table can grow without touching the code
+const ADVISORY_DESC_JSON: &str = include_str!("advisories.json");
+
+/// What `vulnerable` lists for advisories without a description
+pub const NO_ADVISORY_DESCRIPTION: &str = "<no description available>";
+
 lazy_static! {
-    static ref ADVISORY_DESC: HashMap<&'static str, &'static str> = [
-        (
-            "INTEL-SA-00161",
-            "You must disable hyperthreading in the BIOS"
-        ),
-        (
-            "INTEL-SA-00289",
-            "You must disable overclocking/undervolting in the BIOS"
-        ),
+    // a compile time constant, so it failing to parse is a bug in the build rather than bad input
+    static ref ADVISORY_DESC: HashMap<String, String> = serde_json::from_str(ADVISORY_DESC_JSON)
+        .expect("Failed to parse the built-in advisory descriptions");
+    static ref ADVISORY_SEVERITY: HashMap<&'static str, Severity> = [
+        ("INTEL-SA-00161", Severity::Configuration),
+        ("INTEL-SA-00219", Severity::Informational),
+        ("INTEL-SA-00233", Severity::Critical),
+        ("INTEL-SA-00289", Severity::Configuration),
+        ("INTEL-SA-00320", Severity::Critical),
+        ("INTEL-SA-00329", Severity::Critical),
+        ("INTEL-SA-00334", Severity::Informational),
+        ("INTEL-SA-00615", Severity::Informational),
+        ("INTEL-SA-00657", Severity::Informational),
+        ("INTEL-SA-00767", Severity::Informational),
+        ("INTEL-SA-00837", Severity::Critical),
     ]
     .iter()
     .copied()
     .collect();
 }
 
-#[derive(Debug)]
-pub struct AdvisoryIDs(pub Vec<String>);
+/// How much an advisory matters to the security of enclaves on an affected platform
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Mitigated in the enclave itself (e.g. by SW hardening), so only worth reporting
+    Informational,
+    /// Mitigated by configuring the platform, e.g. in the BIOS
+    Configuration,
+    /// Exploitable to extract enclave secrets unless the microcode is updated
+    Critical,
+}
 
-#[cfg(feature = "SGX_MODE_HW")]
-impl AdvisoryIDs {
-    pub(crate) fn vulnerable(&self) -> Vec<String> {
-        let mut vulnerable: Vec<String> = vec![];
-        for i in self.0.iter() {
-            if !WHITELISTED_ADVISORIES.contains(&i.as_str()) {
-                vulnerable.push(i.clone());
-                if let Some(v) = ADVISORY_DESC.get(&i.as_str()) {
-                    vulnerable.push((*v).to_string())
-                }
-            }
-        }
-        vulnerable
-    }
+/// The severity of an advisory according to the classification built into the enclave, if it's
+/// classified
+pub fn advisory_severity(id: &str) -> Option<Severity> {
+    ADVISORY_SEVERITY.get(id).copied()
 }
 
-/// A report that can be signed by Intel EPID (which generates
-/// `EndorsedAttestationReport`) and then sent off of the platform to be
-/// verified by remote client.
-#[derive(Debug)]
+/// The severity an advisory is treated as. Advisories the built-in classification doesn't know
+/// yet are assumed to be `Critical` until they are assessed
+fn assumed_severity(id: &str) -> Severity {
+    advisory_severity(id).unwrap_or(Severity::Critical)
+}
+
+/// What is known about an advisory, to tell the operator how to mitigate it
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdvisoryInfo {
+    pub description: String,
+}
+
+/// A source of advisory descriptions. Deployments can supply their own catalog to describe
+/// advisories the one built into the enclave doesn't know about yet
+pub trait AdvisoryCatalog {
+    fn describe(&self, id: &str) -> Option<AdvisoryInfo>;
+}
+
+/// The advisory descriptions built into the enclave
+pub struct BuiltinAdvisoryCatalog;
+
+impl AdvisoryCatalog for BuiltinAdvisoryCatalog {
+    fn describe(&self, id: &str) -> Option<AdvisoryInfo> {
+        ADVISORY_DESC.get(id).map(|description| AdvisoryInfo {
+            description: description.clone(),
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdvisoryIDs(pub Vec<String>);
+
+/// Whether the advisory ID follows Intel's `INTEL-SA-#####` format
+fn is_well_formed_advisory_id(advisory: &str) -> bool {
+    match advisory.strip_prefix("INTEL-SA-") {
+        Some(number) => number.len() == 5 && number.bytes().all(|b| b.is_ascii_digit()),
+        None => false,
+    }
+}
+
+impl AdvisoryIDs {
+    /// Advisory IDs that don't follow the `INTEL-SA-#####` format, or that appear more than once.
+    /// IAS never reports such IDs, so they may have been injected into the report
+    pub fn malformed(&self) -> Vec<&String> {
+        self.0
+            .iter()
+            .enumerate()
+            .filter(|(i, advisory)| {
+                !is_well_formed_advisory_id(advisory) || self.0[..*i].contains(*advisory)
+            })
+            .map(|(_, advisory)| advisory)
+            .collect()
+    }
+
+    /// Advisories whose severity is `Critical`, whether or not they are whitelisted. Like
+    /// `with_severity`, this counts advisories that aren't classified yet as critical
+    pub fn critical(&self) -> Vec<&String> {
+        self.0
+            .iter()
+            .filter(|advisory| assumed_severity(advisory) == Severity::Critical)
+            .collect()
+    }
+
+    /// Every advisory along with its severity, e.g. to color-code the health of a platform.
+    /// Advisories the built-in classification doesn't know yet are assumed to be `Critical` until
+    /// they are assessed
+    pub fn with_severity(&self) -> Vec<(String, Severity)> {
+        self.0
+            .iter()
+            .map(|advisory| (advisory.clone(), assumed_severity(advisory)))
+            .collect()
+    }
+
+    /// Advisories present in this set but not in `baseline`, i.e. issues that were disclosed (or
+    /// started to affect the platform) since the baseline was taken
+    pub fn new_advisories_vs(&self, baseline: &AdvisoryIDs) -> Vec<String> {
+        let mut new_advisories: Vec<String> = vec![];
+        for advisory in self.0.iter() {
+            if !baseline.0.contains(advisory) && !new_advisories.contains(advisory) {
+                new_advisories.push(advisory.clone());
+            }
+        }
+        new_advisories
+    }
+
+    /// Advisories that aren't whitelisted, each followed by its description in `catalog`, or by
+    /// `NO_ADVISORY_DESCRIPTION` if it has none
+    pub fn vulnerable_with(&self, catalog: &dyn AdvisoryCatalog) -> Vec<String> {
+        self.vulnerable_against_with(WHITELISTED_ADVISORIES, catalog)
+    }
+
+    /// Advisories that aren't in `whitelist`, rather than the whitelist compiled into the enclave,
+    /// each followed by its built-in description
+    pub fn vulnerable_against(&self, whitelist: &[&str]) -> Vec<String> {
+        self.vulnerable_against_with(whitelist, &BuiltinAdvisoryCatalog)
+    }
+
+    fn vulnerable_against_with(
+        &self,
+        whitelist: &[&str],
+        catalog: &dyn AdvisoryCatalog,
+    ) -> Vec<String> {
+        let mut vulnerable: Vec<String> = vec![];
+        for i in self.0.iter() {
+            if !whitelist.contains(&i.as_str()) {
+                vulnerable.push(i.clone());
+                vulnerable.push(match catalog.describe(i) {
+                    Some(info) => info.description,
+                    None => NO_ADVISORY_DESCRIPTION.to_string(),
+                });
+            }
+        }
+        vulnerable
+    }
+}
+
+#[cfg(feature = "SGX_MODE_HW")]
+impl AdvisoryIDs {
+    pub(crate) fn vulnerable(&self) -> Vec<String> {
+        self.vulnerable_against(WHITELISTED_ADVISORIES)
+    }
+}
+
+/// The advisories of a report, split by how they relate to a whitelist
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AdvisoryBreakdown {
+    /// Whitelisted advisories the platform is affected by
+    pub whitelisted_hits: Vec<String>,
+    /// Well formed advisories the platform is affected by that aren't whitelisted
+    pub non_whitelisted: Vec<String>,
+    /// Advisory IDs that don't follow the `INTEL-SA-#####` format, which can't be assessed
+    pub unknown: Vec<String>,
+}
+
+/// A report that can be signed by Intel EPID (which generates
+/// `EndorsedAttestationReport`) and then sent off of the platform to be
+/// verified by remote client.
+///
+/// It can be serialized, so that the host can store a verified report and load it back rather
+/// than verifying it again. The deserialized report is only as trustworthy as the storage
+#[derive(Serialize, Deserialize)]
 pub struct AttestationReport {
+    /// The ID IAS assigned to the report
+    pub id: Option<String>,
     /// The freshness of the report, i.e., elapsed time after acquiring the
     /// report in seconds.
     pub timestamp: u64,
@@ -599,158 +1401,706 @@ pub struct AttestationReport {
     pub sgx_quote_status: SgxQuoteStatus,
     /// Content of the quote
     pub sgx_quote_body: SgxQuote,
+    #[serde(
+        serialize_with = "as_optional_base64",
+        deserialize_with = "from_optional_base64"
+    )]
     pub platform_info_blob: Option<Vec<u8>>,
     pub advisory_ids: AdvisoryIDs,
-    pub tcb_eval_data_number: u16,
+    /// Link to Intel's security advisory pages for the advisories above, if IAS supplied one
+    pub advisory_url: Option<String>,
+    /// The TCB recovery the platform was evaluated against. Only reported since API version 5
+    pub tcb_eval_data_number: Option<u16>,
+    /// The challenge the verifier passed to IAS along with the quote, if any
+    pub nonce: Option<String>,
+    /// Identity of the platform that produced the quote, for linkable quotes
+    #[serde(
+        serialize_with = "as_optional_base64",
+        deserialize_with = "from_optional_base64"
+    )]
+    pub epid_pseudonym: Option<Vec<u8>>,
+    /// The quote as IAS returned it, for audit logs and re-verification
+    #[serde(serialize_with = "as_base64", deserialize_with = "from_base64")]
+    pub raw_quote: Vec<u8>,
+}
+
+impl std::fmt::Debug for AttestationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AttestationReport")
+            .field("id", &self.id)
+            .field("timestamp", &self.timestamp)
+            .field("sgx_quote_status", &self.sgx_quote_status)
+            .field("sgx_quote_body", &self.sgx_quote_body)
+            .field("platform_info_blob", &self.platform_info_blob)
+            .field("advisory_ids", &self.advisory_ids)
+            .field("advisory_url", &self.advisory_url)
+            .field("tcb_eval_data_number", &self.tcb_eval_data_number)
+            .field("nonce", &self.nonce)
+            .field("epid_pseudonym", &self.epid_pseudonym)
+            // the quote is parsed above, so only its size is worth logging
+            .field(
+                "raw_quote",
+                &format_args!("<{} bytes>", self.raw_quote.len()),
+            )
+            .finish()
+    }
+}
+
+const PEM_CERT_HEADER: &str = "-----BEGIN CERTIFICATE-----";
+const PEM_CERT_FOOTER: &str = "-----END CERTIFICATE-----";
+
+/// The DER encoding of a certificate that may be PEM encoded, as some transports deliver it.
+/// Certificates without a PEM header are DER already, and are returned as is
+pub fn cert_to_der(cert: &[u8]) -> Result<Cow<'_, [u8]>, Error> {
+    let pem = match std::str::from_utf8(cert) {
+        Ok(pem) if pem.trim_start().starts_with(PEM_CERT_HEADER) => pem.trim(),
+        _ => return Ok(Cow::Borrowed(cert)),
+    };
+
+    if !pem.ends_with(PEM_CERT_FOOTER) {
+        warn!("PEM certificate has no footer");
+        return Err(Error::ReportParseError);
+    }
+
+    let encoded: String = pem[PEM_CERT_HEADER.len()..pem.len() - PEM_CERT_FOOTER.len()]
+        .split_whitespace()
+        .collect();
+    let der = decode_base64(encoded.as_bytes()).map_err(|e| {
+        warn!("Failed to decode PEM certificate: {}", e);
+        Error::ReportParseError
+    })?;
+
+    Ok(Cow::Owned(der))
+}
+
+/// The time the signing cert chain is validated at when the caller doesn't supply one (seconds
+/// since epoch)
+// set as 04.11.23(dd.mm.yy) - should be valid for the foreseeable future, and not rely on SystemTime
+pub const DEFAULT_VERIFICATION_TIME: u64 = 1723218496;
+
+/// Verifies the signature of an IAS report body, and that the signing cert chains to the IAS
+/// root CA through `intermediates`, at `now` (seconds since epoch) or `DEFAULT_VERIFICATION_TIME`.
+/// The signing cert may be DER or PEM encoded
+fn verify_report_signature(
+    report: &[u8],
+    signature: &[u8],
+    signing_cert: &[u8],
+    intermediates: &[&[u8]],
+    now: Option<u64>,
+) -> Result<(), Error> {
+    let signing_cert = cert_to_der(signing_cert)?;
+    let signing_cert = signing_cert.as_ref();
+    verify_chain_signature_algorithms(signing_cert, intermediates)?;
+
+    // Verify report's signature - aka intel's signing cert
+    let signing_cert = webpki::EndEntityCert::from(signing_cert).map_err(|_err| {
+        error!("Failed to validate signature");
+        Error::CertChainInvalid
+    })?;
+
+    let (_, root_store) = get_ias_auth_config();
+
+    let trust_anchors: Vec<webpki::TrustAnchor> = root_store
+        .roots
+        .iter()
+        .map(|cert| cert.to_trust_anchor())
+        .collect();
+
+    let time_stamp =
+        webpki::Time::from_seconds_since_unix_epoch(now.unwrap_or(DEFAULT_VERIFICATION_TIME));
+
+    // note: there's no way to not validate the time, and we don't want to write this code
+    // ourselves. We also can't just ignore the error message, since that means that the rest of
+    // the validation didn't happen (time is validated early on)
+    match signing_cert.verify_is_valid_tls_server_cert(
+        SUPPORTED_SIG_ALGS,
+        &webpki::TLSServerTrustAnchors(&trust_anchors),
+        intermediates,
+        time_stamp,
+    ) {
+        Ok(_) => info!("Certificate verified successfully"),
+        Err(e) => {
+            error!("Certificate verification error {:?}", e);
+            return Err(Error::CertChainInvalid);
+        }
+    };
+
+    // Verify the signature against the signing cert
+    match signing_cert.verify_signature(&webpki::RSA_PKCS1_2048_8192_SHA256, report, signature) {
+        Ok(_) => info!("Signature verified successfully"),
+        Err(e) => {
+            warn!("Signature verification error {:?}", e);
+            return Err(Error::BadSignature);
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse the timestamp of an IAS report (UTC, without a timezone designator) to seconds since epoch
+fn parse_report_timestamp(timestamp: &str) -> Result<i64, Error> {
+    let timestamp_rfc = format!("{}Z", timestamp);
+    let time = chrono::DateTime::parse_from_rfc3339(&timestamp_rfc).map_err(|e| {
+        warn!("Failed to decode timestamp: {}", e);
+        Error::TimestampParse(timestamp.to_string())
+    })?;
+
+    Ok(time.timestamp())
+}
+
+/// `tcbEvaluationDataNumber` of an IAS report. Some IAS responses encode it as a string rather
+/// than as a number, so both are accepted
+fn parse_tcb_eval_data_number(value: &Value) -> Result<u16, Error> {
+    let number = match value {
+        Value::Number(number) => number.as_u64(),
+        Value::String(number) => number.parse::<u64>().ok(),
+        _ => None,
+    };
+
+    number
+        .and_then(|number| u16::try_from(number).ok())
+        .ok_or_else(|| {
+            warn!("Invalid TCB evaluation data number {}", value);
+            Error::ReportParseError
+        })
+}
+
+fn tcb_eval_data_number_from_json<'de, D>(deserializer: D) -> Result<Option<u16>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    parse_tcb_eval_data_number(&Value::deserialize(deserializer)?)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+/// A string of an IAS report body, borrowed from the JSON unless it has escapes (such as `\/`)
+/// that had to be resolved
+struct BodyStr<'a>(Cow<'a, str>);
+
+impl<'de> Deserialize<'de> for BodyStr<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct BodyStrVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for BodyStrVisitor {
+            type Value = BodyStr<'de>;
+
+            fn expecting(&self, formatter: &mut ::std::fmt::Formatter) -> std::fmt::Result {
+                write!(formatter, "a string")
+            }
+
+            fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(BodyStr(Cow::Borrowed(v)))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(BodyStr(Cow::Owned(v.to_string())))
+            }
+
+            fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(BodyStr(Cow::Owned(v)))
+            }
+        }
+
+        deserializer.deserialize_str(BodyStrVisitor)
+    }
+}
+
+/// The chunks of a quote body that some non-standard services split across
+/// `isvEnclaveQuoteBody1`, `isvEnclaveQuoteBody2` and so on rather than a single
+/// `isvEnclaveQuoteBody`, by chunk number. Any other field is ignored
+struct QuoteBodyChunks<'a>(BTreeMap<u32, Cow<'a, str>>);
+
+impl<'de> Deserialize<'de> for QuoteBodyChunks<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ChunksVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for ChunksVisitor {
+            type Value = QuoteBodyChunks<'de>;
+
+            fn expecting(&self, formatter: &mut ::std::fmt::Formatter) -> std::fmt::Result {
+                write!(formatter, "the fields of an IAS report")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::MapAccess<'de>,
+            {
+                let mut chunks = BTreeMap::new();
+                while let Some(BodyStr(key)) = map.next_key()? {
+                    let number = key
+                        .strip_prefix("isvEnclaveQuoteBody")
+                        .filter(|n| !n.starts_with('0') && n.bytes().all(|b| b.is_ascii_digit()))
+                        .and_then(|n| n.parse::<u32>().ok());
+                    match number {
+                        Some(number) => {
+                            let BodyStr(chunk) = map.next_value()?;
+                            chunks.insert(number, chunk);
+                        }
+                        None => {
+                            map.next_value::<serde::de::IgnoredAny>()?;
+                        }
+                    }
+                }
+
+                Ok(QuoteBodyChunks(chunks))
+            }
+        }
+
+        deserializer.deserialize_map(ChunksVisitor)
+    }
+}
+
+/// The fields of an IAS report body. Both `AttestationReport::from_report_json` and
+/// `AttestationReport::from_report_bytes` extract the report from this, so they can't drift apart
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IasReportBody<'a> {
+    #[serde(default)]
+    id: Option<String>,
+    version: u64,
+    #[serde(borrow)]
+    timestamp: BodyStr<'a>,
+    #[serde(borrow)]
+    isv_enclave_quote_status: BodyStr<'a>,
+    #[serde(borrow, default)]
+    isv_enclave_quote_body: Option<BodyStr<'a>>,
+    #[serde(borrow, default)]
+    platform_info_blob: Option<BodyStr<'a>>,
+    #[serde(rename = "advisoryIDs", default)]
+    advisory_ids: Vec<String>,
+    #[serde(rename = "advisoryURL", default)]
+    advisory_url: Option<String>,
+    #[serde(default, deserialize_with = "tcb_eval_data_number_from_json")]
+    tcb_evaluation_data_number: Option<u16>,
+    #[serde(default)]
+    nonce: Option<String>,
+    #[serde(borrow, default)]
+    epid_pseudonym: Option<BodyStr<'a>>,
+    #[serde(borrow, flatten)]
+    quote_body_chunks: QuoteBodyChunks<'a>,
+}
+
+impl<'a> IasReportBody<'a> {
+    /// The encoded quote body. A body split into chunks has the consecutive chunks concatenated
+    /// into `scratch`
+    fn quote_body_encoded<'s>(&'s self, scratch: &'s mut String) -> Result<&'s str, Error> {
+        if let Some(BodyStr(body)) = &self.isv_enclave_quote_body {
+            return Ok(body);
+        }
+
+        scratch.clear();
+        let mut chunks = 0;
+        while let Some(chunk) = self.quote_body_chunks.0.get(&(chunks + 1)) {
+            scratch.push_str(chunk);
+            chunks += 1;
+        }
+
+        if chunks == 0 {
+            warn!("Error unpacking enclave quote body");
+            return Err(Error::ReportParseError);
+        }
+        trace!("Reassembled the enclave quote body from {} chunks", chunks);
+
+        Ok(scratch)
+    }
+}
+
+/// Scratch space for `AttestationReport::from_cert_with_buffers`, to be reused across calls
+#[derive(Default)]
+pub struct VerificationBuffers {
+    quote_body: String,
 }
 
 impl AttestationReport {
     /// Construct a AttestationReport from a X509 certificate and verify
     /// attestation report with the report_ca_cert which is from the attestation
-    /// service provider.
+    /// service provider. The certificate may be DER or PEM encoded.
     // just unused in SW mode
     #[allow(dead_code)]
     pub fn from_cert(cert: &[u8]) -> Result<Self, Error> {
-        let payload = get_netscape_comment(cert).map_err(|_err| {
-            error!("Failed to get netscape comment");
+        Self::from_cert_at(cert, None)
+    }
+
+    /// Like `from_cert`, but also verifies that the first half of `report_data` is the SHA-256 of
+    /// the public key of `cert` itself, for enclaves that bind the key of their RA cert that way.
+    /// `from_cert` trusts whatever `report_data` holds, so a valid report could otherwise be
+    /// replayed in a cert with another key.
+    ///
+    /// Secret Network's registration handshake puts the node's key itself in `report_data` rather
+    /// than a hash of the cert's key, so its certs don't pass this
+    pub fn from_cert_binding(cert: &[u8]) -> Result<Self, Error> {
+        let report = Self::from_cert(cert)?;
+
+        let cert_pubkey = get_cert_pubkey(&cert_to_der(cert)?).map_err(|_| {
+            warn!("Failed to get the public key of the certificate");
             Error::ReportParseError
         })?;
+        report
+            .sgx_quote_body
+            .isv_enclave_report
+            .verify_pubkey_hash_binding(&cert_pubkey)?;
 
-        // Convert to endorsed report
-        let report: EndorsedAttestationReport = serde_json::from_slice(&payload)?;
+        Ok(report)
+    }
 
-        // Verify report's signature - aka intel's signing cert
-        let signing_cert = webpki::EndEntityCert::from(&report.signing_cert).map_err(|_err| {
-            error!("Failed to validate signature");
+    /// Like `from_cert`, but validates the signing cert chain at `now` (seconds since epoch), so
+    /// that expired certs are rejected. With `None` the chain is validated at the fixed
+    /// `DEFAULT_VERIFICATION_TIME`, as `from_cert` does.
+    ///
+    /// Consensus-critical callers must pass a time every node agrees on, such as the block time,
+    /// and never the local clock - otherwise nodes may disagree on whether a cert has expired
+    pub fn from_cert_at(cert: &[u8], now: Option<u64>) -> Result<Self, Error> {
+        let payload = get_netscape_comment(&cert_to_der(cert)?).map_err(|_err| {
+            error!("Failed to get netscape comment");
             Error::ReportParseError
         })?;
 
-        let (ias_cert, root_store) = get_ias_auth_config();
+        // Convert to endorsed report
+        let report = EndorsedAttestationReport::from_json_bytes(&payload)?;
 
-        let trust_anchors: Vec<webpki::TrustAnchor> = root_store
-            .roots
-            .iter()
-            .map(|cert| cert.to_trust_anchor())
-            .collect();
+        let (ias_cert, _) = get_ias_auth_config();
+        Self::from_signed_report_at(
+            &report.report,
+            &report.signature,
+            &report.signing_cert,
+            &[ias_cert.as_slice()],
+            now,
+        )
+    }
 
-        let chain: Vec<&[u8]> = vec![&ias_cert];
-
-        // set as 04.11.23(dd.mm.yy) - should be valid for the foreseeable future, and not rely on SystemTime
-        let time_stamp = webpki::Time::from_seconds_since_unix_epoch(1723218496);
-
-        // note: there's no way to not validate the time, and we don't want to write this code
-        // ourselves. We also can't just ignore the error message, since that means that the rest of
-        // the validation didn't happen (time is validated early on)
-        match signing_cert.verify_is_valid_tls_server_cert(
-            SUPPORTED_SIG_ALGS,
-            &webpki::TLSServerTrustAnchors(&trust_anchors),
-            &chain,
-            time_stamp,
-        ) {
-            Ok(_) => info!("Certificate verified successfully"),
-            Err(e) => {
-                error!("Certificate verification error {:?}", e);
-                return Err(Error::ReportValidationError);
-            }
-        };
+    /// Construct a AttestationReport from a report endorsed by IAS, whose signing cert is issued
+    /// by the IAS root CA
+    pub fn from_endorsed(report: &EndorsedAttestationReport) -> Result<Self, Error> {
+        let (ias_cert, _) = get_ias_auth_config();
 
-        // Verify the signature against the signing cert
-        match signing_cert.verify_signature(
-            &webpki::RSA_PKCS1_2048_8192_SHA256,
+        Self::from_signed_report(
             &report.report,
             &report.signature,
-        ) {
-            Ok(_) => info!("Signature verified successfully"),
-            Err(e) => {
-                warn!("Signature verification error {:?}", e);
-                return Err(Error::ReportParseError);
-            }
-        }
+            &report.signing_cert,
+            &[ias_cert.as_slice()],
+        )
+    }
+
+    /// Construct a AttestationReport from a report body and its signature, where the
+    /// end-entity signing cert and the intermediates that chain it to the IAS root CA are
+    /// supplied separately
+    pub fn from_signed_report(
+        report: &[u8],
+        signature: &[u8],
+        signing_cert: &[u8],
+        intermediates: &[&[u8]],
+    ) -> Result<Self, Error> {
+        Self::from_signed_report_at(report, signature, signing_cert, intermediates, None)
+    }
+
+    fn from_signed_report_at(
+        report: &[u8],
+        signature: &[u8],
+        signing_cert: &[u8],
+        intermediates: &[&[u8]],
+        now: Option<u64>,
+    ) -> Result<Self, Error> {
+        verify_report_signature(report, signature, signing_cert, intermediates, now)?;
 
         // Verify and extract information from attestation report
-        let attn_report: Value = serde_json::from_slice(&report.report)?;
+        let attn_report: Value = serde_json::from_slice(report)?;
         trace!("attn_report: {}", attn_report);
 
-        // Verify API version is supported
-        let version = attn_report["version"]
-            .as_u64()
-            .ok_or(Error::ReportParseError)?;
+        Self::from_report_json(&attn_report)
+    }
+
+    /// Like `from_cert`, but also returns the report body as JSON, for inspecting the fields
+    /// `AttestationReport` doesn't model
+    pub fn from_cert_with_json(cert: &[u8]) -> Result<(Self, Value), Error> {
+        let payload = get_netscape_comment(&cert_to_der(cert)?).map_err(|_err| {
+            error!("Failed to get netscape comment");
+            Error::ReportParseError
+        })?;
+        let report = EndorsedAttestationReport::from_json_bytes(&payload)?;
+
+        let (ias_cert, _) = get_ias_auth_config();
+        verify_report_signature(
+            &report.report,
+            &report.signature,
+            &report.signing_cert,
+            &[ias_cert.as_slice()],
+            None,
+        )?;
+
+        let attn_report: Value = serde_json::from_slice(&report.report)?;
+        let parsed = Self::from_report_json(&attn_report)?;
+
+        Ok((parsed, attn_report))
+    }
+
+    /// Like `from_cert`, but with fewer allocations for the hot path of registration - the report
+    /// body is deserialized in place rather than through a `serde_json::Value`, and a chunked
+    /// quote body is reassembled in `buffers`, which can be reused across verifications
+    pub fn from_cert_with_buffers(
+        cert: &[u8],
+        buffers: &mut VerificationBuffers,
+    ) -> Result<Self, Error> {
+        let payload = get_netscape_comment(&cert_to_der(cert)?).map_err(|_err| {
+            error!("Failed to get netscape comment");
+            Error::ReportParseError
+        })?;
+        let report = EndorsedAttestationReport::from_json_bytes(&payload)?;
 
-        if version != 5 {
+        let (ias_cert, _) = get_ias_auth_config();
+        verify_report_signature(
+            &report.report,
+            &report.signature,
+            &report.signing_cert,
+            &[ias_cert.as_slice()],
+            None,
+        )?;
+
+        Self::from_report_bytes(&report.report, buffers)
+    }
+
+    /// Extract the attestation report fields from the body of an IAS report. The body is
+    /// expected to have been verified against the IAS signing cert already.
+    pub(crate) fn from_report_json(attn_report: &Value) -> Result<Self, Error> {
+        Self::from_report_body(IasReportBody::deserialize(attn_report)?, &mut String::new())
+    }
+
+    /// Same as `from_report_json`, but straight from the bytes of the report body
+    fn from_report_bytes(report: &[u8], buffers: &mut VerificationBuffers) -> Result<Self, Error> {
+        Self::from_report_body(serde_json::from_slice(report)?, &mut buffers.quote_body)
+    }
+
+    fn from_report_body(body: IasReportBody, quote_body: &mut String) -> Result<Self, Error> {
+        // Verify API version is supported
+        if !SUPPORTED_API_VERSIONS.contains(&body.version) {
             warn!("API version incompatible");
-            return Err(Error::ReportParseError);
+            return Err(Error::UnsupportedApiVersion(body.version));
         };
 
-        let mut platform_info_blob = None;
-        if let Some(blob) = attn_report["platformInfoBlob"].as_str() {
-            let as_binary = hex::decode(blob).map_err(|_| {
+        let platform_info_blob = match &body.platform_info_blob {
+            Some(BodyStr(blob)) => Some(decode_hex(blob.as_bytes()).map_err(|_| {
                 warn!("Error parsing platform info");
                 Error::ReportParseError
-            })?;
-            platform_info_blob = Some(as_binary)
-        }
-
-        // Get quote status
-        let sgx_quote_status = {
-            let status_string = attn_report["isvEnclaveQuoteStatus"]
-                .as_str()
-                .ok_or_else(|| {
-                    warn!("Error parsing enclave quote status");
-                    Error::ReportParseError
-                })?;
-            SgxQuoteStatus::from(status_string)
+            })?),
+            None => None,
         };
 
+        let sgx_quote_status = SgxQuoteStatus::from(body.isv_enclave_quote_status.0.as_ref());
+
         // Get quote body
-        let sgx_quote_body = {
-            let quote_encoded = attn_report["isvEnclaveQuoteBody"].as_str().ok_or_else(|| {
-                warn!("Error unpacking enclave quote body");
-                Error::ReportParseError
-            })?;
-            let quote_raw = base64::decode(quote_encoded.as_bytes()).map_err(|_| {
-                warn!("Error decoding encoded quote body");
-                Error::ReportParseError
-            })?;
-            SgxQuote::parse_from(quote_raw.as_slice())?
-        };
+        let quote_encoded = body.quote_body_encoded(quote_body)?;
+        let quote_raw = decode_base64(quote_encoded.as_bytes()).map_err(|_| {
+            warn!("Error decoding encoded quote body");
+            Error::ReportParseError
+        })?;
+        let sgx_quote_body = SgxQuote::parse_from(quote_raw.as_slice())?;
 
-        let advisories: Vec<String> = if let Some(raw) = attn_report.get("advisoryIDs") {
-            serde_json::from_value(raw.clone()).map_err(|_| {
-                warn!("Failed to decode advisories");
+        let tcb_eval_data_number = match body.version {
+            4 => None,
+            _ => Some(body.tcb_evaluation_data_number.ok_or_else(|| {
+                warn!("Report has no TCB evaluation data number");
                 Error::ReportParseError
-            })?
-        } else {
-            vec![]
+            })?),
         };
 
-        let tcb_eval_data_number = attn_report["tcbEvaluationDataNumber"]
-            .as_u64()
-            .ok_or(Error::ReportParseError)? as u16;
-
-        let timestamp_str = attn_report["timestamp"]
-            .as_str()
-            .ok_or(Error::ReportParseError)?;
+        let epid_pseudonym = match &body.epid_pseudonym {
+            Some(BodyStr(pseudonym)) => {
+                Some(decode_base64(pseudonym.as_bytes()).map_err(|_| {
+                    warn!("Error decoding EPID pseudonym");
+                    Error::ReportParseError
+                })?)
+            }
+            None => None,
+        };
 
-        let timestamp_rfc = format!("{}Z", timestamp_str);
-        let time = chrono::DateTime::parse_from_rfc3339(&timestamp_rfc).map_err(|e| {
-            warn!("Failed to decode timestamp: {}", e);
-            Error::ReportParseError
-        })?;
-        let timestamp_since_epoch = time.timestamp();
+        let timestamp_since_epoch = parse_report_timestamp(&body.timestamp.0)?;
 
         // We don't actually validate the public key, since we use ephemeral certificates,
         // and all we really care about that the report is valid and the key that is saved in the
         // report_data field
 
         Ok(Self {
+            id: body.id,
             timestamp: timestamp_since_epoch as u64,
             sgx_quote_status,
             sgx_quote_body,
             platform_info_blob,
-            advisory_ids: AdvisoryIDs(advisories),
+            advisory_ids: AdvisoryIDs(body.advisory_ids),
+            advisory_url: body.advisory_url,
             tcb_eval_data_number,
+            nonce: body.nonce,
+            epid_pseudonym,
+            raw_quote: quote_raw,
         })
     }
+
+    /// The version of the quote, which tells how the platform attested
+    pub fn quote_version(&self) -> &SgxQuoteVersion {
+        &self.sgx_quote_body.version
+    }
+
+    /// Whether the quote was signed with an ECDSA attestation key (DCAP) rather than with EPID
+    pub fn is_ecdsa(&self) -> bool {
+        matches!(
+            self.quote_version(),
+            SgxQuoteVersion::V3(_) | SgxQuoteVersion::V4(..)
+        )
+    }
+
+    /// The quote as IAS returned it, wrapped in a DER OCTET STRING. The raw bytes are used rather
+    /// than a re-encoding of the parsed quote, which would zero the reserved and KSS fields and so
+    /// no longer be the quote IAS signed
+    pub fn quote_to_der(&self) -> Vec<u8> {
+        yasna::construct_der(|writer| writer.write_bytes(&self.raw_quote))
+    }
+
+    /// The DER encoded quote in a PEM block labeled `SGX QUOTE`, for tools that read PEM
+    pub fn quote_to_pem(&self) -> String {
+        let encoded = base64::encode(&self.quote_to_der());
+
+        let mut pem = String::from("-----BEGIN SGX QUOTE-----\n");
+        for line in encoded.as_bytes().chunks(64) {
+            // base64 is ascii, so the chunks are always valid utf8
+            pem.push_str(std::str::from_utf8(line).unwrap_or_default());
+            pem.push('\n');
+        }
+        pem.push_str("-----END SGX QUOTE-----\n");
+
+        pem
+    }
+
+    /// Verify that the report is of an allowed enclave - its MRENCLAVE is one of
+    /// `expected_mr_enclave`, it's signed by `expected_mr_signer`, and its ISV SVN is at least
+    /// `min_isv_svn`. The quote status and advisories are the business of `evaluate`
+    pub fn verify(
+        &self,
+        expected_mr_enclave: &[[u8; 32]],
+        expected_mr_signer: &[u8; 32],
+        min_isv_svn: u16,
+    ) -> Result<(), Error> {
+        let enclave_report = &self.sgx_quote_body.isv_enclave_report;
+
+        // every allowed measurement is compared, so that the time taken doesn't reveal which one
+        // (if any) matched
+        let allowed = expected_mr_enclave.iter().fold(false, |allowed, expected| {
+            allowed | measurements_equal(expected, &enclave_report.mr_enclave)
+        });
+        if !allowed {
+            warn!(
+                "MRENCLAVE {} is not in the allowlist",
+                hex::encode(enclave_report.mr_enclave)
+            );
+            return Err(Error::MrEnclaveNotAllowed(enclave_report.mr_enclave));
+        }
+
+        if !measurements_equal(&enclave_report.mr_signer, expected_mr_signer) {
+            warn!(
+                "MRSIGNER {} is not the expected {}",
+                hex::encode(enclave_report.mr_signer),
+                hex::encode(expected_mr_signer)
+            );
+            return Err(Error::MrSignerMismatch(enclave_report.mr_signer));
+        }
+
+        if enclave_report.isv_svn < min_isv_svn {
+            warn!(
+                "ISV SVN {} is lower than the minimum {}",
+                enclave_report.isv_svn, min_isv_svn
+            );
+            return Err(Error::IsvSvnTooLow {
+                min: min_isv_svn,
+                got: enclave_report.isv_svn,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Verify that the report is of an allowed enclave identity - one of the `allowed`
+    /// `(mr_signer, isv_prod_id, min_isv_svn)` tuples has its MRSIGNER and product ID, and its
+    /// ISV SVN is at least the tuple's minimum. Unlike `verify`, any build of the product is
+    /// accepted, so enclaves can be upgraded without updating the allowlist
+    pub fn verify_signer_identity(&self, allowed: &[([u8; 32], u16, u16)]) -> Result<(), Error> {
+        let enclave_report = &self.sgx_quote_body.isv_enclave_report;
+
+        let of_signer: Vec<_> = allowed
+            .iter()
+            .filter(|(mr_signer, _, _)| measurements_equal(mr_signer, &enclave_report.mr_signer))
+            .collect();
+        if of_signer.is_empty() {
+            warn!(
+                "MRSIGNER {} is not in the allowlist",
+                hex::encode(enclave_report.mr_signer)
+            );
+            return Err(Error::MrSignerMismatch(enclave_report.mr_signer));
+        }
+
+        // the product may be listed more than once, in which case the lowest SVN is the minimum
+        let min_isv_svn = of_signer
+            .iter()
+            .filter(|(_, isv_prod_id, _)| *isv_prod_id == enclave_report.isv_prod_id)
+            .map(|(_, _, min_isv_svn)| *min_isv_svn)
+            .min();
+        match min_isv_svn {
+            None => {
+                warn!(
+                    "ISV product ID {} is not allowed for MRSIGNER {}",
+                    enclave_report.isv_prod_id,
+                    hex::encode(enclave_report.mr_signer)
+                );
+                Err(Error::IsvProdIdNotAllowed(enclave_report.isv_prod_id))
+            }
+            Some(min) if enclave_report.isv_svn < min => {
+                warn!(
+                    "ISV SVN {} is lower than the minimum {}",
+                    enclave_report.isv_svn, min
+                );
+                Err(Error::IsvSvnTooLow {
+                    min,
+                    got: enclave_report.isv_svn,
+                })
+            }
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Splits the advisories of the report into the whitelisted ones, the other well formed ones,
+    /// and the malformed ones. Advisories listed more than once are only counted once
+    pub fn advisory_breakdown(&self, whitelist: &[&str]) -> AdvisoryBreakdown {
+        let mut breakdown = AdvisoryBreakdown::default();
+
+        for (i, advisory) in self.advisory_ids.0.iter().enumerate() {
+            if self.advisory_ids.0[..i].contains(advisory) {
+                continue;
+            }
+
+            let bucket = if !is_well_formed_advisory_id(advisory) {
+                &mut breakdown.unknown
+            } else if whitelist.contains(&advisory.as_str()) {
+                &mut breakdown.whitelisted_hits
+            } else {
+                &mut breakdown.non_whitelisted
+            };
+            bucket.push(advisory.clone());
+        }
+
+        breakdown
+    }
 }
 
 #[cfg(feature = "test")]
@@ -760,55 +2110,40 @@ pub mod tests {
     use std::untrusted::fs::File;
 
     use crate::registration::attestation::verify_quote_ecdsa;
+    use crate::registration::dcap::tests::{dcap_collateral_fixture, dcap_quote_fixture};
+    use crate::registration::dcap::{DcapCollateral, DcapError};
 
     use super::*;
 
-    fn tls_ra_cert_der_test() -> Vec<u8> {
-        let mut cert = vec![];
-        let mut f =
-            File::open("../execute/src/registration/fixtures/attestation_cert_hw_invalid_test.der")
-                .unwrap();
-        f.read_to_end(&mut cert).unwrap();
+    /// The contents of a file in the `fixtures` directory, for the tests of any module
+    pub fn read_fixture(name: &str) -> Vec<u8> {
+        let mut bytes = vec![];
+        let mut f = File::open(format!("../execute/src/registration/fixtures/{}", name)).unwrap();
+        f.read_to_end(&mut bytes).unwrap();
 
-        cert
+        bytes
     }
 
-    fn tls_ra_cert_der_v3() -> Vec<u8> {
-        let mut cert = vec![];
-        let mut f = File::open("../execute/src/registration/fixtures/tls_ra_cert_v3.der").unwrap();
-        f.read_to_end(&mut cert).unwrap();
-
-        cert
+    fn tls_ra_cert_der_test() -> Vec<u8> {
+        read_fixture("attestation_cert_hw_invalid_test.der")
     }
 
-    fn tls_ra_cert_der_v4() -> Vec<u8> {
-        let mut cert = vec![];
-        let mut f =
-            File::open("../execute/src/registration/fixtures/attestation_cert_out_of_date.der")
-                .unwrap();
-        f.read_to_end(&mut cert).unwrap();
+    fn tls_ra_cert_der_v3() -> Vec<u8> {
+        read_fixture("tls_ra_cert_v3.der")
+    }
 
-        cert
+    /// An RA cert with a v4 IAS report of a `GROUP_OUT_OF_DATE` platform, signed by Intel, for
+    /// tests of any module that need real evidence
+    pub fn tls_ra_cert_der_v4() -> Vec<u8> {
+        read_fixture("attestation_cert_out_of_date.der")
     }
 
     fn _test_aes_encrypttls_ra_cert_der_out_of_date() -> Vec<u8> {
-        let mut cert = vec![];
-        let mut f = File::open(
-            "../execute/src/registration/fixtures/attestation_cert_sw_config_needed.der",
-        )
-        .unwrap();
-        f.read_to_end(&mut cert).unwrap();
-
-        cert
+        read_fixture("attestation_cert_sw_config_needed.der")
     }
 
     fn _ias_root_ca_cert_der() -> Vec<u8> {
-        let mut cert = vec![];
-        let mut f =
-            File::open("../execute/src/registration/fixtures/ias_root_ca_cert.der").unwrap();
-        f.read_to_end(&mut cert).unwrap();
-
-        cert
+        read_fixture("ias_root_ca_cert.der")
     }
 
     fn attesation_report() -> Value {
@@ -843,6 +2178,236 @@ pub mod tests {
         report
     }
 
+    /// The static report above, updated to the fields of the currently supported API version
+    fn attesation_report_v5() -> Value {
+        let mut report = attesation_report();
+        report["version"] = json!(5);
+        report["tcbEvaluationDataNumber"] = json!(16);
+
+        report
+    }
+
+    /// Builds an `AttestationReport` out of the static report above, for tests that only care
+    /// about the parsed fields and not about the IAS signature
+    pub fn mock_attestation_report() -> AttestationReport {
+        let attn_report = attesation_report();
+        let sgx_quote_body_encoded = attn_report["isvEnclaveQuoteBody"].as_str().unwrap();
+        let quote_raw = base64::decode(&sgx_quote_body_encoded.as_bytes()).unwrap();
+
+        AttestationReport {
+            id: attn_report["id"].as_str().map(|id| id.to_string()),
+            timestamp: 1581459959,
+            sgx_quote_status: SgxQuoteStatus::GroupOutOfDate,
+            sgx_quote_body: SgxQuote::parse_from(quote_raw.as_slice()).unwrap(),
+            platform_info_blob: None,
+            advisory_ids: AdvisoryIDs(vec![]),
+            advisory_url: None,
+            tcb_eval_data_number: Some(16),
+            nonce: None,
+            epid_pseudonym: None,
+            raw_quote: quote_raw,
+        }
+    }
+
+    /// The platform info blob of the static report above
+    pub fn mock_platform_info_blob() -> Vec<u8> {
+        let attn_report = attesation_report();
+        hex::decode(attn_report["platformInfoBlob"].as_str().unwrap()).unwrap()
+    }
+
+    pub fn test_chunked_quote_body() {
+        let mut attn_report = attesation_report_v5();
+        let body = attn_report["isvEnclaveQuoteBody"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let report = AttestationReport::from_report_json(&attn_report).unwrap();
+
+        attn_report
+            .as_object_mut()
+            .unwrap()
+            .remove("isvEnclaveQuoteBody");
+        attn_report["isvEnclaveQuoteBody1"] = json!(body[..300]);
+        attn_report["isvEnclaveQuoteBody2"] = json!(body[300..]);
+        let chunked = AttestationReport::from_report_json(&attn_report).unwrap();
+        assert_eq!(chunked.sgx_quote_body, report.sgx_quote_body);
+
+        // chunks have to be consecutive
+        attn_report
+            .as_object_mut()
+            .unwrap()
+            .remove("isvEnclaveQuoteBody1");
+        assert!(AttestationReport::from_report_json(&attn_report).is_err());
+    }
+
+    pub fn test_quote_status_to_auth_result() {
+        for (status, result) in [
+            (SgxQuoteStatus::OK, NodeAuthResult::BadQuoteStatus),
+            (
+                SgxQuoteStatus::SignatureInvalid,
+                NodeAuthResult::BadQuoteStatus,
+            ),
+            (SgxQuoteStatus::GroupRevoked, NodeAuthResult::GroupRevoked),
+            (
+                SgxQuoteStatus::SignatureRevoked,
+                NodeAuthResult::SignatureRevoked,
+            ),
+            (SgxQuoteStatus::KeyRevoked, NodeAuthResult::KeyRevoked),
+            (
+                SgxQuoteStatus::SigrlVersionMismatch,
+                NodeAuthResult::SigrlVersionMismatch,
+            ),
+            (
+                SgxQuoteStatus::GroupOutOfDate,
+                NodeAuthResult::GroupOutOfDate,
+            ),
+            (
+                SgxQuoteStatus::ConfigurationNeeded,
+                NodeAuthResult::ConfigurationNeeded,
+            ),
+            (
+                SgxQuoteStatus::SwHardeningNeeded,
+                NodeAuthResult::SwHardeningNeeded,
+            ),
+            (
+                SgxQuoteStatus::ConfigurationAndSwHardeningNeeded,
+                NodeAuthResult::SwHardeningAndConfigurationNeeded,
+            ),
+            (SgxQuoteStatus::OutOfDate, NodeAuthResult::OutOfDate),
+            (
+                SgxQuoteStatus::OutOfDateConfigurationNeeded,
+                NodeAuthResult::OutOfDateConfigurationNeeded,
+            ),
+            (
+                SgxQuoteStatus::UnknownBadStatus,
+                NodeAuthResult::UnknownQuoteStatus,
+            ),
+        ]
+        .iter()
+        {
+            assert_eq!(NodeAuthResult::from(status), *result, "{:?}", status);
+        }
+    }
+
+    pub fn test_take_bytes_overflow() {
+        let bytes = [1u8, 2, 3, 4];
+
+        let mut pos = 1;
+        assert_eq!(take_bytes(&bytes, &mut pos, 2), Some(&bytes[1..3]));
+        assert_eq!(pos, 3);
+        assert_eq!(take_bytes(&bytes, &mut pos, 2), None);
+        assert_eq!(pos, 3);
+
+        // `pos + n` wraps around to a small offset, which a plain addition would let through
+        let mut pos = 2;
+        assert_eq!(take_bytes(&bytes, &mut pos, usize::MAX), None);
+        let mut pos = usize::MAX;
+        assert_eq!(take_bytes(&bytes, &mut pos, 2), None);
+        assert_eq!(pos, usize::MAX);
+
+        // collateral that declares items of the largest sizes it can encode
+        let mut collateral = vec![0u8; 4];
+        for _ in 0..7 {
+            collateral.extend_from_slice(&u32::MAX.to_le_bytes());
+        }
+        collateral.extend_from_slice(&[0u8; 64]);
+        assert!(DcapCollateral::parse_from(&collateral).is_err());
+
+        assert!(matches!(
+            SgxQuote::parse_from(&[2, 0, 1, 0, 0, 0]),
+            Err(Error::TruncatedReport { got: 6, .. })
+        ));
+    }
+
+    pub fn test_specific_errors() {
+        let attn_report = attesation_report_v5();
+        let sgx_quote_body_encoded = attn_report["isvEnclaveQuoteBody"].as_str().unwrap();
+        let quote_raw = base64::decode(&sgx_quote_body_encoded.as_bytes()).unwrap();
+
+        assert!(matches!(
+            SgxEnclaveReport::parse_from(&quote_raw[48..148]),
+            Err(Error::TruncatedReport {
+                expected: ENCLAVE_REPORT_SIZE,
+                got: 100
+            })
+        ));
+        // one byte short and one byte over the fixed size
+        let report = &quote_raw[48..48 + ENCLAVE_REPORT_SIZE];
+        assert!(SgxEnclaveReport::parse_from(report).is_ok());
+        assert!(matches!(
+            SgxEnclaveReport::parse_from(&report[..ENCLAVE_REPORT_SIZE - 1]),
+            Err(Error::TruncatedReport {
+                expected: ENCLAVE_REPORT_SIZE,
+                got: 383
+            })
+        ));
+        let mut longer = report.to_vec();
+        longer.push(0);
+        assert!(matches!(
+            SgxEnclaveReport::parse_from(&longer),
+            Err(Error::TruncatedReport {
+                expected: ENCLAVE_REPORT_SIZE,
+                got: 385
+            })
+        ));
+
+        assert!(matches!(
+            SgxQuote::parse_from(&quote_raw[..200]),
+            Err(Error::TruncatedReport {
+                expected: QUOTE_SIZE,
+                got: 200
+            })
+        ));
+
+        let mut unknown_version = quote_raw.clone();
+        unknown_version[0] = 9;
+        assert!(matches!(
+            SgxQuote::parse_from(&unknown_version),
+            Err(Error::UnsupportedQuoteVersion(9))
+        ));
+
+        let mut old_api = attn_report.clone();
+        old_api["version"] = json!(3);
+        assert!(matches!(
+            AttestationReport::from_report_json(&old_api),
+            Err(Error::UnsupportedApiVersion(3))
+        ));
+
+        let mut bad_timestamp = attn_report;
+        bad_timestamp["timestamp"] = json!("yesterday");
+        match AttestationReport::from_report_json(&bad_timestamp) {
+            Err(Error::TimestampParse(timestamp)) => assert_eq!(timestamp, "yesterday"),
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+
+    pub fn test_extended_enclave_report() {
+        let bytes = read_fixture("enclave_report_kss.bin");
+
+        let extended = SgxExtendedEnclaveReport::parse_from(&bytes).unwrap();
+        assert!(extended.has_kss_fields());
+        assert_eq!(
+            extended.isv_ext_prod_id,
+            [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16]
+        );
+        assert_eq!(extended.config_id[..4], [0xc0, 0xc1, 0xc2, 0xc3]);
+        assert_eq!(extended.config_id[63], 0xcf);
+        assert_eq!(extended.config_svn, 7);
+        assert_eq!(extended.isv_family_id[0], 0xf0);
+        assert_eq!(extended.isv_family_id[15], 0xff);
+
+        // the fields of the plain report are where they always were
+        let plain = mock_attestation_report().sgx_quote_body.isv_enclave_report;
+        assert!(extended.report.matches_golden(&plain, &[]));
+        assert_eq!(SgxEnclaveReport::parse_from(&bytes).unwrap(), plain);
+
+        // a report of a platform without KSS
+        let extended = SgxExtendedEnclaveReport::parse_from(&plain.to_bytes()).unwrap();
+        assert!(!extended.has_kss_fields());
+
+        assert!(SgxExtendedEnclaveReport::parse_from(&bytes[..383]).is_err());
+    }
+
     pub fn test_sgx_quote_parse_from() {
         let attn_report = attesation_report();
         let sgx_quote_body_encoded = attn_report["isvEnclaveQuoteBody"].as_str().unwrap();
@@ -903,6 +2468,580 @@ pub mod tests {
         );
     }
 
+    pub fn test_advisory_ids_new_vs_baseline() {
+        let baseline = AdvisoryIDs(vec![
+            "INTEL-SA-00161".to_string(),
+            "INTEL-SA-00219".to_string(),
+            "INTEL-SA-00334".to_string(),
+        ]);
+        // 00161 was mitigated since, 00615 is newly disclosed
+        let current = AdvisoryIDs(vec![
+            "INTEL-SA-00219".to_string(),
+            "INTEL-SA-00615".to_string(),
+            "INTEL-SA-00334".to_string(),
+        ]);
+
+        assert_eq!(
+            current.new_advisories_vs(&baseline),
+            vec!["INTEL-SA-00615".to_string()]
+        );
+        assert!(baseline.new_advisories_vs(&baseline).is_empty());
+        assert_eq!(baseline.new_advisories_vs(&AdvisoryIDs(vec![])), baseline.0);
+    }
+
+    pub fn test_sgx_quote_status_display() {
+        let statuses = [
+            SgxQuoteStatus::OK,
+            SgxQuoteStatus::SignatureInvalid,
+            SgxQuoteStatus::GroupRevoked,
+            SgxQuoteStatus::SignatureRevoked,
+            SgxQuoteStatus::KeyRevoked,
+            SgxQuoteStatus::SigrlVersionMismatch,
+            SgxQuoteStatus::GroupOutOfDate,
+            SgxQuoteStatus::ConfigurationNeeded,
+            SgxQuoteStatus::SwHardeningNeeded,
+            SgxQuoteStatus::ConfigurationAndSwHardeningNeeded,
+            SgxQuoteStatus::OutOfDate,
+            SgxQuoteStatus::OutOfDateConfigurationNeeded,
+        ];
+
+        for status in statuses.iter() {
+            assert_eq!(SgxQuoteStatus::from(status.to_string().as_str()), *status);
+        }
+
+        assert_eq!(
+            SgxQuoteStatus::ConfigurationAndSwHardeningNeeded.to_string(),
+            "CONFIGURATION_AND_SW_HARDENING_NEEDED"
+        );
+        assert_eq!(
+            SgxQuoteStatus::UnknownBadStatus.to_string(),
+            "UNKNOWN_BAD_STATUS"
+        );
+    }
+
+    pub fn test_epid_group_id() {
+        let attn_report = attesation_report();
+        let sgx_quote_body_encoded = attn_report["isvEnclaveQuoteBody"].as_str().unwrap();
+        let quote_raw = base64::decode(&sgx_quote_body_encoded.as_bytes()).unwrap();
+        let gid = SgxQuote::parse_from(quote_raw.as_slice())
+            .unwrap()
+            .epid_group_id();
+
+        assert_eq!(gid.to_string(), "00000b2f");
+        assert_eq!(EpidGroupId::parse("00000b2f").unwrap(), gid);
+        assert_eq!(EpidGroupId::parse("00000B2F").unwrap(), gid);
+        assert!(EpidGroupId::parse("b2f").is_err());
+        assert!(EpidGroupId::parse("0000xb2f").is_err());
+
+        let revoked_groups = vec![EpidGroupId(0x0b2e), EpidGroupId(0x0b2f)];
+        assert!(gid.is_revoked(&revoked_groups));
+        assert!(!gid.is_revoked(&revoked_groups[..1]));
+
+        let report = mock_attestation_report();
+        assert_eq!(
+            report.verify_group_not_revoked(&revoked_groups),
+            Err(NodeAuthResult::GroupRevoked)
+        );
+        assert_eq!(report.verify_group_not_revoked(&[]), Ok(()));
+    }
+
+    pub fn test_advisory_ids_malformed() {
+        let advisories = AdvisoryIDs(vec![
+            "INTEL-SA-00334".to_string(),
+            "INTEL-SA-00615".to_string(),
+        ]);
+        assert!(advisories.malformed().is_empty());
+
+        let advisories = AdvisoryIDs(vec![
+            "INTEL-SA-00334".to_string(),
+            "INTEL-SA-334".to_string(),
+            "intel-sa-00615".to_string(),
+            "INTEL-SA-0061a".to_string(),
+            "INTEL-SA-00334".to_string(),
+        ]);
+        assert_eq!(
+            advisories.malformed(),
+            vec![
+                "INTEL-SA-334",
+                "intel-sa-00615",
+                "INTEL-SA-0061a",
+                "INTEL-SA-00334"
+            ]
+        );
+    }
+
+    pub fn test_advisory_ids_with_severity() {
+        let advisories = AdvisoryIDs(vec![
+            "INTEL-SA-00334".to_string(),
+            "INTEL-SA-00161".to_string(),
+            "INTEL-SA-00233".to_string(),
+            "INTEL-SA-99999".to_string(),
+        ]);
+        assert_eq!(
+            advisories.with_severity(),
+            vec![
+                ("INTEL-SA-00334".to_string(), Severity::Informational),
+                ("INTEL-SA-00161".to_string(), Severity::Configuration),
+                ("INTEL-SA-00233".to_string(), Severity::Critical),
+                ("INTEL-SA-99999".to_string(), Severity::Critical),
+            ]
+        );
+        // `critical` fails closed on the unclassified advisory too, like `with_severity`
+        assert_eq!(
+            advisories.critical(),
+            vec!["INTEL-SA-00233", "INTEL-SA-99999"]
+        );
+    }
+
+    pub fn test_sgx_quote_to_pem() {
+        let mut report = AttestationReport::from_report_json(&attesation_report()).unwrap();
+        // a reserved byte of the enclave report, which the parsed quote doesn't keep
+        report.raw_quote[48 + 20] = 0x5a;
+        assert_ne!(report.sgx_quote_body.to_bytes(), report.raw_quote);
+
+        let pem = report.quote_to_pem();
+        assert!(pem.starts_with("-----BEGIN SGX QUOTE-----\n"));
+        assert!(pem.ends_with("-----END SGX QUOTE-----\n"));
+
+        let encoded: String = pem
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .collect();
+        let der = base64::decode(&encoded).unwrap();
+        assert_eq!(der, report.quote_to_der());
+
+        let decoded = yasna::parse_der(&der, |reader| reader.read_bytes()).unwrap();
+        assert_eq!(decoded, report.raw_quote);
+    }
+
+    pub fn test_advisory_catalog() {
+        struct LocalCatalog;
+        impl AdvisoryCatalog for LocalCatalog {
+            fn describe(&self, id: &str) -> Option<AdvisoryInfo> {
+                match id {
+                    "INTEL-SA-00999" => Some(AdvisoryInfo {
+                        description: "You must update the microcode".to_string(),
+                    }),
+                    _ => None,
+                }
+            }
+        }
+
+        assert_eq!(
+            BuiltinAdvisoryCatalog.describe("INTEL-SA-00161"),
+            Some(AdvisoryInfo {
+                description: "You must disable hyperthreading in the BIOS".to_string()
+            })
+        );
+        assert_eq!(BuiltinAdvisoryCatalog.describe("INTEL-SA-00999"), None);
+
+        let advisories = AdvisoryIDs(vec!["INTEL-SA-00999".to_string()]);
+        assert_eq!(
+            advisories.vulnerable_with(&BuiltinAdvisoryCatalog),
+            vec![
+                "INTEL-SA-00999".to_string(),
+                NO_ADVISORY_DESCRIPTION.to_string()
+            ]
+        );
+        assert_eq!(
+            advisories.vulnerable_with(&LocalCatalog),
+            vec![
+                "INTEL-SA-00999".to_string(),
+                "You must update the microcode".to_string()
+            ]
+        );
+    }
+
+    pub fn test_advisory_descriptions() {
+        // the embedded table parses, and covers every advisory we whitelist or classify
+        for advisory in WHITELISTED_ADVISORIES
+            .iter()
+            .chain(ADVISORY_SEVERITY.keys())
+        {
+            assert!(
+                BuiltinAdvisoryCatalog.describe(advisory).is_some(),
+                "{} has no description",
+                advisory
+            );
+        }
+        assert_eq!(
+            BuiltinAdvisoryCatalog.describe("INTEL-SA-00289"),
+            Some(AdvisoryInfo {
+                description: "You must disable overclocking/undervolting in the BIOS".to_string()
+            })
+        );
+
+        // every vulnerable advisory comes with a description, known or not
+        let unknown = AdvisoryIDs(vec!["INTEL-SA-00001".to_string()]);
+        let vulnerable = unknown.vulnerable_against(&[]);
+        assert_eq!(vulnerable.len(), 2);
+        assert_eq!(vulnerable[0], "INTEL-SA-00001");
+        assert_eq!(vulnerable[1], NO_ADVISORY_DESCRIPTION);
+
+        let known = AdvisoryIDs(vec!["INTEL-SA-00837".to_string()]);
+        let vulnerable = known.vulnerable_against(&[]);
+        assert_eq!(vulnerable.len(), 2);
+        assert_ne!(vulnerable[1], NO_ADVISORY_DESCRIPTION);
+    }
+
+    pub fn test_advisory_ids_vulnerable_against() {
+        let advisories = AdvisoryIDs(vec![
+            "INTEL-SA-00161".to_string(),
+            "INTEL-SA-00999".to_string(),
+        ]);
+
+        // vulnerable to both, with the description of the one the enclave knows
+        assert_eq!(
+            advisories.vulnerable_against(&[]),
+            vec![
+                "INTEL-SA-00161".to_string(),
+                "You must disable hyperthreading in the BIOS".to_string(),
+                "INTEL-SA-00999".to_string(),
+                NO_ADVISORY_DESCRIPTION.to_string()
+            ]
+        );
+
+        // an operator on newer microcode accepts INTEL-SA-00161
+        assert_eq!(
+            advisories.vulnerable_against(&["INTEL-SA-00161"]),
+            vec![
+                "INTEL-SA-00999".to_string(),
+                NO_ADVISORY_DESCRIPTION.to_string()
+            ]
+        );
+        assert!(advisories
+            .vulnerable_against(&["INTEL-SA-00161", "INTEL-SA-00999"])
+            .is_empty());
+
+        assert_eq!(
+            advisories.vulnerable_against(WHITELISTED_ADVISORIES),
+            advisories.vulnerable_with(&BuiltinAdvisoryCatalog)
+        );
+    }
+
+    pub fn test_advisory_breakdown() {
+        let mut report = mock_attestation_report();
+        report.advisory_ids = AdvisoryIDs(vec![
+            "INTEL-SA-00334".to_string(),
+            "INTEL-SA-00161".to_string(),
+            "INTEL-SA-334".to_string(),
+            "INTEL-SA-00219".to_string(),
+            "INTEL-SA-00161".to_string(),
+        ]);
+
+        let breakdown = report.advisory_breakdown(&["INTEL-SA-00334", "INTEL-SA-00219"]);
+        assert_eq!(
+            breakdown,
+            AdvisoryBreakdown {
+                whitelisted_hits: vec!["INTEL-SA-00334".to_string(), "INTEL-SA-00219".to_string()],
+                non_whitelisted: vec!["INTEL-SA-00161".to_string()],
+                unknown: vec!["INTEL-SA-334".to_string()],
+            }
+        );
+
+        let breakdown = report.advisory_breakdown(&[]);
+        assert!(breakdown.whitelisted_hits.is_empty());
+        assert_eq!(breakdown.non_whitelisted.len(), 3);
+
+        report.advisory_ids = AdvisoryIDs(vec![]);
+        assert_eq!(
+            report.advisory_breakdown(&["INTEL-SA-00334"]),
+            AdvisoryBreakdown::default()
+        );
+    }
+
+    pub fn test_tcb_eval_data_number_as_string() {
+        let mut attn_report = attesation_report_v5();
+        let report = AttestationReport::from_report_json(&attn_report).unwrap();
+        assert_eq!(report.tcb_eval_data_number, Some(16));
+
+        attn_report["tcbEvaluationDataNumber"] = json!("17");
+        let report = AttestationReport::from_report_json(&attn_report).unwrap();
+        assert_eq!(report.tcb_eval_data_number, Some(17));
+
+        // the body parsed in place accepts both forms too
+        let body_bytes = serde_json::to_vec(&attn_report).unwrap();
+        let body: IasReportBody = serde_json::from_slice(&body_bytes).unwrap();
+        assert_eq!(body.tcb_evaluation_data_number, Some(17));
+        attn_report["tcbEvaluationDataNumber"] = json!(18);
+        let body_bytes = serde_json::to_vec(&attn_report).unwrap();
+        let body: IasReportBody = serde_json::from_slice(&body_bytes).unwrap();
+        assert_eq!(body.tcb_evaluation_data_number, Some(18));
+
+        for invalid in [
+            json!("sixteen"),
+            json!(""),
+            json!(-1),
+            json!(70000),
+            json!(null),
+        ]
+        .iter()
+        {
+            attn_report["tcbEvaluationDataNumber"] = invalid.clone();
+            assert!(AttestationReport::from_report_json(&attn_report).is_err());
+        }
+    }
+
+    pub fn test_attestation_report_advisory_url() {
+        let mut attn_report = attesation_report_v5();
+        attn_report["advisoryIDs"] = json!(["INTEL-SA-00334"]);
+        attn_report["advisoryURL"] = json!("https://security-center.intel.com");
+
+        let report = AttestationReport::from_report_json(&attn_report).unwrap();
+        assert_eq!(
+            report.advisory_url,
+            Some("https://security-center.intel.com".to_string())
+        );
+    }
+
+    pub fn test_attestation_report_no_advisory_url() {
+        let report = AttestationReport::from_report_json(&attesation_report_v5()).unwrap();
+        assert_eq!(report.advisory_url, None);
+    }
+
+    pub fn test_from_report_bytes_allocates_less() {
+        let mut attn_report = attesation_report_v5();
+        attn_report["advisoryIDs"] = json!(["INTEL-SA-00161", "INTEL-SA-00219"]);
+        attn_report["advisoryURL"] = json!("https://security-center.intel.com");
+        let report_bytes = serde_json::to_vec(&attn_report).unwrap();
+
+        // the strings of the body are borrowed from the report rather than copied
+        let body: IasReportBody = serde_json::from_slice(&report_bytes).unwrap();
+        assert!(matches!(body.timestamp.0, Cow::Borrowed(_)));
+        assert!(matches!(body.isv_enclave_quote_status.0, Cow::Borrowed(_)));
+        assert!(matches!(
+            body.isv_enclave_quote_body,
+            Some(BodyStr(Cow::Borrowed(_)))
+        ));
+
+        let original = AttestationReport::from_report_json(&attn_report).unwrap();
+        let mut buffers = VerificationBuffers::default();
+        let optimized = AttestationReport::from_report_bytes(&report_bytes, &mut buffers).unwrap();
+        assert_eq!(format!("{:?}", optimized), format!("{:?}", original));
+
+        // a chunked quote body is reassembled in the buffer, which later reports reuse
+        let body = attn_report["isvEnclaveQuoteBody"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        attn_report
+            .as_object_mut()
+            .unwrap()
+            .remove("isvEnclaveQuoteBody");
+        attn_report["isvEnclaveQuoteBody1"] = json!(body[..300]);
+        attn_report["isvEnclaveQuoteBody2"] = json!(body[300..]);
+        let chunked_bytes = serde_json::to_vec(&attn_report).unwrap();
+
+        AttestationReport::from_report_bytes(&chunked_bytes, &mut buffers).unwrap();
+        let scratch = buffers.quote_body.as_ptr();
+        assert!(buffers.quote_body.capacity() >= body.len());
+
+        let chunked = AttestationReport::from_report_bytes(&chunked_bytes, &mut buffers).unwrap();
+        assert_eq!(buffers.quote_body.as_ptr(), scratch);
+        assert_eq!(chunked.raw_quote, original.raw_quote);
+    }
+
+    pub fn test_from_report_bytes_escaped_strings() {
+        let mut attn_report = attesation_report_v5();
+        attn_report["advisoryURL"] = json!("https://security-center.intel.com");
+        let original = AttestationReport::from_report_json(&attn_report).unwrap();
+
+        // JSON allows escaping `/`, which the base64 of the quote body is full of
+        let report_bytes = String::from_utf8(serde_json::to_vec(&attn_report).unwrap())
+            .unwrap()
+            .replace('/', "\\/");
+        assert!(report_bytes.contains("\\/"));
+
+        let mut buffers = VerificationBuffers::default();
+        let escaped =
+            AttestationReport::from_report_bytes(report_bytes.as_bytes(), &mut buffers).unwrap();
+        assert_eq!(format!("{:?}", escaped), format!("{:?}", original));
+
+        // as are chunked quote bodies
+        let body = attn_report["isvEnclaveQuoteBody"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        attn_report
+            .as_object_mut()
+            .unwrap()
+            .remove("isvEnclaveQuoteBody");
+        attn_report["isvEnclaveQuoteBody1"] = json!(body[..300]);
+        attn_report["isvEnclaveQuoteBody2"] = json!(body[300..]);
+        let chunked = AttestationReport::from_report_bytes(
+            &serde_json::to_vec(&attn_report).unwrap(),
+            &mut buffers,
+        )
+        .unwrap();
+        assert_eq!(chunked.sgx_quote_body, original.sgx_quote_body);
+        assert_eq!(chunked.raw_quote, original.raw_quote);
+    }
+
+    pub fn test_report_data_halves() {
+        let report = mock_attestation_report();
+        let enclave_report = &report.sgx_quote_body.isv_enclave_report;
+
+        // the key the node of the static report registered with
+        assert_eq!(
+            hex::encode(enclave_report.report_data_pubkey()),
+            "d8218f64d0c56609d20352e14b7ffd9b1881c07cf8cef67bc2edf833ad079d78"
+        );
+        assert_eq!(
+            hex::encode(enclave_report.report_data_tail()),
+            "0dd65b8745788ea87be4422dd5775d80f59fa9446632133b33cf2d60f1ba51b3"
+        );
+        assert_eq!(
+            [
+                &enclave_report.report_data_pubkey()[..],
+                &enclave_report.report_data_tail()[..]
+            ]
+            .concat(),
+            enclave_report.report_data.to_vec()
+        );
+    }
+
+    pub fn test_expected_report_data_binding() {
+        let pubkey = [3u8; 32];
+        let secondary = [9u8; 32];
+
+        let mut enclave_report = mock_attestation_report().sgx_quote_body.isv_enclave_report;
+        enclave_report.report_data = expected_report_data(&pubkey, None);
+        assert!(enclave_report
+            .verify_report_data_binding(&pubkey, None)
+            .is_ok());
+        assert!(enclave_report
+            .verify_report_data_binding(&pubkey, Some(&secondary))
+            .is_err());
+
+        enclave_report.report_data = expected_report_data(&pubkey, Some(&secondary));
+        assert_eq!(enclave_report.report_data_pubkey(), &pubkey);
+        assert_eq!(enclave_report.report_data_tail(), &secondary);
+        assert!(enclave_report
+            .verify_report_data_binding(&pubkey, Some(&secondary))
+            .is_ok());
+        assert!(enclave_report
+            .verify_report_data_binding(&[4u8; 32], Some(&secondary))
+            .is_err());
+
+        // a challenge is bound as a zero padded secondary value
+        let challenge = challenge_report_data(&[1, 2, 3, 4]).unwrap();
+        enclave_report.report_data = expected_report_data(&pubkey, Some(&challenge));
+        assert_eq!(enclave_report.report_data[32..36], [1, 2, 3, 4]);
+        assert_eq!(enclave_report.report_data[36..], [0u8; 28]);
+        assert!(enclave_report
+            .verify_report_data_binding(&pubkey, Some(&challenge))
+            .is_ok());
+        assert_eq!(challenge_report_data(&[7u8; 32]), Some([7u8; 32]));
+        assert_eq!(challenge_report_data(&[7u8; 33]), None);
+    }
+
+    pub fn test_report_data_merkle_root() {
+        let mut enclave_report = mock_attestation_report().sgx_quote_body.isv_enclave_report;
+        let root = enclave_crypto::sha_256(&[enclave_crypto::sha_256(b"key"), [7u8; 32]].concat());
+        enclave_report.report_data = expected_report_data(&root, Some(&[9u8; 32]));
+
+        assert!(enclave_report.verify_report_data_merkle_root(&root).is_ok());
+
+        let mut other_root = root;
+        other_root[31] ^= 0x01;
+        assert!(enclave_report
+            .verify_report_data_merkle_root(&other_root)
+            .is_err());
+        // the second half of report_data isn't the root
+        assert!(enclave_report
+            .verify_report_data_merkle_root(&[9u8; 32])
+            .is_err());
+    }
+
+    pub fn test_report_data_cert_binding() {
+        let tls_cert = tls_ra_cert_der_v4();
+        let mut enclave_report = mock_attestation_report().sgx_quote_body.isv_enclave_report;
+        enclave_report.report_data = expected_report_data(&sha_256(&tls_cert), None);
+
+        assert!(enclave_report
+            .verify_report_data_cert_binding(&tls_cert)
+            .is_ok());
+
+        let mut other_cert = tls_cert.clone();
+        other_cert.push(0);
+        assert!(matches!(
+            enclave_report.verify_report_data_cert_binding(&other_cert),
+            Err(Error::ReportValidationError)
+        ));
+        assert!(enclave_report
+            .verify_report_data_cert_binding(&tls_ra_cert_der_v3())
+            .is_err());
+    }
+
+    pub fn test_report_data_expected_length() {
+        let mut enclave_report = mock_attestation_report().sgx_quote_body.isv_enclave_report;
+        enclave_report.report_data = expected_report_data(&[3u8; 32], None);
+
+        assert!(enclave_report
+            .verify_report_data_binding(&[3u8; 31], None)
+            .is_err());
+        assert!(enclave_report
+            .verify_report_data_binding(&[3u8; 32], None)
+            .is_ok());
+        assert!(enclave_report
+            .verify_report_data_binding(&[3u8; 32], Some(&[0u8; 31]))
+            .is_err());
+        assert!(enclave_report
+            .verify_report_data_binding(&[3u8; 33], None)
+            .is_err());
+
+        let report_data = enclave_report.report_data.to_vec();
+        assert!(enclave_report.verify_report_data(&report_data).is_ok());
+        assert!(enclave_report
+            .verify_report_data(&report_data[..32])
+            .is_err());
+        let mut too_long = report_data.clone();
+        too_long.push(0);
+        assert_eq!(too_long.len(), 65);
+        assert!(enclave_report.verify_report_data(&too_long).is_err());
+    }
+
+    pub fn test_enclave_report_attributes() {
+        let enclave_report = mock_attestation_report().sgx_quote_body.isv_enclave_report;
+        assert_eq!(
+            enclave_report.attributes_hex(),
+            "07000000000000000700000000000000"
+        );
+
+        let mut allowed = <[u8; 16]>::try_from(
+            hex::decode("07000000000000000700000000000000")
+                .unwrap()
+                .as_slice(),
+        )
+        .unwrap();
+        assert!(enclave_report.verify_attributes(&[allowed]).is_ok());
+        assert!(enclave_report.verify_attributes(&[]).is_err());
+
+        // the fixture enclave runs in debug mode, so its production build has other attributes
+        allowed[0] &= !0x02;
+        assert!(enclave_report.verify_attributes(&[allowed]).is_err());
+    }
+
+    pub fn test_enclave_report_matches_golden() {
+        let golden = mock_attestation_report().sgx_quote_body.isv_enclave_report;
+        let mut enclave_report = mock_attestation_report().sgx_quote_body.isv_enclave_report;
+        assert!(enclave_report.matches_golden(&golden, &[]));
+
+        // the same image on a platform with newer microcode, with the node's own key
+        enclave_report.cpu_svn[0] += 1;
+        enclave_report.report_data = [7u8; 64];
+        assert!(!enclave_report.matches_golden(&golden, &[]));
+        assert!(!enclave_report.matches_golden(&golden, &[ReportField::ReportData]));
+        assert!(
+            enclave_report.matches_golden(&golden, &[ReportField::CpuSvn, ReportField::ReportData])
+        );
+
+        // a different image
+        enclave_report.mr_enclave[0] ^= 0xff;
+        assert!(!enclave_report
+            .matches_golden(&golden, &[ReportField::CpuSvn, ReportField::ReportData]));
+    }
+
     pub fn test_attestation_report_from_cert() {
         let tls_ra_cert = tls_ra_cert_der_v4();
         let report = AttestationReport::from_cert(&tls_ra_cert);
@@ -912,6 +3051,290 @@ pub mod tests {
         assert_eq!(report.sgx_quote_status, SgxQuoteStatus::GroupOutOfDate);
     }
 
+    pub fn test_attestation_report_from_cert_binding() {
+        let cert = tls_ra_cert_der_v4();
+        let cert_pubkey = get_cert_pubkey(&cert).unwrap();
+        assert_eq!(cert_pubkey.len(), 65);
+        assert_eq!(cert_pubkey[0], 0x04);
+        // computed independently, over the uncompressed point in the fixture's subjectPublicKey
+        let pubkey_hash = "56e8909c6031a25e5bb1b62a5d6a842de31a38d693f9873f48d134e519bb06f7";
+        assert_eq!(hex::encode(sha_256(&cert_pubkey)), pubkey_hash);
+
+        // an enclave that binds its cert's key by hash puts it in the first half of report_data
+        let mut bound = mock_attestation_report().sgx_quote_body.isv_enclave_report;
+        bound.report_data[..32].copy_from_slice(&hex::decode(pubkey_hash).unwrap());
+        assert!(bound.verify_pubkey_hash_binding(&cert_pubkey).is_ok());
+
+        let mut other_key = cert_pubkey.clone();
+        other_key[64] ^= 1;
+        assert!(matches!(
+            bound.verify_pubkey_hash_binding(&other_key),
+            Err(Error::ReportValidationError)
+        ));
+
+        // the fixture is a Secret Network cert, whose report_data holds the node's key rather
+        // than the hash of the cert's key
+        let report = AttestationReport::from_cert(&cert).unwrap();
+        assert_ne!(
+            hex::encode(
+                report
+                    .sgx_quote_body
+                    .isv_enclave_report
+                    .report_data_pubkey()
+            ),
+            pubkey_hash
+        );
+        assert!(matches!(
+            AttestationReport::from_cert_binding(&cert),
+            Err(Error::ReportValidationError)
+        ));
+    }
+
+    pub fn test_attestation_report_raw_quote() {
+        let report = AttestationReport::from_cert(&tls_ra_cert_der_v4()).unwrap();
+        assert_eq!(report.raw_quote.len(), QUOTE_SIZE);
+
+        let reparsed = SgxQuote::parse_from(&report.raw_quote).unwrap();
+        assert!(reparsed == report.sgx_quote_body);
+        assert_eq!(reparsed.to_bytes(), report.raw_quote);
+
+        // only the size of the quote is logged
+        let debug = format!("{:?}", report);
+        assert!(debug.contains(&format!("raw_quote: <{} bytes>", QUOTE_SIZE)));
+    }
+
+    pub fn test_measurements_equal() {
+        let measurement = [0x5au8; 32];
+        let copy = measurement;
+        assert!(measurements_equal(&measurement, &copy));
+        assert!(measurements_equal(&[0u8; 32], &[0u8; 32]));
+
+        for i in [0, 15, 31].iter() {
+            let mut other = measurement;
+            other[*i] ^= 1;
+            assert!(!measurements_equal(&measurement, &other));
+        }
+    }
+
+    pub fn test_attestation_report_verify() {
+        let report = AttestationReport::from_cert(&tls_ra_cert_der_v4()).unwrap();
+        let enclave_report = &report.sgx_quote_body.isv_enclave_report;
+        let mr_enclave = enclave_report.mr_enclave;
+        let mr_signer = enclave_report.mr_signer;
+        let isv_svn = enclave_report.isv_svn;
+
+        let other = [0xab; 32];
+        assert!(report
+            .verify(&[other, mr_enclave], &mr_signer, isv_svn)
+            .is_ok());
+        assert!(report.verify(&[mr_enclave], &mr_signer, 0).is_ok());
+
+        assert!(matches!(
+            report.verify(&[other], &mr_signer, isv_svn),
+            Err(Error::MrEnclaveNotAllowed(got)) if got == mr_enclave
+        ));
+        assert!(matches!(
+            report.verify(&[], &mr_signer, 0),
+            Err(Error::MrEnclaveNotAllowed(_))
+        ));
+        assert!(matches!(
+            report.verify(&[mr_enclave], &other, isv_svn),
+            Err(Error::MrSignerMismatch(got)) if got == mr_signer
+        ));
+        assert!(matches!(
+            report.verify(&[mr_enclave], &mr_signer, isv_svn + 1),
+            Err(Error::IsvSvnTooLow { min, got }) if min == isv_svn + 1 && got == isv_svn
+        ));
+    }
+
+    pub fn test_attestation_report_verify_signer_identity() {
+        let report = AttestationReport::from_cert(&tls_ra_cert_der_v4()).unwrap();
+        let enclave_report = &report.sgx_quote_body.isv_enclave_report;
+        let mr_signer = enclave_report.mr_signer;
+        let isv_prod_id = enclave_report.isv_prod_id;
+        let isv_svn = enclave_report.isv_svn;
+
+        let other = [0xab; 32];
+        assert!(report
+            .verify_signer_identity(&[(other, isv_prod_id, 0), (mr_signer, isv_prod_id, isv_svn)])
+            .is_ok());
+
+        assert!(matches!(
+            report.verify_signer_identity(&[(other, isv_prod_id, 0)]),
+            Err(Error::MrSignerMismatch(got)) if got == mr_signer
+        ));
+        assert!(matches!(
+            report.verify_signer_identity(&[(mr_signer, isv_prod_id + 1, 0)]),
+            Err(Error::IsvProdIdNotAllowed(got)) if got == isv_prod_id
+        ));
+        assert!(matches!(
+            report.verify_signer_identity(&[(mr_signer, isv_prod_id, isv_svn + 1)]),
+            Err(Error::IsvSvnTooLow { min, got }) if min == isv_svn + 1 && got == isv_svn
+        ));
+        // the lowest minimum of the product is the one that applies
+        assert!(report
+            .verify_signer_identity(&[
+                (mr_signer, isv_prod_id, isv_svn + 1),
+                (mr_signer, isv_prod_id, isv_svn)
+            ])
+            .is_ok());
+    }
+
+    pub fn test_attestation_report_from_cert_with_json() {
+        let (report, attn_report) =
+            AttestationReport::from_cert_with_json(&tls_ra_cert_der_v4()).unwrap();
+        assert_eq!(report.sgx_quote_status, SgxQuoteStatus::GroupOutOfDate);
+
+        assert_eq!(
+            attn_report["id"].as_str(),
+            Some("94069855828834101660661931058257587238")
+        );
+        assert_eq!(report.id.as_deref(), attn_report["id"].as_str());
+        assert_eq!(
+            attn_report["isvEnclaveQuoteStatus"].as_str(),
+            Some("GROUP_OUT_OF_DATE")
+        );
+
+        assert!(AttestationReport::from_cert_with_json(&tls_ra_cert_der_v3()).is_err());
+    }
+
+    fn endorsed_report(cert: &[u8]) -> EndorsedAttestationReport {
+        let payload = get_netscape_comment(cert).ok().unwrap();
+        serde_json::from_slice(&payload).unwrap()
+    }
+
+    /// The signed report in `tls_ra_cert_der_v4`
+    pub fn endorsed_report_v4() -> EndorsedAttestationReport {
+        endorsed_report(&tls_ra_cert_der_v4())
+    }
+
+    pub fn test_endorsed_report_from_json_bytes() {
+        let payload = get_netscape_comment(&tls_ra_cert_der_v4()).ok().unwrap();
+        let endorsed = EndorsedAttestationReport::from_json_bytes(&payload).unwrap();
+        assert!(AttestationReport::from_endorsed(&endorsed).is_ok());
+
+        let json: Value = serde_json::from_slice(&payload).unwrap();
+        for field in ["report", "signature", "signing_cert"].iter() {
+            let mut empty = json.clone();
+            empty[*field] = json!("");
+            assert!(matches!(
+                EndorsedAttestationReport::from_json_bytes(&serde_json::to_vec(&empty).unwrap()),
+                Err(Error::ReportParseError)
+            ));
+
+            let mut missing = json.clone();
+            missing.as_object_mut().unwrap().remove(*field);
+            assert!(matches!(
+                EndorsedAttestationReport::from_json_bytes(&serde_json::to_vec(&missing).unwrap()),
+                Err(Error::ReportParseError)
+            ));
+        }
+
+        assert!(EndorsedAttestationReport::from_json_bytes(b"not json").is_err());
+    }
+
+    pub fn test_attestation_report_separate_intermediates() {
+        let endorsed = endorsed_report(&tls_ra_cert_der_v4());
+        let (ias_cert, _) = get_ias_auth_config();
+
+        let report = AttestationReport::from_signed_report(
+            &endorsed.report,
+            &endorsed.signature,
+            &endorsed.signing_cert,
+            &[ias_cert.as_slice()],
+        );
+        assert!(report.is_ok());
+        assert_eq!(
+            report.unwrap().sgx_quote_status,
+            SgxQuoteStatus::GroupOutOfDate
+        );
+
+        // the root CA can't stand in for the signing cert
+        let report = AttestationReport::from_signed_report(
+            &endorsed.report,
+            &endorsed.signature,
+            &_ias_root_ca_cert_der(),
+            &[ias_cert.as_slice()],
+        );
+        assert!(report.is_err());
+    }
+
+    fn to_pem(der: &[u8]) -> Vec<u8> {
+        let encoded = base64::encode(der);
+        let lines: Vec<&str> = encoded
+            .as_bytes()
+            .chunks(64)
+            .map(|line| std::str::from_utf8(line).unwrap())
+            .collect();
+
+        format!(
+            "{}\n{}\n{}\n",
+            PEM_CERT_HEADER,
+            lines.join("\n"),
+            PEM_CERT_FOOTER
+        )
+        .into_bytes()
+    }
+
+    pub fn test_attestation_report_from_pem_cert() {
+        let der = tls_ra_cert_der_v4();
+        let pem = to_pem(&der);
+        assert_eq!(cert_to_der(&pem).unwrap().as_ref(), der.as_slice());
+        assert!(matches!(cert_to_der(&der).unwrap(), Cow::Borrowed(_)));
+
+        assert_eq!(
+            format!("{:?}", AttestationReport::from_cert(&pem)),
+            format!("{:?}", AttestationReport::from_cert(&der))
+        );
+
+        // a PEM signing cert inside a DER wrapping cert
+        let endorsed = endorsed_report(&der);
+        let (ias_cert, _) = get_ias_auth_config();
+        let from_pem_signing_cert = AttestationReport::from_signed_report(
+            &endorsed.report,
+            &endorsed.signature,
+            &to_pem(&endorsed.signing_cert),
+            &[ias_cert.as_slice()],
+        );
+        let from_der_signing_cert = AttestationReport::from_signed_report(
+            &endorsed.report,
+            &endorsed.signature,
+            &endorsed.signing_cert,
+            &[ias_cert.as_slice()],
+        );
+        assert_eq!(
+            format!("{:?}", from_pem_signing_cert),
+            format!("{:?}", from_der_signing_cert)
+        );
+
+        let truncated = &pem[..pem.len() - 10];
+        assert!(cert_to_der(truncated).is_err());
+    }
+
+    pub fn test_attestation_report_from_cert_at() {
+        let tls_ra_cert = tls_ra_cert_der_v4();
+
+        assert_eq!(
+            format!("{:?}", AttestationReport::from_cert_at(&tls_ra_cert, None)),
+            format!("{:?}", AttestationReport::from_cert(&tls_ra_cert))
+        );
+
+        // 2021-04-01, while the signing cert was valid
+        let report = AttestationReport::from_cert_at(&tls_ra_cert, Some(1617235200));
+        assert_eq!(
+            report.unwrap().sgx_quote_status,
+            SgxQuoteStatus::GroupOutOfDate
+        );
+
+        // the signing cert expires Nov 20 09:36:58 2026 GMT
+        assert!(matches!(
+            AttestationReport::from_cert_at(&tls_ra_cert, Some(1795167419)),
+            Err(Error::CertChainInvalid)
+        ));
+        // and wasn't issued yet in 2016
+        assert!(AttestationReport::from_cert_at(&tls_ra_cert, Some(1451606400)).is_err());
+    }
+
     pub fn test_attestation_report_from_cert_invalid() {
         let tls_ra_cert = tls_ra_cert_der_v4();
         let report = AttestationReport::from_cert(&tls_ra_cert);
@@ -927,6 +3350,53 @@ pub mod tests {
         assert!(report.is_err());
     }
 
+    pub fn test_attestation_report_api_versions() {
+        // the fixture cert is of an API version 4 report, which has no TCB evaluation data number
+        let report = AttestationReport::from_cert(&tls_ra_cert_der_v4()).unwrap();
+        assert_eq!(report.tcb_eval_data_number, None);
+        let mut buffers = VerificationBuffers::default();
+        let report =
+            AttestationReport::from_cert_with_buffers(&tls_ra_cert_der_v4(), &mut buffers).unwrap();
+        assert_eq!(report.tcb_eval_data_number, None);
+
+        let v5 = attesation_report_v5();
+        let report = AttestationReport::from_report_json(&v5).unwrap();
+        assert_eq!(report.tcb_eval_data_number, Some(16));
+        let report =
+            AttestationReport::from_report_bytes(&serde_json::to_vec(&v5).unwrap(), &mut buffers)
+                .unwrap();
+        assert_eq!(report.tcb_eval_data_number, Some(16));
+
+        // version 5 reports must have one
+        let mut without_number = v5.clone();
+        without_number
+            .as_object_mut()
+            .unwrap()
+            .remove("tcbEvaluationDataNumber");
+        assert!(AttestationReport::from_report_json(&without_number).is_err());
+        assert!(AttestationReport::from_report_bytes(
+            &serde_json::to_vec(&without_number).unwrap(),
+            &mut buffers
+        )
+        .is_err());
+
+        let mut v4 = without_number;
+        v4["version"] = json!(4);
+        let report = AttestationReport::from_report_json(&v4).unwrap();
+        assert_eq!(report.tcb_eval_data_number, None);
+        let report =
+            AttestationReport::from_report_bytes(&serde_json::to_vec(&v4).unwrap(), &mut buffers)
+                .unwrap();
+        assert_eq!(report.tcb_eval_data_number, None);
+
+        let mut v6 = v5;
+        v6["version"] = json!(6);
+        assert!(matches!(
+            AttestationReport::from_report_json(&v6),
+            Err(Error::UnsupportedApiVersion(6))
+        ));
+    }
+
     pub fn test_attestation_report_test() {
         let tls_ra_cert = tls_ra_cert_der_test();
         let report = AttestationReport::from_cert(&tls_ra_cert);
@@ -939,43 +3409,154 @@ pub mod tests {
     }
 
     fn load_attestation_dcap() -> (Vec<u8>, Vec<u8>, i64) {
-        let mut vec_quote = vec![];
-        {
-            let mut f =
-                File::open("../execute/src/registration/fixtures/attestation_dcap.quote").unwrap();
-            f.read_to_end(&mut vec_quote).unwrap();
-        }
+        let vec_quote = dcap_quote_fixture();
+        let vec_coll = dcap_collateral_fixture();
+        // 2021-04-15, when both the TCB info and the QE identity of the collateral were current
+        (vec_quote, vec_coll, 1618444800)
+    }
 
-        let mut vec_coll = vec![];
-        {
-            let mut f = File::open(
-                "../execute/src/registration/fixtures/attestation_dcap.quote.collateral",
-            )
-            .unwrap();
-            f.read_to_end(&mut vec_coll).unwrap();
-        }
-        (vec_quote, vec_coll, 1709649832)
+    pub fn test_attestation_report_serde_round_trip() {
+        let (vec_quote, _, _) = load_attestation_dcap();
+        let quote = &vec_quote[..QUOTE_SIZE];
+
+        let mut report = mock_attestation_report();
+        report.sgx_quote_body = SgxQuote::parse_from(quote).unwrap();
+        report.raw_quote = quote.to_vec();
+        report.platform_info_blob = Some(vec![0x15, 0x02, 0x00, 0x65]);
+        report.advisory_ids = AdvisoryIDs(vec!["INTEL-SA-00334".to_string()]);
+
+        let json = serde_json::to_string(&report).unwrap();
+        let restored: AttestationReport = serde_json::from_str(&json).unwrap();
+        assert!(restored.sgx_quote_body == report.sgx_quote_body);
+        assert_eq!(restored.sgx_quote_status, report.sgx_quote_status);
+        assert_eq!(restored.timestamp, report.timestamp);
+        assert_eq!(restored.platform_info_blob, report.platform_info_blob);
+        assert_eq!(restored.advisory_ids.0, report.advisory_ids.0);
+        assert_eq!(restored.epid_pseudonym, None);
+        assert_eq!(restored.raw_quote, report.raw_quote);
+        assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+
+        // the enums, measurements and vendor ID are in a stable, readable form
+        let value: Value = serde_json::from_str(&json).unwrap();
+        let quote_body = &value["sgx_quote_body"];
+        assert_eq!(value["sgx_quote_status"], "GROUP_OUT_OF_DATE");
+        assert_eq!(quote_body["version"], json!({ "V3": "P256_256" }));
+        assert_eq!(
+            quote_body["qe_vendor_id"],
+            report
+                .sgx_quote_body
+                .qe_vendor_id
+                .to_hyphenated()
+                .to_string()
+                .as_str()
+        );
+        assert_eq!(
+            quote_body["isv_enclave_report"]["mr_enclave"],
+            hex::encode(report.sgx_quote_body.isv_enclave_report.mr_enclave).as_str()
+        );
+
+        let mut short_measurement = value.clone();
+        short_measurement["sgx_quote_body"]["isv_enclave_report"]["mr_enclave"] = json!("abcd");
+        assert!(serde_json::from_value::<AttestationReport>(short_measurement).is_err());
     }
 
     pub fn test_attestation_dcap() {
         let (vec_quote, vec_coll, time_s) = load_attestation_dcap();
 
-        let res = verify_quote_ecdsa(&vec_quote, &vec_coll, time_s);
+        let res = verify_quote_ecdsa(&vec_quote, &vec_coll, Some(time_s));
         assert!(res.is_ok());
     }
 
+    pub fn test_attestation_dcap_collateral_expired() {
+        let (vec_quote, vec_coll, _) = load_attestation_dcap();
+
+        // 2024-03-05, when the fixture was recorded, and 2100-01-01
+        for time_s in [1709649832, 4102444800].iter() {
+            assert!(matches!(
+                verify_quote_ecdsa(&vec_quote, &vec_coll, Some(*time_s)),
+                Err(DcapError::CollateralExpired)
+            ));
+        }
+    }
+
+    pub fn test_attestation_dcap_key_type_mismatch() {
+        let (mut vec_quote, vec_coll, time_s) = load_attestation_dcap();
+
+        // declare a P-384 attestation key over P-256 signature data
+        vec_quote[2] = 3;
+
+        assert!(matches!(
+            verify_quote_ecdsa(&vec_quote, &vec_coll, Some(time_s)),
+            Err(DcapError::KeyTypeMismatch)
+        ));
+    }
+
     pub fn test_attestation_dcap_temper() {
-        let (mut vec_quote, mut vec_coll, time_s) = load_attestation_dcap();
-
-        // tamper with quote
-        let mut my_p_quote = vec_quote.as_mut_ptr() as *mut sgx_quote_t;
-        unsafe {
-            let mut p_report = (*my_p_quote).report_body;
-            let mut p_data = p_report.report_data;
-            p_data.d[6] = p_data.d[6] ^ 4;
-        };
+        let (mut vec_quote, vec_coll, time_s) = load_attestation_dcap();
+
+        // the untampered quote verifies at this time, so only the tampering can fail it
+        assert!(verify_quote_ecdsa(&vec_quote, &vec_coll, Some(time_s)).is_ok());
+
+        // tamper with the report data, at offset 320 of the enclave report, which is at offset 48
+        // of the quote
+        vec_quote[48 + 320 + 6] ^= 4;
 
-        let res = verify_quote_ecdsa(&vec_quote, &vec_coll, time_s);
-        assert!(!res.is_ok());
+        let res = verify_quote_ecdsa(&vec_quote, &vec_coll, Some(time_s));
+        assert!(matches!(res, Err(DcapError::IsvSignatureInvalid)));
+    }
+
+    pub fn test_attestation_dcap_temper_mr_enclave() {
+        let (mut vec_quote, vec_coll, time_s) = load_attestation_dcap();
+
+        assert!(verify_quote_ecdsa(&vec_quote, &vec_coll, Some(time_s)).is_ok());
+
+        // MRENCLAVE is at offset 64 of the enclave report
+        vec_quote[48 + 64] ^= 4;
+
+        let res = verify_quote_ecdsa(&vec_quote, &vec_coll, Some(time_s));
+        assert!(matches!(res, Err(DcapError::IsvSignatureInvalid)));
+    }
+
+    pub fn test_attestation_report_quote_version() {
+        let report = AttestationReport::from_report_json(&attesation_report_v5()).unwrap();
+        assert_eq!(
+            report.quote_version(),
+            &SgxQuoteVersion::V2(SgxEpidQuoteSigType::Linkable)
+        );
+        assert!(!report.is_ecdsa());
+
+        let quote = read_fixture("quote_v4_sgx.bin");
+
+        let mut report = mock_attestation_report();
+        report.sgx_quote_body = SgxQuote::parse_from(&quote).unwrap();
+        assert_eq!(
+            report.quote_version(),
+            &SgxQuoteVersion::V4(SgxEcdsaQuoteAkType::P256_256, TeeType::Sgx)
+        );
+        assert!(report.is_ecdsa());
+    }
+
+    pub fn test_sgx_quote_v4_parse_from() {
+        let quote = read_fixture("quote_v4_sgx.bin");
+
+        let parsed = SgxQuote::parse_from(&quote).unwrap();
+        assert_eq!(
+            parsed.version,
+            SgxQuoteVersion::V4(SgxEcdsaQuoteAkType::P256_256, TeeType::Sgx)
+        );
+        assert_eq!(parsed.gid, TEE_TYPE_SGX);
+        assert_eq!(parsed.to_bytes().to_vec(), quote);
+
+        // a TDX quote carries a TD report, which mustn't be read as an enclave report
+        let mut tdx = quote.clone();
+        tdx[4..8].copy_from_slice(&TEE_TYPE_TDX.to_le_bytes());
+        assert!(matches!(
+            SgxQuote::parse_from(&tdx),
+            Err(Error::UnsupportedTeeType(TeeType::Tdx))
+        ));
+
+        let mut unknown = quote;
+        unknown[4] = 0x42;
+        assert!(SgxQuote::parse_from(&unknown).is_err());
     }
 }
@@ -0,0 +1,338 @@
+//! Strength of the key that signs attestation reports.
+//!
+//! The report signature is only as trustworthy as the key of the signing cert. The cert chains to
+//! the pinned IAS root, but we still check its key against a minimum, so that a downgraded
+//! signing key is rejected even if it was issued along the chain. Likewise, each cert of the
+//! chain must be signed with one of the algorithms we accept.
+
+use log::*;
+
+use enclave_ffi_types::NodeAuthResult;
+
+use super::ca_bundle::split_der_element;
+use super::policy::AttestationPolicy;
+use super::report::{AttestationReport, EndorsedAttestationReport, Error};
+
+/// DER encoding of the rsaEncryption OID
+const RSA_ENCRYPTION_OID: &[u8] = &[
+    0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01,
+];
+/// DER encoding of the id-ecPublicKey OID
+const EC_PUBLIC_KEY_OID: &[u8] = &[0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+/// DER encoding of the prime256v1 (P-256) curve OID
+const P256_OID: &[u8] = &[0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+/// DER encoding of the secp384r1 (P-384) curve OID
+const P384_OID: &[u8] = &[0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x22];
+
+/// DER encodings of the certificate signature algorithm OIDs that are accepted along the chain,
+/// with their names for logging
+const ACCEPTED_CERT_SIGNATURE_ALGORITHMS: &[(&str, &[u8])] = &[
+    (
+        "sha256WithRSAEncryption",
+        &[
+            0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b,
+        ],
+    ),
+    (
+        "sha384WithRSAEncryption",
+        &[
+            0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0c,
+        ],
+    ),
+    (
+        "sha512WithRSAEncryption",
+        &[
+            0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0d,
+        ],
+    ),
+    (
+        "RSASSA-PSS",
+        &[
+            0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0a,
+        ],
+    ),
+    (
+        "ecdsa-with-SHA256",
+        &[0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02],
+    ),
+    (
+        "ecdsa-with-SHA384",
+        &[0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x03],
+    ),
+];
+
+/// The name of the algorithm `cert_der` is signed with, if it's one we accept
+fn cert_signature_algorithm(cert_der: &[u8]) -> Result<Option<&'static str>, Error> {
+    let (_, cert, _) = split_der_element(cert_der)?;
+    let (_, _, rest) = split_der_element(cert)?;
+    let (_, signature_algorithm, _) = split_der_element(rest)?;
+    let (oid, _, _) = split_der_element(signature_algorithm)?;
+
+    Ok(ACCEPTED_CERT_SIGNATURE_ALGORITHMS
+        .iter()
+        .find(|(_, accepted)| *accepted == oid)
+        .map(|(name, _)| *name))
+}
+
+/// Checks that the signing cert and each of the intermediates are signed with an accepted
+/// algorithm, logging the algorithm of each level. A chain that mixes in any other algorithm may
+/// have been put together by someone other than Intel
+pub fn verify_chain_signature_algorithms(
+    signing_cert: &[u8],
+    intermediates: &[&[u8]],
+) -> Result<(), Error> {
+    for (level, cert) in std::iter::once(signing_cert)
+        .chain(intermediates.iter().copied())
+        .enumerate()
+    {
+        match cert_signature_algorithm(cert)? {
+            Some(algorithm) => trace!(
+                "Certificate {} of the chain is signed with {}",
+                level,
+                algorithm
+            ),
+            None => {
+                error!(
+                    "Certificate {} of the chain is signed with an unaccepted algorithm",
+                    level
+                );
+                return Err(Error::CertChainInvalid);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The public key of a certificate, as far as its strength is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigningKey {
+    /// An RSA key with a modulus of the given size in bits
+    Rsa { bits: usize },
+    /// An ECDSA key on an acceptable curve, with a field of the given size in bits
+    Ecdsa { bits: usize },
+    /// A key of another algorithm, or on another curve
+    Unsupported,
+}
+
+/// Number of significant bits of a DER INTEGER that's known to be positive
+fn integer_bits(integer: &[u8]) -> usize {
+    let integer = match integer.iter().position(|b| *b != 0) {
+        Some(start) => &integer[start..],
+        None => return 0,
+    };
+
+    integer.len() * 8 - integer[0].leading_zeros() as usize
+}
+
+impl SigningKey {
+    /// The public key of a DER encoded X.509 certificate
+    pub fn of_cert(cert_der: &[u8]) -> Result<Self, Error> {
+        let (_, cert, _) = split_der_element(cert_der)?;
+        let (_, tbs_certificate, _) = split_der_element(cert)?;
+
+        // skip the optional version, then the serial number, signature algorithm, issuer,
+        // validity and subject
+        let mut rest = tbs_certificate;
+        if rest.first() == Some(&0xa0) {
+            rest = split_der_element(rest)?.2;
+        }
+        for _ in 0..5 {
+            rest = split_der_element(rest)?.2;
+        }
+
+        let (_, subject_public_key_info, _) = split_der_element(rest)?;
+        let (_, algorithm, rest) = split_der_element(subject_public_key_info)?;
+        let (algorithm_oid, _, parameters) = split_der_element(algorithm)?;
+        let (_, public_key, _) = split_der_element(rest)?;
+
+        if algorithm_oid == RSA_ENCRYPTION_OID {
+            // the first byte of the BIT STRING is the number of unused bits
+            let public_key = public_key.get(1..).ok_or(Error::ReportParseError)?;
+            let (_, rsa_public_key, _) = split_der_element(public_key)?;
+            let (_, modulus, _) = split_der_element(rsa_public_key)?;
+
+            return Ok(SigningKey::Rsa {
+                bits: integer_bits(modulus),
+            });
+        }
+
+        if algorithm_oid == EC_PUBLIC_KEY_OID {
+            let (curve, _, _) = split_der_element(parameters)?;
+            if curve == P256_OID {
+                return Ok(SigningKey::Ecdsa { bits: 256 });
+            }
+            if curve == P384_OID {
+                return Ok(SigningKey::Ecdsa { bits: 384 });
+            }
+        }
+
+        Ok(SigningKey::Unsupported)
+    }
+}
+
+/// Checks that the key of the report signing cert is at least as strong as the policy requires
+pub fn verify_signing_key(
+    signing_cert: &[u8],
+    policy: &AttestationPolicy,
+) -> Result<(), NodeAuthResult> {
+    let key = SigningKey::of_cert(signing_cert).map_err(|_| {
+        warn!("Failed to read the public key of the report signing cert");
+        NodeAuthResult::InvalidCert
+    })?;
+
+    let accepted = match key {
+        SigningKey::Rsa { bits } => bits >= policy.min_signing_key_rsa_bits,
+        SigningKey::Ecdsa { bits } => bits >= policy.min_signing_key_ecdsa_bits,
+        SigningKey::Unsupported => false,
+    };
+    if !accepted {
+        error!("Report signing cert key {:?} is too weak", key);
+        return Err(NodeAuthResult::InvalidCert);
+    }
+
+    Ok(())
+}
+
+impl AttestationReport {
+    /// Like `from_endorsed`, but also rejects reports whose signing cert key is weaker than the
+    /// policy allows
+    pub fn from_endorsed_with_key_policy(
+        report: &EndorsedAttestationReport,
+        policy: &AttestationPolicy,
+    ) -> Result<Self, NodeAuthResult> {
+        verify_signing_key(&report.signing_cert, policy)?;
+
+        Self::from_endorsed(report).map_err(|_| NodeAuthResult::InvalidCert)
+    }
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use crate::registration::cert::get_ias_auth_config;
+    use crate::registration::report::tests::endorsed_report_v4;
+
+    use super::*;
+
+    fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut element = vec![tag];
+        if content.len() < 0x80 {
+            element.push(content.len() as u8);
+        } else {
+            element.extend_from_slice(&[0x82, (content.len() >> 8) as u8, content.len() as u8]);
+        }
+        element.extend_from_slice(content);
+
+        element
+    }
+
+    /// A certificate that only has the fields needed to find its public key
+    fn synthetic_cert(algorithm: &[u8], public_key: &[u8]) -> Vec<u8> {
+        let subject_public_key_info = der(
+            0x30,
+            &[der(0x30, algorithm), der(0x03, public_key)].concat(),
+        );
+        let tbs_certificate = [
+            der(0xa0, &der(0x02, &[2])),
+            der(0x02, &[1]),
+            der(0x30, &[]),
+            der(0x30, &[]),
+            der(0x30, &[]),
+            der(0x30, &[]),
+            subject_public_key_info,
+        ]
+        .concat();
+
+        der(0x30, &der(0x30, &tbs_certificate))
+    }
+
+    fn synthetic_rsa_cert(bits: usize) -> Vec<u8> {
+        // a leading zero keeps the modulus positive
+        let mut modulus = vec![0u8; bits / 8 + 1];
+        modulus[1] = 0x80;
+        let rsa_public_key = der(0x30, &[der(0x02, &modulus), der(0x02, &[1, 0, 1])].concat());
+
+        synthetic_cert(
+            &[RSA_ENCRYPTION_OID, &[0x05, 0x00]].concat(),
+            &[&[0u8][..], &rsa_public_key].concat(),
+        )
+    }
+
+    pub fn test_signing_key_of_cert() {
+        let endorsed = endorsed_report_v4();
+        assert_eq!(
+            SigningKey::of_cert(&endorsed.signing_cert).unwrap(),
+            SigningKey::Rsa { bits: 2048 }
+        );
+
+        assert_eq!(
+            SigningKey::of_cert(&synthetic_rsa_cert(1024)).unwrap(),
+            SigningKey::Rsa { bits: 1024 }
+        );
+        let ec_cert = synthetic_cert(&[EC_PUBLIC_KEY_OID, P256_OID].concat(), &[0u8; 66]);
+        assert_eq!(
+            SigningKey::of_cert(&ec_cert).unwrap(),
+            SigningKey::Ecdsa { bits: 256 }
+        );
+        assert!(SigningKey::of_cert(&[0x30, 0x82]).is_err());
+    }
+
+    pub fn test_verify_signing_key_minimum() {
+        let policy = AttestationPolicy::default();
+        let endorsed = endorsed_report_v4();
+        assert_eq!(verify_signing_key(&endorsed.signing_cert, &policy), Ok(()));
+
+        assert_eq!(
+            verify_signing_key(&synthetic_rsa_cert(1024), &policy),
+            Err(NodeAuthResult::InvalidCert)
+        );
+        assert_eq!(
+            verify_signing_key(&synthetic_rsa_cert(3072), &policy),
+            Ok(())
+        );
+
+        let policy = AttestationPolicy {
+            min_signing_key_rsa_bits: 3072,
+            ..Default::default()
+        };
+        assert_eq!(
+            verify_signing_key(&endorsed.signing_cert, &policy),
+            Err(NodeAuthResult::InvalidCert)
+        );
+        assert!(matches!(
+            AttestationReport::from_endorsed_with_key_policy(&endorsed, &policy),
+            Err(NodeAuthResult::InvalidCert)
+        ));
+    }
+
+    pub fn test_chain_signature_algorithms() {
+        let endorsed = endorsed_report_v4();
+        let (ias_cert, _) = get_ias_auth_config();
+        assert!(
+            verify_chain_signature_algorithms(&endorsed.signing_cert, &[ias_cert.as_slice()])
+                .is_ok()
+        );
+
+        // an intermediate signed with sha1WithRSAEncryption
+        let sha1_with_rsa = [
+            0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x05, 0x05, 0x00,
+        ];
+        let intermediate = der(
+            0x30,
+            &[
+                der(0x30, &der(0x02, &[1])),
+                der(0x30, &sha1_with_rsa),
+                der(0x03, &[0u8; 257]),
+            ]
+            .concat(),
+        );
+        assert_eq!(cert_signature_algorithm(&intermediate).unwrap(), None);
+        assert!(verify_chain_signature_algorithms(
+            &endorsed.signing_cert,
+            &[intermediate.as_slice(), ias_cert.as_slice()]
+        )
+        .is_err());
+
+        assert!(verify_chain_signature_algorithms(&[0x30, 0x82], &[]).is_err());
+    }
+}
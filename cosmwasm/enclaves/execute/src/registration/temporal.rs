@@ -0,0 +1,326 @@
+//! Checks that the parts of an attestation were valid at the same time.
+//!
+//! Each of the report signing cert and the DCAP collateral is valid for a limited period, and the
+//! report is only coherent if it was produced while all of them were valid.
+
+use log::*;
+
+use super::ca_bundle::split_der_element;
+use super::dcap::TcbInfo;
+use super::report::{AttestationReport, Error};
+
+/// A period of validity, in seconds since epoch (both ends inclusive)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidityWindow {
+    pub not_before: i64,
+    pub not_after: i64,
+}
+
+/// The window a report timestamp fell outside of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemporalViolation {
+    SigningCert,
+    Collateral,
+}
+
+/// How soon a node has to re-attest, relative to a horizon
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReattestUrgency {
+    /// The deadline has passed
+    Overdue,
+    /// The deadline is within the horizon
+    Due,
+    /// The deadline is beyond the horizon
+    Later,
+}
+
+/// An evidence in the re-attestation queue
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReattestEntry<E> {
+    pub evidence: E,
+    /// As returned by `AttestationReport::reattest_deadline`
+    pub deadline: u64,
+    pub urgency: ReattestUrgency,
+}
+
+/// Parse a DER UTCTime or GeneralizedTime element
+fn parse_asn1_time(element: &[u8]) -> Result<i64, Error> {
+    let format = match element.first() {
+        Some(0x17) => "%y%m%d%H%M%SZ",
+        Some(0x18) => "%Y%m%d%H%M%SZ",
+        _ => return Err(Error::ReportParseError),
+    };
+
+    let (_, content, _) = split_der_element(element)?;
+    let time = std::str::from_utf8(content).map_err(|_| Error::ReportParseError)?;
+
+    chrono::NaiveDateTime::parse_from_str(time, format)
+        .map(|time| time.timestamp())
+        .map_err(|e| {
+            warn!("Failed to decode certificate time {}: {}", time, e);
+            Error::TimestampParse(time.to_string())
+        })
+}
+
+impl ValidityWindow {
+    pub fn contains(&self, time: i64) -> bool {
+        self.not_before <= time && time <= self.not_after
+    }
+
+    /// The validity period of a DER encoded X.509 certificate
+    pub fn of_cert(cert_der: &[u8]) -> Result<Self, Error> {
+        let (_, cert, _) = split_der_element(cert_der)?;
+        let (_, tbs_certificate, _) = split_der_element(cert)?;
+
+        // skip the optional version, then the serial number, signature algorithm and issuer
+        let mut rest = tbs_certificate;
+        if rest.first() == Some(&0xa0) {
+            rest = split_der_element(rest)?.2;
+        }
+        for _ in 0..3 {
+            rest = split_der_element(rest)?.2;
+        }
+
+        let (_, validity, _) = split_der_element(rest)?;
+        let (not_before, _, rest) = split_der_element(validity)?;
+        let (not_after, _, _) = split_der_element(rest)?;
+
+        Ok(Self {
+            not_before: parse_asn1_time(not_before)?,
+            not_after: parse_asn1_time(not_after)?,
+        })
+    }
+
+    /// The period from the issue of the TCB info until its next update is due
+    pub fn of_tcb_info(tcb_info: &TcbInfo) -> Self {
+        Self {
+            not_before: tcb_info.issue_date,
+            not_after: tcb_info.next_update,
+        }
+    }
+}
+
+/// Checks that the report was produced while both the signing cert and (for DCAP) the collateral
+/// were valid, i.e. that the windows overlap at the report timestamp
+pub fn verify_temporal_consistency(
+    report_timestamp: i64,
+    signing_cert: &ValidityWindow,
+    collateral: Option<&ValidityWindow>,
+) -> Result<(), TemporalViolation> {
+    if !signing_cert.contains(report_timestamp) {
+        warn!(
+            "Report timestamp {} is outside of the signing cert validity {:?}",
+            report_timestamp, signing_cert
+        );
+        return Err(TemporalViolation::SigningCert);
+    }
+
+    if let Some(collateral) = collateral {
+        if !collateral.contains(report_timestamp) {
+            warn!(
+                "Report timestamp {} is outside of the collateral validity {:?}",
+                report_timestamp, collateral
+            );
+            return Err(TemporalViolation::Collateral);
+        }
+    }
+
+    Ok(())
+}
+
+impl AttestationReport {
+    /// The earliest time (seconds since epoch) the node should re-attest - when the report is no
+    /// longer fresh (older than `max_age`), when the signing cert expires, or (for DCAP) when the
+    /// collateral is due for an update. If that time has already passed, it's `now`
+    pub fn reattest_deadline(
+        &self,
+        now: u64,
+        max_age: u64,
+        signing_cert: &ValidityWindow,
+        collateral: Option<&ValidityWindow>,
+    ) -> u64 {
+        let expiry = |window: &ValidityWindow| window.not_after.max(0) as u64;
+
+        let mut deadline = self.timestamp.saturating_add(max_age);
+        deadline = deadline.min(expiry(signing_cert));
+        if let Some(collateral) = collateral {
+            deadline = deadline.min(expiry(collateral));
+        }
+
+        deadline.max(now)
+    }
+}
+
+/// Orders stored evidences by their re-attestation deadline, nearest first, and buckets them by
+/// whether the deadline has passed at `now` or falls within `horizon` seconds of it. Evidences
+/// with the same deadline keep their relative order
+pub fn reattestation_queue<E>(
+    evidences: impl IntoIterator<Item = (E, u64)>,
+    now: u64,
+    horizon: u64,
+) -> Vec<ReattestEntry<E>> {
+    let mut queue: Vec<_> = evidences
+        .into_iter()
+        .map(|(evidence, deadline)| {
+            let urgency = if deadline <= now {
+                ReattestUrgency::Overdue
+            } else if deadline - now <= horizon {
+                ReattestUrgency::Due
+            } else {
+                ReattestUrgency::Later
+            };
+
+            ReattestEntry {
+                evidence,
+                deadline,
+                urgency,
+            }
+        })
+        .collect();
+    queue.sort_by_key(|entry| entry.deadline);
+
+    queue
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use crate::registration::dcap::tests::dcap_collateral_fixture;
+    use crate::registration::dcap::DcapCollateral;
+    use crate::registration::report::tests::{endorsed_report_v4, mock_attestation_report};
+
+    use super::*;
+
+    fn signing_cert_window() -> ValidityWindow {
+        ValidityWindow::of_cert(&endorsed_report_v4().signing_cert).unwrap()
+    }
+
+    pub fn test_validity_window_of_cert() {
+        let window = signing_cert_window();
+
+        // Nov 22 09:36:58 2016 GMT - Nov 20 09:36:58 2026 GMT
+        assert_eq!(window.not_before, 1479807418);
+        assert_eq!(window.not_after, 1795167418);
+    }
+
+    pub fn test_temporal_consistency() {
+        let signing_cert = signing_cert_window();
+        let collateral = DcapCollateral::parse_from(&dcap_collateral_fixture()).unwrap();
+        let collateral = ValidityWindow::of_tcb_info(&collateral.tcb_info().unwrap());
+
+        // 2021-04-01, while both were valid
+        assert_eq!(
+            verify_temporal_consistency(1617235200, &signing_cert, Some(&collateral)),
+            Ok(())
+        );
+
+        // 2016-01-01, before the signing cert was issued
+        assert_eq!(
+            verify_temporal_consistency(1451606400, &signing_cert, Some(&collateral)),
+            Err(TemporalViolation::SigningCert)
+        );
+
+        // after the signing cert expired
+        assert_eq!(
+            verify_temporal_consistency(1795167419, &signing_cert, None),
+            Err(TemporalViolation::SigningCert)
+        );
+
+        // 2020-08-20, before the collateral was issued
+        assert_eq!(
+            verify_temporal_consistency(1597924642, &signing_cert, Some(&collateral)),
+            Err(TemporalViolation::Collateral)
+        );
+        assert_eq!(
+            verify_temporal_consistency(1597924642, &signing_cert, None),
+            Ok(())
+        );
+    }
+
+    pub fn test_reattest_deadline() {
+        let report = mock_attestation_report();
+        let issued = report.timestamp;
+        let now = issued + 10;
+        let window = |not_after: u64| ValidityWindow {
+            not_before: 0,
+            not_after: not_after as i64,
+        };
+
+        // the report gets stale first
+        assert_eq!(
+            report.reattest_deadline(now, 3600, &window(issued + 7200), None),
+            issued + 3600
+        );
+
+        // the signing cert expires first
+        assert_eq!(
+            report.reattest_deadline(
+                now,
+                3600,
+                &window(issued + 600),
+                Some(&window(issued + 7200))
+            ),
+            issued + 600
+        );
+
+        // the collateral is due for an update first
+        assert_eq!(
+            report.reattest_deadline(
+                now,
+                3600,
+                &window(issued + 7200),
+                Some(&window(issued + 300))
+            ),
+            issued + 300
+        );
+
+        // already overdue
+        assert_eq!(
+            report.reattest_deadline(issued + 5000, 3600, &window(issued + 7200), None),
+            issued + 5000
+        );
+    }
+
+    pub fn test_reattestation_queue() {
+        let now = 10_000;
+        let evidences = vec![
+            ("a", now + 5000),
+            ("b", now - 10),
+            ("c", now + 60),
+            ("d", now + 60),
+        ];
+
+        let queue = reattestation_queue(evidences, now, 3600);
+        let order: Vec<_> = queue.iter().map(|entry| entry.evidence).collect();
+        assert_eq!(order, vec!["b", "c", "d", "a"]);
+
+        let urgencies: Vec<_> = queue.iter().map(|entry| entry.urgency).collect();
+        assert_eq!(
+            urgencies,
+            vec![
+                ReattestUrgency::Overdue,
+                ReattestUrgency::Due,
+                ReattestUrgency::Due,
+                ReattestUrgency::Later
+            ]
+        );
+        assert_eq!(queue[0].deadline, now - 10);
+
+        // deadlines as computed from the reports themselves
+        let report = mock_attestation_report();
+        let issued = report.timestamp;
+        let cert = |not_after: u64| ValidityWindow {
+            not_before: 0,
+            not_after: not_after as i64,
+        };
+        let stale = report.reattest_deadline(issued + 10, 3600, &cert(issued + 7200), None);
+        let expiring = report.reattest_deadline(issued + 10, 3600, &cert(issued + 600), None);
+        let queue = reattestation_queue(vec![(1, stale), (2, expiring)], issued + 10, 60);
+        assert_eq!(queue[0].evidence, 2);
+        assert_eq!(queue[1].evidence, 1);
+        assert!(queue
+            .iter()
+            .all(|entry| entry.urgency == ReattestUrgency::Later));
+
+        assert!(reattestation_queue(Vec::<((), u64)>::new(), now, 3600).is_empty());
+    }
+}
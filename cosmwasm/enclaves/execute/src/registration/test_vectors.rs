@@ -0,0 +1,311 @@
+//! Deterministic test vectors for the quote parser.
+//!
+//! Each vector is the input bytes of a quote body, and the quote it is expected to parse to (or
+//! `None` if it should be rejected). New vectors can be produced with `emit_quote_vector`, whose
+//! output is meant to be pasted into `QUOTE_VECTORS` once reviewed.
+
+use std::convert::TryFrom;
+use std::fmt::Write;
+
+use uuid::Uuid;
+
+use super::report::{
+    Error, SgxEcdsaQuoteAkType, SgxEnclaveReport, SgxEpidQuoteSigType, SgxQuote, SgxQuoteVersion,
+    TeeType,
+};
+
+pub struct QuoteTestVector {
+    pub name: &'static str,
+    /// The quote body, as hex
+    pub input_hex: &'static str,
+    /// The expected outcome of parsing the input - `None` if it should fail to parse
+    pub expected: Option<fn() -> SgxQuote>,
+}
+
+fn from_hex<const N: usize>(hex: &str) -> [u8; N] {
+    <[u8; N]>::try_from(hex::decode(hex).unwrap().as_slice()).unwrap()
+}
+
+pub const QUOTE_VECTORS: &[QuoteTestVector] = &[
+    QuoteTestVector {
+        name: "ias_epid_v2",
+        input_hex: "020001002f0b00000a00090000000000ad73450388a677623f82219675b6024c00000000000000000000000000000000050e0205ff8000000000000000000000000000000000000000000000000000000000000000000000000000000000000007000000000000000700000000000000330aa910a3776780e20026ae3dac07af0e5a931f84f1f8307dbb85902f586953000000000000000000000000000000000000000000000000000000000000000083d719e77deaca1470f6baf62a4d774303c899db69020f9c70ee1dfc08c7ce9e00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000d8218f64d0c56609d20352e14b7ffd9b1881c07cf8cef67bc2edf833ad079d780dd65b8745788ea87be4422dd5775d80f59fa9446632133b33cf2d60f1ba51b3",
+        expected: Some(|| SgxQuote {
+            version: SgxQuoteVersion::V2(SgxEpidQuoteSigType::Linkable),
+            gid: 0xb2f,
+            isv_svn_qe: 0xa,
+            isv_svn_pce: 0x9,
+            qe_vendor_id: Uuid::from_bytes(from_hex("00000000ad73450388a677623f822196")),
+            user_data: from_hex("75b6024c00000000000000000000000000000000"),
+            isv_enclave_report: SgxEnclaveReport {
+                cpu_svn: from_hex("050e0205ff8000000000000000000000"),
+                misc_select: 0x0,
+                attributes: from_hex("07000000000000000700000000000000"),
+                mr_enclave: from_hex("330aa910a3776780e20026ae3dac07af0e5a931f84f1f8307dbb85902f586953"),
+                mr_signer: from_hex("83d719e77deaca1470f6baf62a4d774303c899db69020f9c70ee1dfc08c7ce9e"),
+                isv_prod_id: 0x0,
+                isv_svn: 0x0,
+                report_data: from_hex("d8218f64d0c56609d20352e14b7ffd9b1881c07cf8cef67bc2edf833ad079d780dd65b8745788ea87be4422dd5775d80f59fa9446632133b33cf2d60f1ba51b3"),
+            },
+        }),
+    },
+    QuoteTestVector {
+        name: "dcap_ecdsa_v3_header",
+        input_hex: "03000200000000000a000f00939a7233f79c4ca9940a0db3957f0607266bbd8ea7fba9b3d727d1e791a3aa290000000015150b07ff800e00000000000000000000000000000000000000000000000000000000000000000000000000000000000700000000000000070000000000000015abbb64470ce2f74791479a8121a08b2fb146b9467a190d3e495925dcdd1cf7000000000000000000000000000000000000000000000000000000000000000083d719e77deaca1470f6baf62a4d774303c899db69020f9c70ee1dfc08c7ce9e000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001688f8e17f2d27d528c27c621a20061c0ba325e6c6608074a2dffd0450e521470000000000000000000000000000000000000000000000000000000000000000",
+        expected: Some(|| SgxQuote {
+            version: SgxQuoteVersion::V3(SgxEcdsaQuoteAkType::P256_256),
+            gid: 0x0,
+            isv_svn_qe: 0xa,
+            isv_svn_pce: 0xf,
+            qe_vendor_id: Uuid::from_bytes(from_hex("939a7233f79c4ca9940a0db3957f0607")),
+            user_data: from_hex("266bbd8ea7fba9b3d727d1e791a3aa2900000000"),
+            isv_enclave_report: SgxEnclaveReport {
+                cpu_svn: from_hex("15150b07ff800e000000000000000000"),
+                misc_select: 0x0,
+                attributes: from_hex("07000000000000000700000000000000"),
+                mr_enclave: from_hex("15abbb64470ce2f74791479a8121a08b2fb146b9467a190d3e495925dcdd1cf7"),
+                mr_signer: from_hex("83d719e77deaca1470f6baf62a4d774303c899db69020f9c70ee1dfc08c7ce9e"),
+                isv_prod_id: 0x0,
+                isv_svn: 0x0,
+                report_data: from_hex("1688f8e17f2d27d528c27c621a20061c0ba325e6c6608074a2dffd0450e521470000000000000000000000000000000000000000000000000000000000000000"),
+            },
+        }),
+    },
+    QuoteTestVector {
+        name: "minimum_field_values",
+        input_hex: "010000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+        expected: Some(|| SgxQuote {
+            version: SgxQuoteVersion::V1(SgxEpidQuoteSigType::Unlinkable),
+            gid: 0x0,
+            isv_svn_qe: 0x0,
+            isv_svn_pce: 0x0,
+            qe_vendor_id: Uuid::from_bytes(from_hex("00000000000000000000000000000000")),
+            user_data: from_hex("0000000000000000000000000000000000000000"),
+            isv_enclave_report: SgxEnclaveReport {
+                cpu_svn: from_hex("00000000000000000000000000000000"),
+                misc_select: 0x0,
+                attributes: from_hex("00000000000000000000000000000000"),
+                mr_enclave: from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
+                mr_signer: from_hex("0000000000000000000000000000000000000000000000000000000000000000"),
+                isv_prod_id: 0x0,
+                isv_svn: 0x0,
+                report_data: from_hex("00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000"),
+            },
+        }),
+    },
+    QuoteTestVector {
+        name: "maximum_field_values",
+        input_hex: "02000100ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff00000000000000000000000000000000000000000000000000000000ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff0000000000000000000000000000000000000000000000000000000000000000ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000ffffffff000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+        expected: Some(|| SgxQuote {
+            version: SgxQuoteVersion::V2(SgxEpidQuoteSigType::Linkable),
+            gid: 0xffffffff,
+            isv_svn_qe: 0xffff,
+            isv_svn_pce: 0xffff,
+            qe_vendor_id: Uuid::from_bytes(from_hex("ffffffffffffffffffffffffffffffff")),
+            user_data: from_hex("ffffffffffffffffffffffffffffffffffffffff"),
+            isv_enclave_report: SgxEnclaveReport {
+                cpu_svn: from_hex("ffffffffffffffffffffffffffffffff"),
+                misc_select: 0xffffffff,
+                attributes: from_hex("ffffffffffffffffffffffffffffffff"),
+                mr_enclave: from_hex("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"),
+                mr_signer: from_hex("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"),
+                isv_prod_id: 0xffff,
+                isv_svn: 0xffff,
+                report_data: from_hex("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"),
+            },
+        }),
+    },
+    QuoteTestVector {
+        name: "truncated",
+        input_hex: "0100000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+        expected: None,
+    },
+    QuoteTestVector {
+        name: "trailing_byte",
+        input_hex: "01000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+        expected: None,
+    },
+    QuoteTestVector {
+        name: "unknown_version",
+        input_hex: "090000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+        expected: None,
+    },
+    QuoteTestVector {
+        name: "unknown_epid_signature_type",
+        input_hex: "020002000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+        expected: None,
+    },
+    QuoteTestVector {
+        name: "unknown_ecdsa_key_type",
+        input_hex: "030004000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+        expected: None,
+    },
+];
+
+fn version_literal(version: &SgxQuoteVersion) -> &'static str {
+    match version {
+        SgxQuoteVersion::V1(SgxEpidQuoteSigType::Unlinkable) => {
+            "SgxQuoteVersion::V1(SgxEpidQuoteSigType::Unlinkable)"
+        }
+        SgxQuoteVersion::V1(SgxEpidQuoteSigType::Linkable) => {
+            "SgxQuoteVersion::V1(SgxEpidQuoteSigType::Linkable)"
+        }
+        SgxQuoteVersion::V2(SgxEpidQuoteSigType::Unlinkable) => {
+            "SgxQuoteVersion::V2(SgxEpidQuoteSigType::Unlinkable)"
+        }
+        SgxQuoteVersion::V2(SgxEpidQuoteSigType::Linkable) => {
+            "SgxQuoteVersion::V2(SgxEpidQuoteSigType::Linkable)"
+        }
+        SgxQuoteVersion::V3(SgxEcdsaQuoteAkType::P256_256) => {
+            "SgxQuoteVersion::V3(SgxEcdsaQuoteAkType::P256_256)"
+        }
+        SgxQuoteVersion::V3(SgxEcdsaQuoteAkType::P384_384) => {
+            "SgxQuoteVersion::V3(SgxEcdsaQuoteAkType::P384_384)"
+        }
+        SgxQuoteVersion::V4(SgxEcdsaQuoteAkType::P256_256, TeeType::Sgx) => {
+            "SgxQuoteVersion::V4(SgxEcdsaQuoteAkType::P256_256, TeeType::Sgx)"
+        }
+        SgxQuoteVersion::V4(SgxEcdsaQuoteAkType::P256_256, TeeType::Tdx) => {
+            "SgxQuoteVersion::V4(SgxEcdsaQuoteAkType::P256_256, TeeType::Tdx)"
+        }
+        SgxQuoteVersion::V4(SgxEcdsaQuoteAkType::P384_384, TeeType::Sgx) => {
+            "SgxQuoteVersion::V4(SgxEcdsaQuoteAkType::P384_384, TeeType::Sgx)"
+        }
+        SgxQuoteVersion::V4(SgxEcdsaQuoteAkType::P384_384, TeeType::Tdx) => {
+            "SgxQuoteVersion::V4(SgxEcdsaQuoteAkType::P384_384, TeeType::Tdx)"
+        }
+    }
+}
+
+/// Generates the source of a vector for `input`, in the format of `QUOTE_VECTORS`. The expected
+/// quote is whatever the parser currently makes of the input, so it must be checked by hand
+/// before the vector is committed.
+pub fn emit_quote_vector(name: &str, input: &[u8]) -> String {
+    let mut out = String::new();
+    writeln!(out, "QuoteTestVector {{").unwrap();
+    writeln!(out, "    name: \"{}\",", name).unwrap();
+    writeln!(out, "    input_hex: \"{}\",", hex::encode(input)).unwrap();
+
+    let quote = match SgxQuote::parse_from(input) {
+        Ok(quote) => quote,
+        Err(_) => {
+            writeln!(out, "    expected: None,").unwrap();
+            writeln!(out, "}},").unwrap();
+            return out;
+        }
+    };
+    let report = &quote.isv_enclave_report;
+
+    writeln!(out, "    expected: Some(|| SgxQuote {{").unwrap();
+    writeln!(out, "        version: {},", version_literal(&quote.version)).unwrap();
+    writeln!(out, "        gid: {:#x},", quote.gid).unwrap();
+    writeln!(out, "        isv_svn_qe: {:#x},", quote.isv_svn_qe).unwrap();
+    writeln!(out, "        isv_svn_pce: {:#x},", quote.isv_svn_pce).unwrap();
+    writeln!(
+        out,
+        "        qe_vendor_id: Uuid::from_bytes(from_hex(\"{}\")),",
+        hex::encode(quote.qe_vendor_id.as_bytes())
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "        user_data: from_hex(\"{}\"),",
+        hex::encode(quote.user_data)
+    )
+    .unwrap();
+    writeln!(out, "        isv_enclave_report: SgxEnclaveReport {{").unwrap();
+    writeln!(
+        out,
+        "            cpu_svn: from_hex(\"{}\"),",
+        hex::encode(report.cpu_svn)
+    )
+    .unwrap();
+    writeln!(out, "            misc_select: {:#x},", report.misc_select).unwrap();
+    writeln!(
+        out,
+        "            attributes: from_hex(\"{}\"),",
+        hex::encode(report.attributes)
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "            mr_enclave: from_hex(\"{}\"),",
+        hex::encode(report.mr_enclave)
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "            mr_signer: from_hex(\"{}\"),",
+        hex::encode(report.mr_signer)
+    )
+    .unwrap();
+    writeln!(out, "            isv_prod_id: {:#x},", report.isv_prod_id).unwrap();
+    writeln!(out, "            isv_svn: {:#x},", report.isv_svn).unwrap();
+    writeln!(
+        out,
+        "            report_data: from_hex(\"{}\"),",
+        hex::encode(&report.report_data[..])
+    )
+    .unwrap();
+    writeln!(out, "        }},").unwrap();
+    writeln!(out, "    }}),").unwrap();
+    writeln!(out, "}},").unwrap();
+
+    out
+}
+
+/// Parses the input of `vector` and checks the outcome is the expected one
+pub fn check_quote_vector(vector: &QuoteTestVector) -> Result<(), String> {
+    let input = hex::decode(vector.input_hex).map_err(|e| format!("bad input hex: {}", e))?;
+    let parsed: Result<SgxQuote, Error> = SgxQuote::parse_from(&input);
+
+    match (parsed, vector.expected) {
+        (Ok(quote), Some(expected)) => {
+            if quote != expected() {
+                return Err(format!("parsed to {:?}", quote));
+            }
+            if quote.to_bytes() != input {
+                return Err("doesn't serialize back to the input".to_string());
+            }
+            Ok(())
+        }
+        (Err(_), None) => Ok(()),
+        (Ok(quote), None) => Err(format!("expected an error, parsed to {:?}", quote)),
+        (Err(e), Some(_)) => Err(format!("failed to parse: {:?}", e)),
+    }
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use super::*;
+
+    pub fn test_quote_vectors() {
+        for vector in QUOTE_VECTORS {
+            if let Err(e) = check_quote_vector(vector) {
+                panic!("Quote vector {} failed: {}", vector.name, e);
+            }
+        }
+    }
+
+    pub fn test_emit_quote_vector() {
+        for vector in QUOTE_VECTORS {
+            let input = hex::decode(vector.input_hex).unwrap();
+            let emitted = emit_quote_vector(vector.name, &input);
+
+            assert!(emitted.contains(vector.input_hex));
+            assert_eq!(
+                emitted.contains("expected: None"),
+                vector.expected.is_none(),
+                "{}",
+                vector.name
+            );
+        }
+
+        let emitted = emit_quote_vector(
+            "minimum_field_values",
+            &hex::decode(QUOTE_VECTORS[2].input_hex).unwrap(),
+        );
+        assert!(emitted.contains("version: SgxQuoteVersion::V1(SgxEpidQuoteSigType::Unlinkable),"));
+        assert!(emitted.contains("gid: 0x0,"));
+    }
+}
@@ -0,0 +1,196 @@
+//! Spans of attestation verifications, for distributed tracing pipelines.
+//!
+//! The enclave can't depend on a tracing SDK, so verifications are described as plain spans with
+//! OpenTelemetry style attributes, and handed to a `Tracer` the host application implements to
+//! forward them to its collector.
+
+use enclave_ffi_types::NodeAuthResult;
+
+use super::policy::AttestationPolicy;
+use super::rejection::as_code;
+use super::report::AttestationReport;
+
+/// Name of the span of `evaluate_traced`
+pub const VERIFICATION_SPAN_NAME: &str = "sgx.attestation.verify";
+
+/// The value of a span attribute
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValue {
+    String(String),
+    Int(i64),
+    Bool(bool),
+}
+
+/// A finished span
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub name: &'static str,
+    /// Start and end of the span, on the clock of the tracer
+    pub start_time: u64,
+    pub end_time: u64,
+    pub attributes: Vec<(&'static str, AttributeValue)>,
+}
+
+impl Span {
+    pub fn duration(&self) -> u64 {
+        self.end_time.saturating_sub(self.start_time)
+    }
+
+    pub fn attribute(&self, key: &str) -> Option<&AttributeValue> {
+        self.attributes
+            .iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| value)
+    }
+}
+
+/// Receives the spans of verifications
+pub trait Tracer {
+    /// The current time, in whatever unit and epoch the tracer's backend expects (e.g.
+    /// nanoseconds since epoch for OpenTelemetry)
+    fn now(&self) -> u64;
+
+    /// Called synchronously at the end of every verification
+    fn export(&self, span: Span);
+}
+
+/// Evaluate the report against the policy inside a span, which is exported to `tracer` with the
+/// quote status, the measurement and the verdict as attributes
+pub fn evaluate_traced(
+    report: &AttestationReport,
+    policy: &AttestationPolicy,
+    tracer: &dyn Tracer,
+) -> Result<(), NodeAuthResult> {
+    let start_time = tracer.now();
+    let res = report.evaluate(policy);
+    let end_time = tracer.now();
+
+    let verdict = match res {
+        Ok(()) => NodeAuthResult::Success,
+        Err(e) => e,
+    };
+    let enclave_report = &report.sgx_quote_body.isv_enclave_report;
+
+    tracer.export(Span {
+        name: VERIFICATION_SPAN_NAME,
+        start_time,
+        end_time,
+        attributes: vec![
+            (
+                "sgx.quote_status",
+                AttributeValue::String(report.sgx_quote_status.to_string()),
+            ),
+            (
+                "sgx.mr_enclave",
+                AttributeValue::String(hex::encode(enclave_report.mr_enclave)),
+            ),
+            (
+                "sgx.mr_signer",
+                AttributeValue::String(hex::encode(enclave_report.mr_signer)),
+            ),
+            (
+                "sgx.verdict",
+                AttributeValue::String(format!("{:?}", verdict)),
+            ),
+            (
+                "sgx.verdict_code",
+                AttributeValue::Int(as_code(&verdict).code as i64),
+            ),
+            ("sgx.accepted", AttributeValue::Bool(res.is_ok())),
+        ],
+    });
+
+    res
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use std::cell::{Cell, RefCell};
+
+    use crate::registration::report::tests::mock_attestation_report;
+    use crate::registration::report::SgxQuoteStatus;
+
+    use super::*;
+
+    /// Keeps the exported spans, with a clock that advances by 10 on every reading
+    #[derive(Default)]
+    struct RecordingTracer {
+        clock: Cell<u64>,
+        spans: RefCell<Vec<Span>>,
+    }
+
+    impl Tracer for RecordingTracer {
+        fn now(&self) -> u64 {
+            self.clock.set(self.clock.get() + 10);
+            self.clock.get()
+        }
+
+        fn export(&self, span: Span) {
+            self.spans.borrow_mut().push(span);
+        }
+    }
+
+    pub fn test_evaluate_traced() {
+        let tracer = RecordingTracer::default();
+        let policy = AttestationPolicy {
+            accepted_statuses: vec![SgxQuoteStatus::OK],
+            ..Default::default()
+        };
+
+        let mut report = mock_attestation_report();
+        report.sgx_quote_status = SgxQuoteStatus::OK;
+        assert_eq!(evaluate_traced(&report, &policy, &tracer), Ok(()));
+
+        let rejected = mock_attestation_report();
+        assert_eq!(
+            evaluate_traced(&rejected, &policy, &tracer),
+            Err(NodeAuthResult::GroupOutOfDate)
+        );
+
+        let spans = tracer.spans.borrow();
+        assert_eq!(spans.len(), 2);
+
+        let span = &spans[0];
+        assert_eq!(span.name, VERIFICATION_SPAN_NAME);
+        assert_eq!((span.start_time, span.end_time), (10, 20));
+        assert_eq!(span.duration(), 10);
+        assert_eq!(
+            span.attribute("sgx.quote_status"),
+            Some(&AttributeValue::String("OK".to_string()))
+        );
+        assert_eq!(
+            span.attribute("sgx.mr_enclave"),
+            Some(&AttributeValue::String(hex::encode(
+                report.sgx_quote_body.isv_enclave_report.mr_enclave
+            )))
+        );
+        assert_eq!(
+            span.attribute("sgx.verdict"),
+            Some(&AttributeValue::String("Success".to_string()))
+        );
+        assert_eq!(
+            span.attribute("sgx.accepted"),
+            Some(&AttributeValue::Bool(true))
+        );
+
+        let span = &spans[1];
+        assert_eq!(span.start_time, 30);
+        // the status as IAS spells it, not its Rust name
+        assert_eq!(
+            span.attribute("sgx.quote_status"),
+            Some(&AttributeValue::String("GROUP_OUT_OF_DATE".to_string()))
+        );
+        assert_eq!(
+            span.attribute("sgx.verdict"),
+            Some(&AttributeValue::String("GroupOutOfDate".to_string()))
+        );
+        assert_eq!(
+            span.attribute("sgx.verdict_code"),
+            Some(&AttributeValue::Int(101))
+        );
+        assert_eq!(
+            span.attribute("sgx.accepted"),
+            Some(&AttributeValue::Bool(false))
+        );
+    }
+}
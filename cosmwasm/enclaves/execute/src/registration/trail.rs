@@ -0,0 +1,183 @@
+//! A record of how a verification reached its verdict, for audits and disputes.
+//!
+//! The trail holds the hash of the input and the digest of the policy, rather than the input and
+//! the policy themselves, along with the outcome of every check. It depends on nothing but those
+//! two, so anyone holding them can re-run the verification and compare the trails.
+
+use serde::Serialize;
+
+use enclave_crypto::sha_256;
+use enclave_ffi_types::NodeAuthResult;
+
+use super::policy::AttestationPolicy;
+use super::rejection::as_code;
+use super::report::AttestationReport;
+
+/// The outcome of one check of the verification
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CheckRecord {
+    pub check: &'static str,
+    pub passed: bool,
+    /// The value the check was made on, or why it couldn't be made
+    pub detail: String,
+}
+
+/// Every input and decision of a verification, in the order they were made
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VerificationTrail {
+    /// SHA-256 of the verified certificate, as hex
+    pub input_hash: String,
+    /// `AttestationPolicy::digest` of the policy, as hex
+    pub policy_digest: String,
+    pub checks: Vec<CheckRecord>,
+    /// Stable code and name of the verdict (see `rejection`)
+    pub verdict_code: u16,
+    pub verdict: &'static str,
+}
+
+impl VerificationTrail {
+    fn new(cert: &[u8], policy: &AttestationPolicy) -> Self {
+        Self {
+            input_hash: hex::encode(sha_256(cert)),
+            policy_digest: hex::encode(policy.digest()),
+            checks: vec![],
+            verdict_code: 0,
+            verdict: "",
+        }
+    }
+
+    fn record(&mut self, check: &'static str, passed: bool, detail: String) {
+        self.checks.push(CheckRecord {
+            check,
+            passed,
+            detail,
+        });
+    }
+
+    fn conclude(mut self, verdict: Result<(), NodeAuthResult>) -> Self {
+        let code = as_code(&verdict.err().unwrap_or(NodeAuthResult::Success));
+        self.verdict_code = code.code;
+        self.verdict = code.name;
+
+        self
+    }
+
+    pub fn is_accepted(&self) -> bool {
+        self.verdict_code == 0
+    }
+
+    pub fn to_json(&self) -> String {
+        // the trail only holds strings, numbers and booleans, which always serialize
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Verifies the report in `cert` against `policy` like `AttestationReport::from_cert_with_verdict`,
+/// recording each check along the way
+pub fn verify_with_trail(cert: &[u8], policy: &AttestationPolicy) -> VerificationTrail {
+    let mut trail = VerificationTrail::new(cert, policy);
+
+    let report = match AttestationReport::from_cert(cert) {
+        Ok(report) => {
+            trail.record("signature", true, String::new());
+            report
+        }
+        Err(e) => {
+            trail.record("signature", false, e.to_string());
+            return trail.conclude(Err(NodeAuthResult::InvalidCert));
+        }
+    };
+
+    let context = report.verification_context(policy);
+    trail.record(
+        "attestation_type",
+        context.attestation_type_accepted,
+        "epid".to_string(),
+    );
+    trail.record(
+        "mr_enclave",
+        context.mr_enclave != [0u8; 32],
+        hex::encode(context.mr_enclave),
+    );
+    trail.record(
+        "quote_status",
+        context.status_accepted,
+        context.quote_status.to_string(),
+    );
+    trail.record(
+        "advisories_well_formed",
+        context.malformed_advisories.is_empty() || !context.reject_malformed_advisories,
+        context.malformed_advisories.join(","),
+    );
+    trail.record(
+        "advisories_whitelisted",
+        context.vulnerable_advisories.is_empty(),
+        context.vulnerable_advisories.join(","),
+    );
+    trail.record(
+        "advisories_not_critical",
+        context.critical_advisories.is_empty() || !context.reject_critical_advisories,
+        context.critical_advisories.join(","),
+    );
+    trail.record(
+        "tcb_eval_data_number",
+        context.tcb_eval_data_number_accepted,
+        context.tcb_eval_data_number.to_string(),
+    );
+    trail.record(
+        "report_data_tail",
+        context.report_data_tail_zero || !context.require_zero_report_data_tail,
+        context.report_data_tail_zero.to_string(),
+    );
+    trail.record(
+        "report_data_version",
+        context.report_data_version_accepted,
+        context.report_data_version.to_string(),
+    );
+
+    trail.conclude(context.result())
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+    use crate::registration::report::tests::tls_ra_cert_der_v4;
+    use crate::registration::report::SgxQuoteStatus;
+
+    use super::*;
+
+    pub fn test_verification_trail_is_reproducible() {
+        let cert = tls_ra_cert_der_v4();
+        let policy = AttestationPolicy {
+            accepted_statuses: vec![SgxQuoteStatus::OK],
+            ..Default::default()
+        };
+
+        let trail = verify_with_trail(&cert, &policy);
+        assert_eq!(trail.to_json(), verify_with_trail(&cert, &policy).to_json());
+
+        assert_eq!(trail.input_hash, hex::encode(sha_256(&cert)));
+        assert_eq!(trail.checks[0].check, "signature");
+        assert!(trail.checks[0].passed);
+        assert!(!trail.is_accepted());
+        assert_eq!(
+            trail.verdict_code,
+            as_code(&NodeAuthResult::GroupOutOfDate).code
+        );
+        let quote_status = trail
+            .checks
+            .iter()
+            .find(|record| record.check == "quote_status")
+            .unwrap();
+        assert_eq!(quote_status.detail, "GROUP_OUT_OF_DATE");
+
+        // another policy leaves a different trail for the same input
+        let other = verify_with_trail(&cert, &AttestationPolicy::default());
+        assert_eq!(other.input_hash, trail.input_hash);
+        assert_ne!(other.policy_digest, trail.policy_digest);
+
+        let garbage = verify_with_trail(&[0x30, 0x82], &policy);
+        assert_eq!(garbage.checks.len(), 1);
+        assert!(!garbage.checks[0].passed);
+        assert_eq!(garbage.verdict, "invalid_cert");
+    }
+}
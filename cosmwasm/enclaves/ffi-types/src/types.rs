@@ -177,7 +177,7 @@ pub enum EnclaveError {
 /// enclave while authenticating a new node in the network.
 /// cbindgen:prefix-with-name
 #[repr(C)]
-#[derive(Debug, Display, PartialEq, Eq)]
+#[derive(Debug, Display, PartialEq, Eq, Clone, Copy)]
 pub enum NodeAuthResult {
     #[display(fmt = "Enclave quote is valid")]
     Success,
@@ -223,6 +223,26 @@ pub enum NodeAuthResult {
         fmt = "Unexpected panic during node authentication. Certificate may be malformed or invalid"
     )]
     Panic,
+    #[display(fmt = "The attestation type of the platform is not accepted by the network")]
+    AttestationTypeNotAccepted,
+    #[display(fmt = "Enclave quote status was OUT_OF_DATE which is not allowed")]
+    OutOfDate,
+    #[display(
+        fmt = "Enclave quote status was OUT_OF_DATE_CONFIGURATION_NEEDED which is not allowed"
+    )]
+    OutOfDateConfigurationNeeded,
+    #[display(fmt = "Enclave quote status was SW_HARDENING_NEEDED which is not allowed")]
+    SwHardeningNeeded,
+    #[display(fmt = "Enclave quote status is unknown")]
+    UnknownQuoteStatus,
+    #[display(fmt = "The platform is affected by a critical advisory that must be mitigated")]
+    CriticalAdvisory,
+    #[display(fmt = "The layout version of the enclave's report data is not supported")]
+    UnsupportedReportDataVersion,
+    #[display(fmt = "The attestation report has malformed or duplicate advisory IDs")]
+    MalformedAdvisoryIds,
+    #[display(fmt = "The platform made too many attestation attempts, try again later")]
+    RateLimited,
 }
 
 /// This type represents the possible error conditions that can be encountered in the